@@ -1,13 +1,27 @@
+use std::time::Duration;
+
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use client::http_client::HttpClient;
 use shared::{
     commands::{
+        dead_letter_command::DeadLetterCommand,
         fetch_command::{FetchCommand, FetchPartitionCommand, FetchTopicCommand},
+        import_topic_command::ImportTopicCommand,
         produce_command::ProduceCommand,
+        receive_command::ReceiveCommand,
     },
     consts::DEFAULT_PORT,
-    data::{encoding::Encoding, identifier::Identifier, offset_selection::OffsetSelection},
+    data::{
+        acl::{AclOperation, AclPattern},
+        encoding::Encoding,
+        identifier::Identifier,
+        lease::Lease,
+        offset_selection::OffsetSelection,
+        quota::{Quota, QuotaEntity},
+        timestamp::Timestamp,
+        topic_config::{CleanupPolicy, TopicConfig, TopicConfigKey},
+    },
     logging::set_up_logging,
 };
 use tracing::info;
@@ -18,6 +32,10 @@ struct Cli {
     #[clap(subcommand)]
     command: Command,
 
+    /// Bearer token used to authenticate with the server
+    #[arg(long, env = "PIGEON_TOKEN", global = true)]
+    token: Option<String>,
+
     #[arg(long, short, action = clap::ArgAction::Count)]
     verbose: u8,
 
@@ -31,11 +49,59 @@ enum Command {
         #[clap(subcommand)]
         subcommand: TopicCommand,
     },
+    Tokens {
+        #[clap(subcommand)]
+        subcommand: TokenCommand,
+    },
+    Acls {
+        #[clap(subcommand)]
+        subcommand: AclCommand,
+    },
+    Quotas {
+        #[clap(subcommand)]
+        subcommand: QuotaCommand,
+    },
+    /// Consume a topic as a work queue
+    Queue {
+        #[clap(subcommand)]
+        subcommand: QueueCommand,
+    },
+    Trash {
+        #[clap(subcommand)]
+        subcommand: TrashCommand,
+    },
+    Segments {
+        #[clap(subcommand)]
+        subcommand: SegmentCommand,
+    },
+    Backups {
+        #[clap(subcommand)]
+        subcommand: BackupCommand,
+    },
+    LogDirs {
+        #[clap(subcommand)]
+        subcommand: LogDirCommand,
+    },
     Produce {
         name: String,
         partition_id: u64,
         key: String,
         value: String,
+        /// The record is no longer returned by fetches after this many milliseconds
+        #[arg(long)]
+        ttl_ms: Option<u64>,
+        /// The record is held back from fetches for this many milliseconds
+        #[arg(long)]
+        delay_ms: Option<u64>,
+    },
+    /// Copy a record that could not be processed to the dead-letter topic of its topic
+    DeadLetter {
+        topic: String,
+        partition: u64,
+        offset: u64,
+        error: String,
+        #[arg(long, default_value_t = 1)]
+        attempts: u64,
     },
     Fetch {
         topic: String,
@@ -58,6 +124,235 @@ enum TopicCommand {
     Create {
         name: String,
         partitions: Option<u64>,
+        #[command(flatten)]
+        config: TopicConfigArgs,
+    },
+    Rename {
+        topic: String,
+        name: String,
+    },
+    /// Increase the amount of partitions of a topic
+    Partitions {
+        topic: String,
+        partitions: u64,
+    },
+    /// Change the config of a topic, only the given values are changed
+    Config {
+        topic: String,
+        #[command(flatten)]
+        config: TopicConfigArgs,
+        /// Return a value to the broker default, e.g. `retention_ms`
+        #[arg(long)]
+        reset: Vec<TopicConfigKey>,
+    },
+    /// Write every record of a topic to an archive file
+    Export {
+        topic: String,
+        file: String,
+    },
+    /// Create a topic from an archive file written by `export`
+    Import {
+        file: String,
+        /// Name of the new topic, defaults to the name in the archive
+        #[arg(long)]
+        name: Option<String>,
+        /// Keep the original offsets and timestamps of the records
+        #[arg(long)]
+        keep_offsets: bool,
+    },
+}
+
+#[derive(Args, Debug)]
+struct TopicConfigArgs {
+    #[arg(long)]
+    segment_size: Option<u64>,
+    #[arg(long)]
+    retention_ms: Option<u64>,
+    #[arg(long)]
+    retention_bytes: Option<u64>,
+    #[arg(long)]
+    max_record_size: Option<u64>,
+    /// Keep all segments instead of removing them once outside of retention
+    #[arg(long)]
+    retain: bool,
+}
+
+impl From<TopicConfigArgs> for TopicConfig {
+    fn from(args: TopicConfigArgs) -> Self {
+        TopicConfig {
+            segment_size: args.segment_size,
+            retention_ms: args.retention_ms,
+            retention_bytes: args.retention_bytes,
+            max_record_size: args.max_record_size,
+            cleanup_policy: args.retain.then_some(CleanupPolicy::Retain),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum TokenCommand {
+    List,
+    Create {
+        name: String,
+        #[arg(long)]
+        admin: bool,
+    },
+    Revoke {
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AclCommand {
+    List,
+    Create {
+        /// Principal name, or * for any principal
+        principal: String,
+        /// Topic name, prefix ending in * or * for any topic
+        pattern: AclPattern,
+        /// Operations to allow: produce, fetch, create, delete or describe
+        #[arg(required = true)]
+        operations: Vec<AclOperation>,
+    },
+    Delete {
+        acl_id: u64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum QuotaCommand {
+    List,
+    /// Replace the quota of a client or topic, unset rates are unlimited
+    Set {
+        #[command(flatten)]
+        entity: QuotaEntityArgs,
+        #[arg(long)]
+        produce_bytes_per_sec: Option<u64>,
+        #[arg(long)]
+        fetch_bytes_per_sec: Option<u64>,
+        /// Produce and fetch requests per second
+        #[arg(long)]
+        requests_per_sec: Option<u64>,
+    },
+    Delete {
+        #[command(flatten)]
+        entity: QuotaEntityArgs,
+    },
+}
+
+#[derive(Args, Debug)]
+#[group(required = true, multiple = false)]
+struct QuotaEntityArgs {
+    /// Principal name, or * for every client without its own quota
+    #[arg(long)]
+    client: Option<String>,
+    /// Topic name, or * for every topic without its own quota
+    #[arg(long)]
+    topic: Option<String>,
+}
+
+impl From<QuotaEntityArgs> for QuotaEntity {
+    fn from(args: QuotaEntityArgs) -> Self {
+        match (args.client, args.topic) {
+            (Some(client), _) => QuotaEntity::Client(client),
+            (None, Some(topic)) => QuotaEntity::Topic(topic),
+            (None, None) => unreachable!("clap requires --client or --topic"),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum QueueCommand {
+    State {
+        topic: String,
+    },
+    /// Receive messages, they are redelivered unless acked within the visibility timeout
+    Receive {
+        topic: String,
+        #[arg(long, default_value_t = 1)]
+        max_messages: usize,
+        /// Defaults to the visibility timeout of the server
+        #[arg(long)]
+        visibility_timeout_ms: Option<u64>,
+    },
+    Ack {
+        topic: String,
+        #[command(flatten)]
+        lease: LeaseArgs,
+    },
+    /// Make a received message visible again
+    Nack {
+        topic: String,
+        #[command(flatten)]
+        lease: LeaseArgs,
+        /// Keep the message invisible for this many milliseconds
+        #[arg(long)]
+        delay_ms: Option<u64>,
+    },
+}
+
+#[derive(Args, Debug)]
+struct LeaseArgs {
+    partition_id: u64,
+    offset: u64,
+    lease_id: u64,
+}
+
+impl From<LeaseArgs> for Lease {
+    fn from(args: LeaseArgs) -> Self {
+        Lease {
+            partition_id: args.partition_id,
+            offset: args.offset,
+            lease_id: args.lease_id,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum TrashCommand {
+    List,
+    Restore {
+        trash_id: String,
+        /// Name of the recreated topic, defaults to the original name
+        #[arg(long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum BackupCommand {
+    List,
+    /// Take a point-in-time backup, start a server with the printed path to restore it
+    Create,
+}
+
+#[derive(Subcommand, Debug)]
+enum LogDirCommand {
+    List,
+    /// Move a partition to another log directory, it stays online during the move
+    Move {
+        topic: String,
+        partition: u64,
+        log_dir: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SegmentCommand {
+    List {
+        topic: String,
+        partition: u64,
+    },
+    /// Close the active segment, new records go to a new segment
+    Roll {
+        topic: String,
+        partition: u64,
+    },
+    /// Remove the segments before the offset, which has to be the start of a segment
+    Truncate {
+        topic: String,
+        partition: u64,
+        offset: u64,
     },
 }
 
@@ -66,7 +361,10 @@ pub async fn main() -> Result<()> {
     let cli = Cli::parse();
     set_up_logging(cli.verbose, cli.quiet)?;
 
-    let client = HttpClient::new(format!("http://127.0.0.1:{}", DEFAULT_PORT))?;
+    let mut client = HttpClient::new(format!("http://127.0.0.1:{}", DEFAULT_PORT))?;
+    if let Some(token) = cli.token {
+        client = client.with_token(token);
+    }
 
     match cli.command {
         Command::Topics { subcommand } => {
@@ -82,19 +380,233 @@ pub async fn main() -> Result<()> {
                     let state = client.get_topics().await?;
                     info!("{state:#?}");
                 }
-                TopicCommand::Create { name, partitions } => {
-                    let result = client.create_topic(&name, partitions).await?;
+                TopicCommand::Create {
+                    name,
+                    partitions,
+                    config,
+                } => {
+                    let result = client
+                        .create_topic_with_config(&name, partitions, config.into())
+                        .await?;
 
                     info!("Created topic with id {}", result.topic_id);
                 }
+                TopicCommand::Rename { topic, name } => {
+                    let state = client.rename_topic(&topic, &name).await?;
+                    info!("Renamed topic {} to {}", state.topic_id, state.name);
+                }
+                TopicCommand::Partitions { topic, partitions } => {
+                    let state = client.alter_partitions(&topic, partitions).await?;
+                    info!(
+                        "Topic {} now has {} partitions",
+                        state.name,
+                        state.partitions.len()
+                    );
+                }
+                TopicCommand::Config {
+                    topic,
+                    config,
+                    reset,
+                } => {
+                    let state = client
+                        .alter_topic_config(&topic, config.into(), reset)
+                        .await?;
+                    info!("{:#?}", state.config);
+                }
+                TopicCommand::Export { topic, file } => {
+                    let mut file = tokio::fs::File::create(file).await?;
+                    client.export_topic(&topic, &mut file).await?;
+                }
+                TopicCommand::Import {
+                    file,
+                    name,
+                    keep_offsets,
+                } => {
+                    let file = tokio::fs::File::open(file).await?;
+                    let result = client
+                        .import_topic(file, ImportTopicCommand { name, keep_offsets })
+                        .await?;
+
+                    info!(
+                        "Imported {} records into topic {} ({})",
+                        result.records, result.topic.name, result.topic.topic_id
+                    );
+                }
+            };
+        }
+        Command::Tokens { subcommand } => {
+            match subcommand {
+                TokenCommand::List => {
+                    let tokens = client.get_tokens().await?;
+                    info!("{tokens:#?}");
+                }
+                TokenCommand::Create { name, admin } => {
+                    let result = client.create_token(&name, admin).await?;
+
+                    info!("Created token {}: {}", result.name, result.token);
+                }
+                TokenCommand::Revoke { name } => {
+                    client.revoke_token(&name).await?;
+                }
+            };
+        }
+        Command::Acls { subcommand } => {
+            match subcommand {
+                AclCommand::List => {
+                    let acls = client.get_acls().await?;
+                    info!("{acls:#?}");
+                }
+                AclCommand::Create {
+                    principal,
+                    pattern,
+                    operations,
+                } => {
+                    let result = client.create_acl(&principal, pattern, operations).await?;
+
+                    info!("Created acl with id {}", result.acl_id);
+                }
+                AclCommand::Delete { acl_id } => {
+                    client.delete_acl(acl_id).await?;
+                }
+            };
+        }
+        Command::Quotas { subcommand } => {
+            match subcommand {
+                QuotaCommand::List => {
+                    let quotas = client.get_quotas().await?;
+                    info!("{quotas:#?}");
+                }
+                QuotaCommand::Set {
+                    entity,
+                    produce_bytes_per_sec,
+                    fetch_bytes_per_sec,
+                    requests_per_sec,
+                } => {
+                    let state = client
+                        .set_quota(&entity.into(), Quota {
+                            produce_bytes_per_sec,
+                            fetch_bytes_per_sec,
+                            requests_per_sec,
+                        })
+                        .await?;
+                    info!("Set quota of {}", state.entity);
+                }
+                QuotaCommand::Delete { entity } => {
+                    client.delete_quota(&entity.into()).await?;
+                }
+            };
+        }
+        Command::Queue { subcommand } => {
+            match subcommand {
+                QueueCommand::State { topic } => {
+                    let state = client.get_queue_state(&topic).await?;
+                    info!("{state:#?}");
+                }
+                QueueCommand::Receive {
+                    topic,
+                    max_messages,
+                    visibility_timeout_ms,
+                } => {
+                    let response = client
+                        .receive(&topic, ReceiveCommand {
+                            encoding: Encoding::Utf8,
+                            max_messages,
+                            visibility_timeout_ms,
+                        })
+                        .await?;
+                    info!("{response:#?}");
+                }
+                QueueCommand::Ack { topic, lease } => {
+                    client.ack(&topic, vec![lease.into()]).await?;
+                }
+                QueueCommand::Nack {
+                    topic,
+                    lease,
+                    delay_ms,
+                } => {
+                    client
+                        .nack(
+                            &topic,
+                            vec![lease.into()],
+                            delay_ms.map(Duration::from_millis),
+                        )
+                        .await?;
+                }
             };
         }
+        Command::Trash { subcommand } => {
+            match subcommand {
+                TrashCommand::List => {
+                    let trash = client.get_trash().await?;
+                    info!("{trash:#?}");
+                }
+                TrashCommand::Restore { trash_id, name } => {
+                    let state = client.restore_trash(&trash_id, name.as_deref()).await?;
+                    info!("Restored topic {} with id {}", state.name, state.topic_id);
+                }
+            };
+        }
+        Command::Backups { subcommand } => {
+            match subcommand {
+                BackupCommand::List => {
+                    let backups = client.get_backups().await?;
+                    info!("{backups:#?}");
+                }
+                BackupCommand::Create => {
+                    let backup = client.create_backup().await?;
+                    info!(
+                        "Created backup {} of {} partitions at {}",
+                        backup.backup_id,
+                        backup.partitions.len(),
+                        backup.path
+                    );
+                }
+            };
+        }
+        Command::LogDirs { subcommand } => {
+            match subcommand {
+                LogDirCommand::List => {
+                    let log_dirs = client.get_log_dirs().await?;
+                    info!("{log_dirs:#?}");
+                }
+                LogDirCommand::Move {
+                    topic,
+                    partition,
+                    log_dir,
+                } => {
+                    let state = client.move_partition(&topic, partition, &log_dir).await?;
+                    info!(
+                        "Moved partition {} of topic {topic} to {}",
+                        state.partition_id, state.log_dir
+                    );
+                }
+            };
+        }
+        Command::Segments { subcommand } => {
+            let segments = match subcommand {
+                SegmentCommand::List { topic, partition } => {
+                    client.get_segments(&topic, partition).await?
+                }
+                SegmentCommand::Roll { topic, partition } => {
+                    client.roll_segment(&topic, partition).await?
+                }
+                SegmentCommand::Truncate {
+                    topic,
+                    partition,
+                    offset,
+                } => client.truncate_partition(&topic, partition, offset).await?,
+            };
+            info!("{segments:#?}");
+        }
         Command::Produce {
             name,
             partition_id,
             key,
             value,
+            ttl_ms,
+            delay_ms,
         } => {
+            let after_ms = |ms: u64| Timestamp::from(Timestamp::now().as_micros() + ms * 1000);
             let response = client
                 .produce(ProduceCommand {
                     topic: Identifier::Name(name),
@@ -103,11 +615,35 @@ pub async fn main() -> Result<()> {
                     value,
                     encoding: Encoding::Utf8,
                     headers: None,
+                    expires_at: ttl_ms.map(after_ms),
+                    deliver_at: delay_ms.map(after_ms),
                 })
                 .await?;
 
             info!("Produced offset {}", response.offset);
         }
+        Command::DeadLetter {
+            topic,
+            partition,
+            offset,
+            error,
+            attempts,
+        } => {
+            let response = client
+                .dead_letter(DeadLetterCommand {
+                    topic: Identifier::Name(topic),
+                    partition_id: partition,
+                    offset,
+                    error,
+                    attempts,
+                })
+                .await?;
+
+            info!(
+                "Copied record to {} partition {} offset {}",
+                response.topic, response.partition_id, response.offset
+            );
+        }
         Command::Fetch {
             topic,
             partition,
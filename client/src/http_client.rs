@@ -1,19 +1,53 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
-use reqwest::{Client, IntoUrl, Response, StatusCode, Url};
+use reqwest::{Client, IntoUrl, RequestBuilder, Response, StatusCode, Url};
 use serde::{Serialize, de::DeserializeOwned};
 use shared::{
     commands::{
-        create_topic_command::CreateTopicCommand, fetch_command::FetchCommand,
+        ack_command::{AckCommand, NackCommand},
+        alter_partitions_command::AlterPartitionsCommand,
+        alter_topic_config_command::AlterTopicConfigCommand,
+        create_acl_command::CreateAclCommand,
+        create_token_command::CreateTokenCommand,
+        create_topic_command::CreateTopicCommand,
+        dead_letter_command::DeadLetterCommand,
+        fetch_command::FetchCommand,
+        import_topic_command::ImportTopicCommand,
+        move_partition_command::MovePartitionCommand,
         produce_command::ProduceCommand,
+        receive_command::ReceiveCommand,
+        rename_topic_command::RenameTopicCommand,
+        restore_trash_command::RestoreTrashCommand,
+        truncate_partition_command::TruncatePartitionCommand,
+    },
+    data::{
+        acl::{AclOperation, AclPattern},
+        lease::Lease,
+        quota::{Quota, QuotaEntity},
+        topic_config::{TopicConfig, TopicConfigKey},
     },
     response::{
-        error_response::ErrorResponse, produce_response::ProduceResponse,
+        dead_letter_response::DeadLetterResponse,
+        error_response::{ErrorCode, ErrorResponse},
+        import_response::ImportResponse,
+        produce_response::ProduceResponse,
+        receive_response::ReceiveResponse,
         record_response::FetchResponse,
+        snapshot_response::SnapshotResponse,
+        token_response::CreateTokenResponse,
+    },
+    state::{
+        acl_state::AclState, backup_state::BackupState, log_dir_state::LogDirState,
+        partition_state::PartitionState, queue_state::QueueState, quota_state::QuotaState,
+        segment_state::SegmentState, token_state::TokenState, topic_state::TopicState,
+        trash_state::TrashState,
     },
-    state::topic_state::TopicState,
 };
 use thiserror::Error;
+use tokio::{
+    fs::File,
+    io::{AsyncWrite, AsyncWriteExt},
+};
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -21,15 +55,56 @@ pub enum Error {
     UrlParseError,
     #[error("HTTP Error")]
     HttpError(#[from] reqwest::Error),
+    #[error("IO Error")]
+    Io(#[from] std::io::Error),
     #[error("Invalid JSON Response")]
     InvalidJsonResponse,
-    #[error("Invalid response {0} {1}")]
-    ErrorResponse(StatusCode, String),
+    #[error("Topic name is already in use: {0}")]
+    TopicNameInUse(String),
+    #[error("Topic not found: {0}")]
+    TopicNotFound(String),
+    #[error("Partition not found: {0}")]
+    PartitionNotFound(String),
+    #[error("Fetch timed out: {0}")]
+    FetchTimeout(String),
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    #[error("Throttled: {0}")]
+    Throttled(String, Option<Duration>),
+    /// The message was acked or redelivered since it was received with the lease
+    #[error("Lease not found: {0}")]
+    LeaseNotFound(String),
+    #[error("Invalid response {0} {2}")]
+    ErrorResponse(StatusCode, ErrorCode, String),
+}
+
+impl Error {
+    fn from_response(status: StatusCode, response: ErrorResponse) -> Self {
+        let message = response.error;
+
+        match response.code {
+            ErrorCode::TopicNameInUse => Error::TopicNameInUse(message),
+            ErrorCode::TopicNotFound => Error::TopicNotFound(message),
+            ErrorCode::PartitionNotFound => Error::PartitionNotFound(message),
+            ErrorCode::FetchTimeout => Error::FetchTimeout(message),
+            ErrorCode::Unauthorized => Error::Unauthorized(message),
+            ErrorCode::AdminRequired | ErrorCode::AclDenied => Error::Forbidden(message),
+            ErrorCode::Throttled => {
+                Error::Throttled(message, response.retry_after_ms.map(Duration::from_millis))
+            }
+            ErrorCode::LeaseNotFound => Error::LeaseNotFound(message),
+            code => Error::ErrorResponse(status, code, message),
+        }
+    }
 }
 
+#[derive(Clone)]
 pub struct HttpClient {
     base_url: Url,
     client: Client,
+    token: Option<String>,
 }
 
 impl HttpClient {
@@ -37,13 +112,27 @@ impl HttpClient {
         Ok(Self {
             base_url: base_url.into_url()?,
             client: Client::new(),
+            token: None,
         })
     }
 
+    /// Sends the token as a bearer token with every request
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
     fn get_url(&self, url: &str) -> Result<Url, Error> {
         self.base_url.join(url).map_err(|_| Error::UrlParseError)
     }
 
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
     async fn post<T: DeserializeOwned, TBody: Serialize>(
         &self,
         url: &str,
@@ -51,7 +140,36 @@ impl HttpClient {
     ) -> Result<T, Error> {
         let url = self.get_url(url)?;
 
-        let response = self.client.post(url).json(&body).send().await?;
+        let response = self
+            .authorize(self.client.post(url).json(&body))
+            .send()
+            .await?;
+
+        self.get_response(response).await
+    }
+
+    async fn post_unit<TBody: Serialize>(&self, url: &str, body: TBody) -> Result<(), Error> {
+        let url = self.get_url(url)?;
+
+        let response = self
+            .authorize(self.client.post(url).json(&body))
+            .send()
+            .await?;
+
+        self.get_unit_response(response).await
+    }
+
+    async fn put<T: DeserializeOwned, TBody: Serialize>(
+        &self,
+        url: &str,
+        body: TBody,
+    ) -> Result<T, Error> {
+        let url = self.get_url(url)?;
+
+        let response = self
+            .authorize(self.client.put(url).json(&body))
+            .send()
+            .await?;
 
         self.get_response(response).await
     }
@@ -59,7 +177,7 @@ impl HttpClient {
     async fn delete(&self, url: &str) -> Result<(), Error> {
         let url = self.get_url(url)?;
 
-        let response = self.client.delete(url).send().await?;
+        let response = self.authorize(self.client.delete(url)).send().await?;
 
         self.get_unit_response(response).await
     }
@@ -71,7 +189,10 @@ impl HttpClient {
     ) -> Result<T, Error> {
         let url = self.get_url(url)?;
 
-        let response = self.client.get(url).json(&body).send().await?;
+        let response = self
+            .authorize(self.client.get(url).json(&body))
+            .send()
+            .await?;
 
         self.get_response(response).await
     }
@@ -79,7 +200,7 @@ impl HttpClient {
     async fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T, Error> {
         let url = self.get_url(url)?;
 
-        let response = self.client.get(url).send().await?;
+        let response = self.authorize(self.client.get(url)).send().await?;
 
         self.get_response(response).await
     }
@@ -87,14 +208,16 @@ impl HttpClient {
     async fn get_unit_response(&self, response: Response) -> Result<(), Error> {
         match response.status() {
             StatusCode::OK => Ok(()),
-            status => {
-                let error_response = response
-                    .json::<ErrorResponse>()
-                    .await
-                    .map_err(|_| Error::InvalidJsonResponse)?;
+            _ => Err(self.get_error(response).await),
+        }
+    }
 
-                Err(Error::ErrorResponse(status, error_response.error))
-            }
+    async fn get_error(&self, response: Response) -> Error {
+        let status = response.status();
+
+        match response.json::<ErrorResponse>().await {
+            Ok(error_response) => Error::from_response(status, error_response),
+            Err(_) => Error::InvalidJsonResponse,
         }
     }
 
@@ -104,14 +227,7 @@ impl HttpClient {
                 .json()
                 .await
                 .map_err(|_| Error::InvalidJsonResponse)?),
-            status => {
-                let error_response = response
-                    .json::<ErrorResponse>()
-                    .await
-                    .map_err(|_| Error::InvalidJsonResponse)?;
-
-                Err(Error::ErrorResponse(status, error_response.error))
-            }
+            _ => Err(self.get_error(response).await),
         }
     }
 
@@ -131,15 +247,39 @@ impl HttpClient {
         &self,
         name: &str,
         partitions: Option<u64>,
+    ) -> Result<TopicState, Error> {
+        self.create_topic_with_config(name, partitions, TopicConfig::default())
+            .await
+    }
+
+    pub async fn create_topic_with_config(
+        &self,
+        name: &str,
+        partitions: Option<u64>,
+        config: TopicConfig,
     ) -> Result<TopicState, Error> {
         self.post("/topics", CreateTopicCommand {
             name: name.to_string(),
             partitions,
             topic_id: None,
+            config,
         })
         .await
     }
 
+    pub async fn alter_topic_config(
+        &self,
+        name: &str,
+        config: TopicConfig,
+        reset: Vec<TopicConfigKey>,
+    ) -> Result<TopicState, Error> {
+        self.put(
+            &format!("/topics/{}/config", name),
+            AlterTopicConfigCommand { config, reset },
+        )
+        .await
+    }
+
     pub async fn create_topic_if_not_exists(
         &self,
         name: &str,
@@ -147,19 +287,272 @@ impl HttpClient {
     ) -> Result<TopicState, Error> {
         match self.create_topic(name, partitions).await {
             Ok(topic) => Ok(topic),
-            // TODO: this isn't a great way to check the error
-            Err(Error::ErrorResponse(_, message)) if message.contains("is already in use") => {
-                return self.get_topic(name).await;
-            }
+            Err(Error::TopicNameInUse(_)) => self.get_topic(name).await,
             Err(err) => Err(err),
         }
     }
 
+    /// Increases the partition count of a topic, the count can never be decreased
+    pub async fn alter_partitions(&self, name: &str, partitions: u64) -> Result<TopicState, Error> {
+        self.put(
+            &format!("/topics/{}/partitions", name),
+            AlterPartitionsCommand { partitions },
+        )
+        .await
+    }
+
+    pub async fn rename_topic(&self, name: &str, new_name: &str) -> Result<TopicState, Error> {
+        self.post(&format!("/topics/{}/rename", name), RenameTopicCommand {
+            name: new_name.to_string(),
+        })
+        .await
+    }
+
     pub async fn produce(&self, produce: ProduceCommand) -> Result<ProduceResponse, Error> {
         self.post("/topics/records", produce).await
     }
 
+    /// Copies a record that could not be processed to `<topic>.dlq` with headers describing the
+    /// failure, the dead-letter topic is created on first use
+    pub async fn dead_letter(
+        &self,
+        dead_letter: DeadLetterCommand,
+    ) -> Result<DeadLetterResponse, Error> {
+        self.post("/topics/records/dead-letter", dead_letter).await
+    }
+
     pub async fn fetch(&self, fetch: FetchCommand) -> Result<FetchResponse, Error> {
         self.get_with_body("/topics/records", fetch).await
     }
+
+    /// Receives messages of a topic consumed as a queue, they have to be acked before their
+    /// visibility timeout runs out or they are redelivered
+    pub async fn receive(
+        &self,
+        name: &str,
+        receive: ReceiveCommand,
+    ) -> Result<ReceiveResponse, Error> {
+        self.post(&format!("/topics/{}/queue/receive", name), receive)
+            .await
+    }
+
+    pub async fn ack(&self, name: &str, leases: Vec<Lease>) -> Result<(), Error> {
+        self.post_unit(&format!("/topics/{}/queue/ack", name), AckCommand {
+            leases,
+        })
+        .await
+    }
+
+    /// Makes received messages visible again after the delay, or immediately without one
+    pub async fn nack(
+        &self,
+        name: &str,
+        leases: Vec<Lease>,
+        delay: Option<Duration>,
+    ) -> Result<(), Error> {
+        self.post_unit(&format!("/topics/{}/queue/nack", name), NackCommand {
+            leases,
+            delay_ms: delay.map(|delay| delay.as_millis() as u64),
+        })
+        .await
+    }
+
+    pub async fn get_queue_state(&self, name: &str) -> Result<QueueState, Error> {
+        self.get(&format!("/topics/{}/queue", name)).await
+    }
+
+    pub async fn create_token(
+        &self,
+        name: &str,
+        admin: bool,
+    ) -> Result<CreateTokenResponse, Error> {
+        self.post("/admin/tokens", CreateTokenCommand {
+            name: name.to_string(),
+            admin,
+        })
+        .await
+    }
+
+    pub async fn get_tokens(&self) -> Result<Vec<TokenState>, Error> {
+        self.get("/admin/tokens").await
+    }
+
+    pub async fn revoke_token(&self, name: &str) -> Result<(), Error> {
+        self.delete(&format!("/admin/tokens/{}", name)).await
+    }
+
+    pub async fn create_acl(
+        &self,
+        principal: &str,
+        pattern: AclPattern,
+        operations: Vec<AclOperation>,
+    ) -> Result<AclState, Error> {
+        self.post("/admin/acls", CreateAclCommand {
+            principal: principal.to_string(),
+            pattern,
+            operations,
+        })
+        .await
+    }
+
+    pub async fn get_acls(&self) -> Result<Vec<AclState>, Error> {
+        self.get("/admin/acls").await
+    }
+
+    pub async fn delete_acl(&self, acl_id: u64) -> Result<(), Error> {
+        self.delete(&format!("/admin/acls/{}", acl_id)).await
+    }
+
+    /// Replaces the quota of a client or topic, unset rates are unlimited
+    pub async fn set_quota(&self, entity: &QuotaEntity, quota: Quota) -> Result<QuotaState, Error> {
+        self.put(&quota_url(entity), quota).await
+    }
+
+    pub async fn get_quotas(&self) -> Result<Vec<QuotaState>, Error> {
+        self.get("/admin/quotas").await
+    }
+
+    pub async fn delete_quota(&self, entity: &QuotaEntity) -> Result<(), Error> {
+        self.delete(&quota_url(entity)).await
+    }
+
+    pub async fn get_trash(&self) -> Result<Vec<TrashState>, Error> {
+        self.get("/admin/trash").await
+    }
+
+    /// Recreates a deleted topic from the trash, the name defaults to its original name
+    pub async fn restore_trash(
+        &self,
+        trash_id: &str,
+        name: Option<&str>,
+    ) -> Result<TopicState, Error> {
+        self.post(
+            &format!("/admin/trash/{}/restore", trash_id),
+            RestoreTrashCommand {
+                name: name.map(str::to_string),
+            },
+        )
+        .await
+    }
+
+    /// Forces a snapshot of the metadata, returns the offset the snapshot was taken at
+    pub async fn snapshot_metadata(&self) -> Result<SnapshotResponse, Error> {
+        self.post("/admin/metadata/snapshot", ()).await
+    }
+
+    pub async fn get_backups(&self) -> Result<Vec<BackupState>, Error> {
+        self.get("/admin/backups").await
+    }
+
+    /// Takes a point-in-time backup of the data directory on the server
+    pub async fn create_backup(&self) -> Result<BackupState, Error> {
+        self.post("/admin/backups", ()).await
+    }
+
+    pub async fn get_segments(
+        &self,
+        topic: &str,
+        partition_id: u64,
+    ) -> Result<Vec<SegmentState>, Error> {
+        self.get(&format!(
+            "/admin/topics/{}/partitions/{}/segments",
+            topic, partition_id
+        ))
+        .await
+    }
+
+    /// Closes the active segment of a partition, returns the segments afterwards
+    pub async fn roll_segment(
+        &self,
+        topic: &str,
+        partition_id: u64,
+    ) -> Result<Vec<SegmentState>, Error> {
+        self.post(
+            &format!("/admin/topics/{}/partitions/{}/roll", topic, partition_id),
+            (),
+        )
+        .await
+    }
+
+    /// Removes the segments that only contain records before `offset`, returns the remaining
+    /// segments
+    pub async fn get_log_dirs(&self) -> Result<Vec<LogDirState>, Error> {
+        self.get("/admin/log-dirs").await
+    }
+
+    /// Moves a partition to another configured log directory while it stays online
+    pub async fn move_partition(
+        &self,
+        topic: &str,
+        partition_id: u64,
+        log_dir: &str,
+    ) -> Result<PartitionState, Error> {
+        self.post(
+            &format!("/admin/topics/{}/partitions/{}/move", topic, partition_id),
+            MovePartitionCommand {
+                log_dir: log_dir.to_string(),
+            },
+        )
+        .await
+    }
+
+    pub async fn truncate_partition(
+        &self,
+        topic: &str,
+        partition_id: u64,
+        offset: u64,
+    ) -> Result<Vec<SegmentState>, Error> {
+        self.post(
+            &format!(
+                "/admin/topics/{}/partitions/{}/truncate",
+                topic, partition_id
+            ),
+            TruncatePartitionCommand { offset },
+        )
+        .await
+    }
+
+    /// Streams an archive of all partitions of a topic into `writer`
+    pub async fn export_topic<W: AsyncWrite + Unpin>(
+        &self,
+        topic: &str,
+        writer: &mut W,
+    ) -> Result<(), Error> {
+        let url = self.get_url(&format!("/admin/topics/{}/export", topic))?;
+
+        let mut response = self.authorize(self.client.get(url)).send().await?;
+        if response.status() != StatusCode::OK {
+            return Err(self.get_error(response).await);
+        }
+
+        while let Some(chunk) = response.chunk().await? {
+            writer.write_all(&chunk).await?;
+        }
+        writer.flush().await?;
+
+        Ok(())
+    }
+
+    /// Creates a topic from an archive written by [`HttpClient::export_topic`]
+    pub async fn import_topic(
+        &self,
+        archive: File,
+        command: ImportTopicCommand,
+    ) -> Result<ImportResponse, Error> {
+        let url = self.get_url("/admin/topics/import")?;
+
+        let response = self
+            .authorize(self.client.post(url).query(&command).body(archive))
+            .send()
+            .await?;
+
+        self.get_response(response).await
+    }
+}
+
+fn quota_url(entity: &QuotaEntity) -> String {
+    match entity {
+        QuotaEntity::Client(name) => format!("/admin/quotas/clients/{}", name),
+        QuotaEntity::Topic(name) => format!("/admin/quotas/topics/{}", name),
+    }
 }
@@ -46,6 +46,8 @@ pub async fn main() -> Result<()> {
                 value: format!("Idx: {}", idx),
                 encoding: Encoding::Utf8,
                 headers: None,
+                expires_at: None,
+                deliver_at: None,
             })
             .await;
 
@@ -68,6 +68,8 @@ async fn produce(
             value,
             encoding: Encoding::Utf8,
             headers: None,
+            expires_at: None,
+            deliver_at: None,
         })
         .await
         .map(|result| result.offset)?)
@@ -0,0 +1,98 @@
+use shared::{
+    data::acl::{ANY_PRINCIPAL, AclOperation, AclPattern},
+    state::acl_state::AclState,
+};
+use tracing::info;
+
+use crate::{
+    auth::Principal,
+    meta::{MetadataEntry, create_acl_entry::CreateAclEntry, delete_acl_entry::DeleteAclEntry},
+};
+
+use super::AppLock;
+use super::error::{Error, Result};
+
+impl AppLock {
+    /// Checks whether the principal may perform the operation on the topic. Admins and all
+    /// principals when ACLs are disabled are always allowed, otherwise a matching ACL is required
+    pub fn authorize(
+        &self,
+        principal: &Principal,
+        operation: AclOperation,
+        topic_name: &str,
+    ) -> Result<()> {
+        if !self.config.acl.enabled || principal.admin {
+            return Ok(());
+        }
+
+        let allowed = self.acls.values().any(|acl| {
+            (acl.principal == ANY_PRINCIPAL || acl.principal == principal.name)
+                && acl.pattern.matches(topic_name)
+                && acl.operations.contains(&operation)
+        });
+
+        if !allowed {
+            return Err(Error::AclDenied(
+                principal.name.to_string(),
+                operation,
+                topic_name.to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub async fn create_acl(
+        &mut self,
+        principal: &str,
+        pattern: AclPattern,
+        operations: Vec<AclOperation>,
+    ) -> Result<AclState> {
+        if principal.is_empty() {
+            return Err(Error::InvalidPrincipal(principal.to_string()));
+        }
+
+        let acl_id = self
+            .acls
+            .last_key_value()
+            .map(|(acl_id, _)| acl_id + 1)
+            .unwrap_or(0);
+
+        info!("Creating acl {acl_id} for {principal} on {pattern}");
+        self.append_metadata(MetadataEntry::CreateAcl(CreateAclEntry {
+            acl_id,
+            principal: principal.to_string(),
+            pattern: pattern.clone(),
+            operations: operations.clone(),
+        }))
+        .await?;
+
+        let acl = AclState {
+            acl_id,
+            principal: principal.to_string(),
+            pattern,
+            operations,
+        };
+        self.acls.insert(acl_id, acl.clone());
+
+        Ok(acl)
+    }
+
+    pub async fn delete_acl(&mut self, acl_id: u64) -> Result<()> {
+        if !self.acls.contains_key(&acl_id) {
+            return Err(Error::AclNotFound(acl_id));
+        }
+
+        info!("Deleting acl {acl_id}");
+        self.append_metadata(MetadataEntry::DeleteAcl(DeleteAclEntry { acl_id }))
+            .await?;
+
+        self.acls.remove(&acl_id);
+
+        Ok(())
+    }
+
+    pub fn acl_states(&self) -> Vec<AclState> {
+        self.acls.values().cloned().collect()
+    }
+}
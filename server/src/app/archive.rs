@@ -0,0 +1,140 @@
+use shared::{
+    data::{
+        identifier::Identifier,
+        topic_archive::{ARCHIVE_ENCODING, TopicArchiveHeader},
+    },
+    response::record_response::RecordResponse,
+};
+use tracing::info;
+
+use super::{
+    AppLock,
+    error::{Error, Result},
+};
+use crate::{auth::Principal, dur, dur::record::Record};
+
+/// A topic as it was when its export started, records appended later are not exported
+pub struct TopicExport {
+    pub topic_id: u64,
+    pub header: TopicArchiveHeader,
+    /// Offset each partition ends at, by partition id
+    pub end_offsets: Vec<u64>,
+}
+
+impl AppLock {
+    pub fn export_topic(&self, identifier: &Identifier) -> Result<TopicExport> {
+        let topic = self.get_topic(identifier)?;
+
+        if topic.is_internal() {
+            return Err(Error::InternalTopicName(topic.name().to_string()));
+        }
+
+        let state = topic.state();
+        info!("Exporting topic {} ({})", state.name, state.topic_id);
+
+        Ok(TopicExport {
+            topic_id: state.topic_id,
+            header: TopicArchiveHeader {
+                name: state.name,
+                partitions: state.partitions.len() as u64,
+                config: state.config,
+            },
+            end_offsets: state
+                .partitions
+                .iter()
+                .map(|partition| partition.current_offset)
+                .collect(),
+        })
+    }
+
+    /// Reads the next records of an export, at most one segment at a time to bound memory
+    pub async fn export_records(
+        &self,
+        topic_id: u64,
+        partition_id: u64,
+        offset: u64,
+        end_offset: u64,
+    ) -> Result<Vec<RecordResponse>> {
+        let topic = self.get_topic_by_id(topic_id)?;
+
+        Ok(topic
+            .read_segment_from(partition_id, offset)
+            .await?
+            .iter()
+            .filter(|record| record.offset < end_offset)
+            .map(|record| record.to_response(&ARCHIVE_ENCODING, topic_id, partition_id))
+            .collect::<std::result::Result<_, _>>()?)
+    }
+
+    /// Creates the topic of an archive, the name defaults to the name in the archive
+    pub async fn import_topic(
+        &mut self,
+        principal: &Principal,
+        header: &TopicArchiveHeader,
+        name: Option<&str>,
+    ) -> Result<u64> {
+        let name = name.unwrap_or(&header.name);
+        info!(
+            "Importing topic {name} with {} partitions",
+            header.partitions
+        );
+
+        self.create_topic(
+            principal,
+            None,
+            name,
+            Some(header.partitions),
+            header.config.clone(),
+        )
+        .await
+    }
+
+    /// Appends a record of an archive, with `keep_offsets` its original offset and timestamp are
+    /// kept, so records have to be imported in order
+    pub async fn import_record(
+        &mut self,
+        topic_id: u64,
+        record: RecordResponse,
+        keep_offsets: bool,
+    ) -> Result<()> {
+        let partition_id = record.partition_id;
+        let record = Record::from_response(record, &ARCHIVE_ENCODING)?;
+        let offset = record.offset;
+
+        let topic = self.get_topic_by_id(topic_id)?;
+        if partition_id >= topic.partition_count() {
+            return Err(Error::InvalidArchive(format!(
+                "record for partition {partition_id} of a topic with {} partitions",
+                topic.partition_count()
+            )));
+        }
+        self.validate_record(topic, &record.key, &record.value, &record.headers)?;
+
+        let log_dir = topic.log_dir(partition_id)?.clone();
+        self.check_disk_space(&log_dir)?;
+
+        let disk_space = self.disk_space.clone();
+        let topic = self.get_topic_by_id_mut(topic_id)?;
+        let record = if keep_offsets {
+            topic
+                .append_record(partition_id, record)
+                .await
+                .inspect_err(|e| disk_space.handle_append_error(&log_dir, e))
+                .map_err(|e| match e {
+                    dur::error::Error::OffsetOutOfRange => Error::InvalidArchive(format!(
+                        "offset {offset} of partition {partition_id} is not after the previous record"
+                    )),
+                    e => e.into(),
+                })?
+        } else {
+            topic
+                .append(partition_id, record.key, record.value, record.headers)
+                .await
+                .inspect_err(|e| disk_space.handle_append_error(&log_dir, e))?
+        };
+
+        self.notify_listeners(topic_id, partition_id, record);
+
+        Ok(())
+    }
+}
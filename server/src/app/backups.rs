@@ -0,0 +1,93 @@
+use shared::{
+    data::timestamp::Timestamp,
+    state::backup_state::{BackupPartitionState, BackupState},
+};
+use tokio::fs::create_dir_all;
+use tracing::{info, warn};
+
+use crate::{
+    config::LogDir,
+    dur::{self, backup},
+    meta::snapshot::MetadataSnapshot,
+};
+
+use super::{AppLock, error::Result};
+
+impl AppLock {
+    pub async fn backup_states(&self) -> Result<Vec<BackupState>> {
+        Ok(backup::list(&self.config).await?)
+    }
+
+    /// Takes a point-in-time backup of every topic, including `__metadata`, and the metadata
+    /// snapshots. Partitions are backed up within their log directory. Holding the write lock keeps appends out while the log-end offsets are recorded
+    /// and the active segments are copied
+    pub async fn create_backup(&mut self) -> Result<BackupState> {
+        let created_at = Timestamp::now();
+        let backup_id = created_at.as_micros().to_string();
+
+        match self.write_backup(&backup_id, created_at).await {
+            Ok(state) => {
+                info!(
+                    "Created backup {backup_id} of {} partitions",
+                    state.partitions.len()
+                );
+                Ok(state)
+            }
+            Err(e) => {
+                warn!("Failed to create backup {backup_id}: {e}");
+                backup::remove(&self.config, &backup_id).await?;
+                Err(e)
+            }
+        }
+    }
+
+    async fn write_backup(&self, backup_id: &str, created_at: Timestamp) -> Result<BackupState> {
+        let target = backup::target_config(&self.config, backup_id);
+        for log_dir in self.config.all_log_dirs() {
+            create_dir_all(log_dir.backup_dir(backup_id).topics_path())
+                .await
+                .map_err(dur::error::Error::from)?;
+        }
+
+        let mut partitions = Vec::new();
+        let mut topic_ids = self.topics.keys().copied().collect::<Vec<_>>();
+        topic_ids.sort();
+        for topic_id in topic_ids {
+            let end_offsets = self.topics[&topic_id].backup(backup_id).await?;
+
+            partitions.extend(end_offsets.into_iter().enumerate().map(
+                |(partition_id, end_offset)| BackupPartitionState {
+                    topic_id,
+                    partition_id: partition_id as u64,
+                    end_offset,
+                },
+            ));
+        }
+
+        let snapshot_offsets = MetadataSnapshot::offsets(&self.config)
+            .await
+            .map_err(dur::error::Error::from)?;
+        backup::link_metadata_snapshots(&self.config, &target, &snapshot_offsets).await?;
+
+        let state = BackupState {
+            backup_id: backup_id.to_string(),
+            path: target.base_path(),
+            log_dirs: self
+                .config
+                .log_dirs
+                .iter()
+                .map(|log_dir| {
+                    LogDir::new(log_dir.clone())
+                        .backup_dir(backup_id)
+                        .to_string()
+                })
+                .collect(),
+            created_at,
+            metadata_snapshot_offset: snapshot_offsets.last().copied(),
+            partitions,
+        };
+        backup::write_state(&self.config, &state).await?;
+
+        Ok(state)
+    }
+}
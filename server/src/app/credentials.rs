@@ -0,0 +1,124 @@
+use bytes::Bytes;
+use shared::{
+    data::topic_config::TopicConfig, response::token_response::CreateTokenResponse,
+    state::token_state::TokenState,
+};
+use tracing::{debug, info, warn};
+
+use crate::auth::{
+    CREDENTIALS_TOPIC, CredentialEntry, Principal, constant_time_eq,
+    create_token_entry::CreateTokenEntry, generate_token, hash_token,
+    revoke_token_entry::RevokeTokenEntry,
+};
+
+use super::AppLock;
+use super::error::{Error, Result};
+
+impl AppLock {
+    pub fn auth_enabled(&self) -> bool {
+        self.config.auth.enabled
+    }
+
+    pub fn principal_header(&self) -> Option<&str> {
+        self.config.acl.principal_header.as_deref()
+    }
+
+    pub fn authenticate(&self, token: &str) -> Result<Principal> {
+        if self
+            .config
+            .auth
+            .admin_token
+            .as_ref()
+            .is_some_and(|admin_token| constant_time_eq(admin_token.as_bytes(), token.as_bytes()))
+        {
+            return Ok(Principal {
+                name: "admin".to_string(),
+                admin: true,
+            });
+        }
+
+        self.credentials
+            .authenticate(token)
+            .ok_or(Error::Unauthorized)
+    }
+
+    pub async fn create_token(&mut self, name: &str, admin: bool) -> Result<CreateTokenResponse> {
+        if name.is_empty() {
+            return Err(Error::InvalidTokenName(name.to_string()));
+        }
+
+        if self.credentials.tokens.contains_key(name) {
+            return Err(Error::TokenNameInUse(name.to_string()));
+        }
+
+        let token = generate_token();
+
+        info!("Creating token with name {name}");
+        self.append_credential(CredentialEntry::CreateToken(CreateTokenEntry {
+            name: name.to_string(),
+            hash: hash_token(&token),
+            admin,
+        }))
+        .await?;
+
+        Ok(CreateTokenResponse {
+            name: name.to_string(),
+            admin,
+            token,
+        })
+    }
+
+    pub async fn revoke_token(&mut self, name: &str) -> Result<()> {
+        if !self.credentials.tokens.contains_key(name) {
+            return Err(Error::TokenNameNotFound(name.to_string()));
+        }
+
+        info!("Revoking token with name {name}");
+        self.append_credential(CredentialEntry::RevokeToken(RevokeTokenEntry {
+            name: name.to_string(),
+        }))
+        .await
+    }
+
+    pub fn token_states(&self) -> Vec<TokenState> {
+        self.credentials.states()
+    }
+
+    async fn append_credential(&mut self, entry: CredentialEntry) -> Result<()> {
+        if !self.topic_ids.contains_key(CREDENTIALS_TOPIC) {
+            info!("Creating {CREDENTIALS_TOPIC} topic");
+            self.create_topic_internal(None, CREDENTIALS_TOPIC, Some(1), TopicConfig::default())
+                .await?;
+        }
+
+        let log_dir = self
+            .get_topic_by_name(CREDENTIALS_TOPIC)?
+            .log_dir(0)?
+            .clone();
+        self.check_disk_space(&log_dir)?;
+
+        let disk_space = self.disk_space.clone();
+        let topic = self.get_topic_by_name_mut(CREDENTIALS_TOPIC)?;
+
+        let record = topic
+            .append(
+                0,
+                Bytes::new(),
+                serde_json::to_string(&entry)
+                    .expect("serde_json to_string failed")
+                    .into(),
+                Vec::new(),
+            )
+            .await
+            .inspect_err(|e| {
+                warn!("Failed to append credential entry {e}");
+                disk_space.handle_append_error(&log_dir, e);
+            })?;
+
+        debug!("Appended credential entry with offset {}", record.offset);
+
+        self.credentials.apply(entry);
+
+        Ok(())
+    }
+}
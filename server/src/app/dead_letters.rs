@@ -0,0 +1,185 @@
+use bytes::Bytes;
+use shared::{
+    consts::{
+        DEAD_LETTER_ATTEMPTS_HEADER, DEAD_LETTER_ERROR_HEADER, DEAD_LETTER_OFFSET_HEADER,
+        DEAD_LETTER_PARTITION_HEADER, DEAD_LETTER_TOPIC_HEADER, DEAD_LETTER_TOPIC_SUFFIX,
+        DELIVER_AT_HEADER, EXPIRES_AT_HEADER,
+    },
+    data::{acl::AclOperation, identifier::Identifier, topic_config::TopicConfig},
+    response::dead_letter_response::DeadLetterResponse,
+};
+use tracing::{info, warn};
+
+use crate::{
+    auth::Principal,
+    dur::record::{Record, RecordHeader},
+};
+
+use super::{
+    AppLock,
+    error::{Error, Result},
+};
+
+impl AppLock {
+    /// Copies a record a consumer could not process to the dead-letter topic of its topic, with
+    /// headers describing where it came from and why it failed. The record stays in its topic
+    pub async fn dead_letter_record(
+        &mut self,
+        principal: &Principal,
+        identifier: &Identifier,
+        partition_id: u64,
+        offset: u64,
+        error: &str,
+        attempts: u64,
+    ) -> Result<DeadLetterResponse> {
+        let topic = self.get_topic(identifier)?;
+
+        if topic.is_internal() {
+            return Err(Error::InternalTopicName(topic.name().to_string()));
+        }
+
+        self.authorize(principal, AclOperation::Fetch, topic.name())?;
+
+        let topic_name = topic.name().to_string();
+        let dead_letter_name = dead_letter_topic_name(&topic_name);
+        self.authorize(principal, AclOperation::Produce, &dead_letter_name)?;
+
+        let record = self
+            .read_exact(identifier, partition_id, offset)
+            .await?
+            .ok_or(Error::RecordNotFound(partition_id, offset))?;
+
+        let dead_letter_partition_id = self
+            .create_dead_letter_topic(&dead_letter_name, partition_id)
+            .await?;
+        let headers = dead_letter_headers(
+            record.headers,
+            &topic_name,
+            partition_id,
+            offset,
+            error,
+            attempts,
+        );
+
+        let dead_letter_offset = self
+            .produce_record(
+                principal,
+                Identifier::Name(dead_letter_name.clone()),
+                dead_letter_partition_id,
+                record.key,
+                record.value,
+                headers,
+            )
+            .await?;
+
+        info!(
+            "Copied record at offset {offset} of {topic_name} partition {partition_id} to \
+             {dead_letter_name}"
+        );
+
+        Ok(DeadLetterResponse {
+            topic: dead_letter_name,
+            partition_id: dead_letter_partition_id,
+            offset: dead_letter_offset,
+        })
+    }
+
+    /// Moves a queue message that was delivered too often to the dead-letter topic. Unlike
+    /// [`AppLock::dead_letter_record`] it is not subject to the ACLs and quotas of a worker
+    pub(super) async fn dead_letter_message(
+        &mut self,
+        topic_id: u64,
+        partition_id: u64,
+        record: Record,
+        deliveries: u64,
+    ) -> Result<()> {
+        let topic_name = self.get_topic_by_id(topic_id)?.name().to_string();
+        let dead_letter_name = dead_letter_topic_name(&topic_name);
+        let dead_letter_partition_id = self
+            .create_dead_letter_topic(&dead_letter_name, partition_id)
+            .await?;
+        let headers = dead_letter_headers(
+            record.headers,
+            &topic_name,
+            partition_id,
+            record.offset,
+            &format!("Not acked after {deliveries} deliveries"),
+            deliveries,
+        );
+
+        let dead_letter_topic = self.get_topic_by_name(&dead_letter_name)?;
+        let log_dir = dead_letter_topic.log_dir(dead_letter_partition_id)?.clone();
+        self.check_disk_space(&log_dir)?;
+
+        let disk_space = self.disk_space.clone();
+        let dead_letter_topic = self.get_topic_by_name_mut(&dead_letter_name)?;
+        let dead_letter_id = dead_letter_topic.id();
+        let dead_letter = dead_letter_topic
+            .append(dead_letter_partition_id, record.key, record.value, headers)
+            .await
+            .inspect_err(|e| {
+                warn!("Failed to append dead letter {e}");
+                disk_space.handle_append_error(&log_dir, e);
+            })?;
+
+        info!(
+            "Moved record at offset {} of {topic_name} partition {partition_id} to \
+             {dead_letter_name} after {deliveries} deliveries",
+            record.offset
+        );
+
+        self.notify_listeners(dead_letter_id, dead_letter_partition_id, dead_letter);
+
+        Ok(())
+    }
+
+    /// Creates the dead-letter topic on first use, returns the partition records of
+    /// `partition_id` go to so they keep their order
+    async fn create_dead_letter_topic(
+        &mut self,
+        dead_letter_name: &str,
+        partition_id: u64,
+    ) -> Result<u64> {
+        if !self.topic_ids.contains_key(dead_letter_name) {
+            info!("Creating dead-letter topic {dead_letter_name}");
+            self.create_topic_internal(None, dead_letter_name, None, TopicConfig::default())
+                .await?;
+        }
+
+        let partition_count = self.get_topic_by_name(dead_letter_name)?.partition_count();
+
+        Ok(partition_id % partition_count)
+    }
+}
+
+fn dead_letter_topic_name(topic_name: &str) -> String {
+    format!("{topic_name}{DEAD_LETTER_TOPIC_SUFFIX}")
+}
+
+/// The headers of the record followed by the reserved dead-letter headers. The delivery headers
+/// are left out, a dead letter would otherwise expire or hold back its dead-letter partition
+fn dead_letter_headers(
+    mut headers: Vec<RecordHeader>,
+    topic_name: &str,
+    partition_id: u64,
+    offset: u64,
+    error: &str,
+    attempts: u64,
+) -> Vec<RecordHeader> {
+    headers.retain(|header| header.key != EXPIRES_AT_HEADER && header.key != DELIVER_AT_HEADER);
+    headers.extend(
+        [
+            (DEAD_LETTER_TOPIC_HEADER, topic_name.to_string()),
+            (DEAD_LETTER_PARTITION_HEADER, partition_id.to_string()),
+            (DEAD_LETTER_OFFSET_HEADER, offset.to_string()),
+            (DEAD_LETTER_ERROR_HEADER, error.to_string()),
+            (DEAD_LETTER_ATTEMPTS_HEADER, attempts.to_string()),
+        ]
+        .map(|(key, value)| RecordHeader {
+            key: key.to_string(),
+            value: Bytes::from(value),
+        }),
+    );
+
+    headers
+}
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use tokio::{task::JoinHandle, time};
+use tracing::{info, warn};
+
+use crate::{config::LogDir, dur};
+
+use super::{App, AppLock, error::Result};
+
+/// Logs the log directories that went below `min_free_bytes` or back above it
+pub(super) fn log_watermark_crossings(changes: Vec<(LogDir, u64, u64)>, min_free_bytes: u64) {
+    for (log_dir, previous, free_bytes) in changes {
+        match (previous < min_free_bytes, free_bytes < min_free_bytes) {
+            (false, true) => warn!(
+                "{log_dir} has {free_bytes} bytes free, rejecting produces until {min_free_bytes} bytes are free"
+            ),
+            (true, false) => {
+                info!("{log_dir} has {free_bytes} bytes free, accepting produces again")
+            }
+            _ => {}
+        }
+    }
+}
+
+impl AppLock {
+    /// Rejects appends to a log directory below `disk.min_free_bytes`, so a full disk stops
+    /// produces with a clear error instead of failing part way through a write
+    pub(super) fn check_disk_space(&self, log_dir: &LogDir) -> Result<()> {
+        let Some(free_bytes) = self.disk_space.free_bytes(log_dir) else {
+            return Ok(());
+        };
+
+        let min_free_bytes = self.config.disk.min_free_bytes;
+        if free_bytes < min_free_bytes {
+            return Err(dur::error::Error::DiskFull(format!(
+                "{log_dir} has {free_bytes} bytes free, produces need {min_free_bytes}"
+            ))
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+impl App {
+    /// Measures the free space of the log directories, produces resume on their own once space
+    /// is freed
+    pub async fn refresh_disk_space(&self) -> Result<()> {
+        let (disk_space, min_free_bytes) = {
+            let lock = self.read().await;
+            (lock.disk_space.clone(), lock.config.disk.min_free_bytes)
+        };
+
+        let changes = tokio::task::spawn_blocking(move || disk_space.refresh())
+            .await
+            .expect("Disk space refresh panicked")
+            .map_err(dur::error::Error::from)?;
+
+        log_watermark_crossings(changes, min_free_bytes);
+
+        Ok(())
+    }
+
+    /// The interval is read again after every run, so a config reload applies to the next run
+    pub async fn spawn_disk_space_task(&self) -> JoinHandle<()> {
+        let app = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = app.refresh_disk_space().await {
+                    warn!("Failed to check free disk space {e}");
+                }
+
+                let interval = app.read().await.config.disk.check_interval_ms;
+                time::sleep(Duration::from_millis(interval)).await;
+            }
+        })
+    }
+}
@@ -1,4 +1,7 @@
-use shared::data::encoding;
+use shared::{
+    data::{acl::AclOperation, encoding},
+    response::error_response::ErrorCode,
+};
 use thiserror::Error;
 use tokio::sync::broadcast::error::RecvError;
 
@@ -30,6 +33,102 @@ pub enum Error {
     RecvError(#[from] RecvError),
     #[error("Topic name ({0}) is invalid")]
     InvalidName(String),
+    #[error("Missing or invalid authentication token")]
+    Unauthorized,
+    #[error("Admin privileges are required")]
+    AdminRequired,
+    #[error("Token name ({0}) is invalid")]
+    InvalidTokenName(String),
+    #[error("Token name ({0}) is already in use")]
+    TokenNameInUse(String),
+    #[error("Token with name ({0}) not found")]
+    TokenNameNotFound(String),
+    #[error("Principal ({0}) is not allowed to {1} topic ({2})")]
+    AclDenied(String, AclOperation, String),
+    #[error("Acl with id ({0}) not found")]
+    AclNotFound(u64),
+    #[error("Principal ({0}) is invalid")]
+    InvalidPrincipal(String),
+    #[error("Record of {0} bytes exceeds the maximum record size of {1} bytes")]
+    RecordTooLarge(u64, u64),
+    #[error("Record with {0} headers exceeds the maximum of {1} headers")]
+    TooManyHeaders(u64, u64),
+    #[error("Invalid reserved header {0}: {1}")]
+    InvalidReservedHeader(String, String),
+    #[error("Request body exceeds the maximum request size of {0} bytes")]
+    RequestTooLarge(usize),
+    #[error("Invalid topic config: {0}")]
+    InvalidTopicConfig(String),
+    #[error("Topic has {0} partitions, the partition count can not be decreased to {1}")]
+    PartitionCountDecrease(u64, u64),
+    #[error("Trash entry ({0}) not found")]
+    TrashNotFound(String),
+    #[error("Invalid topic archive: {0}")]
+    InvalidArchive(String),
+    #[error("Log directory ({0}) is not configured")]
+    UnknownLogDir(String),
+    #[error("Quota exceeded, retry after {0} ms")]
+    Throttled(u64),
+    #[error("Invalid quota: {0}")]
+    InvalidQuota(String),
+    #[error("Quota of {0} not found")]
+    QuotaNotFound(String),
+    #[error(
+        "Lease ({0}) on partition ({1}) offset ({2}) not found, the message was acked or redelivered"
+    )]
+    LeaseNotFound(u64, u64, u64),
+    #[error("Delay of {0} ms exceeds the maximum queue delay of {1} ms")]
+    QueueDelayTooLong(u64, u64),
+    #[error("Receive of {0} messages exceeds the maximum of {1} messages")]
+    TooManyMessages(u64, u64),
+    #[error("Offset ({0}) is not the start of a segment, roll the segment or pick a segment start")]
+    OffsetNotOnSegmentBoundary(u64),
+    #[error("Record on partition ({0}) offset ({1}) not found")]
+    RecordNotFound(u64, u64),
+}
+
+impl Error {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::Durrability(error) => error.code(),
+            Error::TopicIdNotFound(_) => ErrorCode::TopicNotFound,
+            Error::TopicNameNotFound(_) => ErrorCode::TopicNotFound,
+            Error::MaxTopicIdReached => ErrorCode::MaxTopicIdReached,
+            Error::TopicIdInUse(_) => ErrorCode::TopicIdInUse,
+            Error::TopicNameInUse(_) => ErrorCode::TopicNameInUse,
+            Error::InternalTopicName(_) => ErrorCode::InternalTopicName,
+            Error::ReservedTopicName => ErrorCode::ReservedTopicName,
+            Error::EncodingError(_) => ErrorCode::Encoding,
+            Error::FetchTimeout => ErrorCode::FetchTimeout,
+            Error::RecvError(_) => ErrorCode::Internal,
+            Error::InvalidName(_) => ErrorCode::InvalidTopicName,
+            Error::Unauthorized => ErrorCode::Unauthorized,
+            Error::AdminRequired => ErrorCode::AdminRequired,
+            Error::InvalidTokenName(_) => ErrorCode::InvalidTokenName,
+            Error::TokenNameInUse(_) => ErrorCode::TokenNameInUse,
+            Error::TokenNameNotFound(_) => ErrorCode::TokenNotFound,
+            Error::AclDenied(..) => ErrorCode::AclDenied,
+            Error::AclNotFound(_) => ErrorCode::AclNotFound,
+            Error::InvalidPrincipal(_) => ErrorCode::InvalidPrincipal,
+            Error::RecordTooLarge(..) => ErrorCode::RecordTooLarge,
+            Error::TooManyHeaders(..) => ErrorCode::TooManyHeaders,
+            Error::InvalidReservedHeader(..) => ErrorCode::InvalidReservedHeader,
+            Error::RequestTooLarge(_) => ErrorCode::RequestTooLarge,
+            Error::InvalidTopicConfig(_) => ErrorCode::InvalidTopicConfig,
+            Error::PartitionCountDecrease(..) => ErrorCode::PartitionCountDecrease,
+            Error::TrashNotFound(_) => ErrorCode::TrashNotFound,
+            Error::InvalidArchive(_) => ErrorCode::InvalidArchive,
+            Error::UnknownLogDir(_) => ErrorCode::UnknownLogDir,
+            Error::Throttled(_) => ErrorCode::Throttled,
+            Error::InvalidQuota(_) => ErrorCode::InvalidQuota,
+            Error::QuotaNotFound(_) => ErrorCode::QuotaNotFound,
+            Error::LeaseNotFound(..) => ErrorCode::LeaseNotFound,
+            Error::QueueDelayTooLong(..) => ErrorCode::QueueDelayTooLong,
+            Error::TooManyMessages(..) => ErrorCode::TooManyMessages,
+            Error::OffsetNotOnSegmentBoundary(_) => ErrorCode::OffsetNotOnSegmentBoundary,
+            Error::RecordNotFound(..) => ErrorCode::RecordNotFound,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,14 +1,23 @@
+use std::time::Duration;
+
 use bytes::Bytes;
-use tracing::{debug, warn};
+use tokio::{task::JoinHandle, time};
+use tracing::{debug, info, warn};
 
 use crate::{
     dur::{self, record::Record},
-    meta::MetadataEntry,
+    meta::{Metadata, MetadataEntry, TopicMetadata, snapshot::MetadataSnapshot},
 };
 
-use super::AppLock;
+use super::{App, AppLock, error::Result};
+
+/// Amount of snapshots kept on disk, the metadata log is only truncated up to the oldest one so
+/// startup can fall back to it when the newest snapshot is unreadable
+const SNAPSHOTS_RETAINED: usize = 2;
 
 impl AppLock {
+    /// Not subject to `disk.min_free_bytes`, deleting or trimming topics is how space is freed
+    /// and needs metadata entries. The entries are small, the reserved space is left for them
     pub async fn append_metadata(&mut self, entry: MetadataEntry) -> dur::error::Result<Record> {
         let topic = self
             .get_topic_by_id_mut(0)
@@ -32,4 +41,104 @@ impl AppLock {
             })
             .inspect_err(|e| warn!("Failed to append metadata entry {e}"))
     }
+
+    /// The current metadata state, as it would be rebuilt from the metadata topic
+    pub fn metadata(&self) -> Metadata {
+        Metadata {
+            topics: self
+                .topics
+                .values()
+                .map(|topic| {
+                    (topic.id(), TopicMetadata {
+                        topic_id: topic.id(),
+                        name: topic.name().to_string(),
+                        partitions: topic.partition_count(),
+                        config: topic.topic_config().clone(),
+                        log_dirs: topic
+                            .placements()
+                            .into_iter()
+                            .enumerate()
+                            .map(|(partition_id, (log_dir, _))| {
+                                (partition_id as u64, log_dir.to_string())
+                            })
+                            .collect(),
+                    })
+                })
+                .collect(),
+            acls: self.acls.clone(),
+            quotas: self.quota_states(),
+        }
+    }
+
+    /// Writes a snapshot of the metadata when there are entries after the last snapshot, returns
+    /// the offset of the latest snapshot. Metadata segments covered by the retained snapshots are
+    /// removed afterwards
+    pub async fn snapshot_metadata(&mut self) -> Result<Option<u64>> {
+        let Some(offset) = self.get_topic_by_id(0)?.max_offset(0)? else {
+            return Ok(self.metadata_snapshot_offset);
+        };
+
+        if self.metadata_snapshot_offset == Some(offset) {
+            return Ok(Some(offset));
+        }
+
+        MetadataSnapshot {
+            offset,
+            metadata: self.metadata(),
+        }
+        .write(&self.config)
+        .await
+        .map_err(dur::error::Error::from)?;
+        self.metadata_snapshot_offset = Some(offset);
+        info!("Wrote metadata snapshot at offset {offset}");
+
+        let offsets = MetadataSnapshot::remove_old(&self.config, SNAPSHOTS_RETAINED)
+            .await
+            .map_err(dur::error::Error::from)?;
+
+        if offsets.len() >= SNAPSHOTS_RETAINED {
+            let removed = self
+                .get_topic_by_id_mut(0)?
+                .delete_segments_before(0, offsets[0] + 1)
+                .await?;
+            debug!("Removed {removed} metadata segments covered by snapshots");
+        }
+
+        Ok(Some(offset))
+    }
+
+    /// Only snapshots once enough entries were appended since the last snapshot
+    async fn snapshot_metadata_if_needed(&mut self) -> Result<Option<u64>> {
+        let Some(offset) = self.get_topic_by_id(0)?.max_offset(0)? else {
+            return Ok(None);
+        };
+
+        let entries = match self.metadata_snapshot_offset {
+            Some(snapshot_offset) => offset.saturating_sub(snapshot_offset),
+            None => offset + 1,
+        };
+
+        if entries < self.config.metadata.snapshot_min_entries {
+            return Ok(None);
+        }
+
+        self.snapshot_metadata().await
+    }
+}
+
+impl App {
+    pub async fn spawn_metadata_snapshot_task(&self) -> JoinHandle<()> {
+        let app = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = app.write().await.snapshot_metadata_if_needed().await {
+                    warn!("Failed to snapshot metadata {e}");
+                }
+
+                let interval = app.read().await.config.metadata.snapshot_interval_ms;
+                time::sleep(Duration::from_millis(interval)).await;
+            }
+        })
+    }
 }
@@ -0,0 +1,112 @@
+use shared::data::identifier::Identifier;
+
+use super::AppLock;
+use crate::metrics::{METRICS, MetricsWriter};
+
+/// Label used for requests on topics that do not exist, so bogus names do not create new series
+const UNKNOWN_TOPIC: &str = "unknown";
+
+impl AppLock {
+    /// Name of the topic used as metrics label
+    pub fn topic_label(&self, identifier: &Identifier) -> String {
+        let topic_id = match identifier {
+            Identifier::Name(name) => self.topic_ids.get(name).copied(),
+            Identifier::Id(topic_id) => Some(*topic_id),
+        };
+
+        topic_id
+            .and_then(|topic_id| self.topics.get(&topic_id))
+            .map(|topic| topic.name().to_string())
+            .unwrap_or(UNKNOWN_TOPIC.to_string())
+    }
+
+    /// Encodes the process wide metrics together with gauges read from the current state
+    pub fn encode_metrics(&self) -> String {
+        let mut writer = MetricsWriter::default();
+        METRICS.encode(&mut writer);
+
+        let mut topics = self.topics.values().collect::<Vec<_>>();
+        topics.sort_by(|a, b| a.name().cmp(b.name()));
+        let states = topics.iter().map(|topic| topic.state()).collect::<Vec<_>>();
+
+        writer.header(
+            "pigeon_partition_log_end_offset",
+            "Offset of the next record appended to the partition",
+            "gauge",
+        );
+        for state in &states {
+            for partition in &state.partitions {
+                let partition_id = partition.partition_id.to_string();
+                writer.sample(
+                    "pigeon_partition_log_end_offset",
+                    &[("topic", &state.name), ("partition", &partition_id)],
+                    partition.current_offset,
+                );
+            }
+        }
+
+        writer.header(
+            "pigeon_partition_segments",
+            "Segments on disk per partition",
+            "gauge",
+        );
+        for state in &states {
+            for partition in &state.partitions {
+                let partition_id = partition.partition_id.to_string();
+                writer.sample(
+                    "pigeon_partition_segments",
+                    &[("topic", &state.name), ("partition", &partition_id)],
+                    partition.segment_count,
+                );
+            }
+        }
+
+        writer.header(
+            "pigeon_topic_disk_usage_bytes",
+            "Bytes used on disk by the logs and indexes of a topic",
+            "gauge",
+        );
+        for topic in &topics {
+            writer.sample(
+                "pigeon_topic_disk_usage_bytes",
+                &[("topic", topic.name())],
+                topic.disk_usage(),
+            );
+        }
+
+        writer.header(
+            "pigeon_log_dir_free_bytes",
+            "Bytes free on the filesystem of a log directory as of the last check",
+            "gauge",
+        );
+        for log_dir in self.config.all_log_dirs() {
+            if let Some(free_bytes) = self.disk_space.free_bytes(&log_dir) {
+                writer.sample(
+                    "pigeon_log_dir_free_bytes",
+                    &[("log_dir", log_dir.path())],
+                    free_bytes,
+                );
+            }
+        }
+
+        writer.header(
+            "pigeon_fetch_subscribers",
+            "Fetch requests currently long-polling a topic",
+            "gauge",
+        );
+        for topic in &topics {
+            let subscribers = self
+                .listeners
+                .get(&topic.id())
+                .map(|sender| sender.receiver_count())
+                .unwrap_or(0);
+            writer.sample(
+                "pigeon_fetch_subscribers",
+                &[("topic", topic.name())],
+                subscribers,
+            );
+        }
+
+        writer.finish()
+    }
+}
@@ -1,93 +1,210 @@
+mod acls;
+mod archive;
+mod backups;
+mod credentials;
+mod dead_letters;
+mod disk;
 pub mod error;
 mod metadata;
+mod metrics;
+mod placement;
+mod queues;
+mod quotas;
+mod reload;
+mod retention;
+mod segments;
+mod shutdown;
+mod tiering;
 mod topics;
+mod trash;
 
 use std::{
-    collections::HashMap,
-    fs::{self, remove_dir_all},
-    path::Component,
+    collections::{BTreeMap, HashMap},
     sync::Arc,
 };
 
-use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, broadcast};
+use disk::log_watermark_crossings;
+use placement::{LogDirUsage, remove_interrupted_moves};
+use quotas::QuotaBuckets;
+use shared::{
+    data::{
+        quota::{Quota, QuotaEntity},
+        topic_config::TopicConfig,
+    },
+    state::acl_state::AclState,
+};
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, broadcast, watch};
 use tracing::{debug, info, warn};
 
 use crate::{
+    auth::{CREDENTIALS_TOPIC, Credentials},
     config::Config,
-    dur::{self, record::Record, topic::Topic},
-    meta::Metadata,
+    dur::{
+        self, disk::DiskSpace, inspect::find_log_dir, record::Record, topic::Topic,
+        trash::move_topic_to_trash,
+    },
+    meta::{
+        Metadata, MetadataEntry,
+        place_partitions_entry::PlacePartitionsEntry,
+        recovery::{RecoveryReport, quarantine, scan_topic_dirs},
+        snapshot::MetadataSnapshot,
+    },
+    queue::{QUEUES_TOPIC, Queues},
 };
 
 pub struct App {
     app: Arc<RwLock<AppLock>>,
+    /// Kept outside of the lock so shutdown can be signalled while a request holds it
+    shutdown: watch::Sender<bool>,
 }
 
 impl App {
-    pub async fn load_from_disk(config: Config) -> Result<Self, dur::error::Error> {
+    pub async fn load_from_disk(mut config: Config) -> Result<Self, dur::error::Error> {
         debug!("Loading App with config: {:#?}", config);
+        if let Some(storage) = &config.tiering.storage {
+            config.tiering.backend = Some(dur::storage::open(storage)?);
+        }
         let config = Arc::new(config);
 
         debug!("Loading metadata topic from disk");
-        let mut metadata_topic = Topic::load_from_disk(config.clone(), 0, "__metadata", 1).await?;
+        let metadata_topic =
+            Topic::load_from_disk(config.clone(), 0, "__metadata", vec![config.base_log_dir()])
+                .await?;
 
-        debug!("Loading metadata records");
-        let metadata_messages = metadata_topic
-            .read_all_from_partition(0)
-            .await
-            .expect("Failed to initialise metadata");
+        debug!("Loading metadata snapshot");
+        let (mut metadata, metadata_snapshot_offset) =
+            match MetadataSnapshot::load_latest(&config).await? {
+                Some(snapshot) => {
+                    info!("Loaded metadata snapshot at offset {}", snapshot.offset);
+                    (snapshot.metadata, Some(snapshot.offset))
+                }
+                None => (Metadata::default(), None),
+            };
+        let replay_offset = metadata_snapshot_offset.map_or(0, |offset| offset + 1);
+
+        if let Some(min_offset) = metadata_topic.min_offset(0)?
+            && min_offset > replay_offset
+        {
+            return Err(dur::error::Error::MissingLogEntries(
+                min_offset,
+                replay_offset,
+            ));
+        }
+
+        debug!("Loading metadata records from offset {replay_offset}");
+        let metadata_messages = metadata_topic.read_from_partition(0, replay_offset).await?;
 
         debug!("Loading metadata from {} records", metadata_messages.len());
-        let metadata = Metadata::from_records(metadata_messages);
+        let mut report = RecoveryReport {
+            invalid_entries: metadata.apply_records(metadata_messages),
+            ..Default::default()
+        };
+        quarantine(&config, &report.invalid_entries).await?;
         debug!("Loaded metadata: {metadata:#?}");
 
+        let topic_dirs = scan_topic_dirs(&config, &mut report.unexpected_paths).await?;
+        if config.metadata.rebuild {
+            info!(
+                "Rebuilding metadata from {} topic directories",
+                topic_dirs.len()
+            );
+            report.rebuilt_entries = metadata.rebuild(&topic_dirs);
+        }
+
+        remove_interrupted_moves(&config).await?;
+
         debug!("Loading {} topics from disk", metadata.topics.len());
         let mut topics = HashMap::new();
         let mut topic_ids = HashMap::new();
-        for (key, topic_metadata) in metadata.topics {
-            let topic = Topic::load_from_disk(
+        let mut usage = LogDirUsage::new(&config);
+        let mut placements = Vec::new();
+        let mut topic_metadatas = metadata.topics.into_iter().collect::<Vec<_>>();
+        topic_metadatas.sort_by_key(|(key, _)| *key);
+        for (key, topic_metadata) in topic_metadatas {
+            let mut log_dirs = Vec::new();
+            let mut moved = BTreeMap::new();
+            for partition_id in 0..topic_metadata.partitions {
+                let recorded = topic_metadata.log_dirs.get(&partition_id);
+                let log_dir = match key {
+                    0 => config.base_log_dir(),
+                    _ => {
+                        match find_log_dir(&config, key, partition_id, recorded.map(String::as_str))
+                            .await?
+                        {
+                            Some(log_dir) => log_dir,
+                            None => {
+                                warn!(
+                                    "Partition {partition_id} of topic {key} is missing, creating it"
+                                );
+                                usage.place()
+                            }
+                        }
+                    }
+                };
+
+                if key != 0 && recorded != Some(&log_dir.to_string()) {
+                    moved.insert(partition_id, log_dir.to_string());
+                }
+                log_dirs.push(log_dir);
+            }
+
+            let mut topic = Topic::load_from_disk(
                 config.clone(),
                 topic_metadata.topic_id,
                 &topic_metadata.name,
-                topic_metadata.partitions,
+                log_dirs,
             )
-            .await
-            .expect("Failed to load topic during startup");
+            .await?;
+            topic.set_config(topic_metadata.config);
+            usage.add(&topic);
+
+            if !moved.is_empty() {
+                placements.push(MetadataEntry::PlacePartitions(PlacePartitionsEntry {
+                    topic_id: key,
+                    log_dirs: moved,
+                }));
+            }
 
             topics.insert(key, topic);
             topic_ids.insert(topic_metadata.name, topic_metadata.topic_id);
         }
 
-        let topic_folders = fs::read_dir(config.topics_path())?;
-        for folder in topic_folders {
-            let folder = folder?;
-
-            let path = folder.path();
-            let component = path
-                .components()
-                .next_back()
-                .expect("Expected at least some components in path");
-
-            if let Component::Normal(dir_name) = component {
-                let id: u64 = dir_name
-                    .to_str()
-                    .expect("OsStr contained unexpected characters")
-                    .parse()
-                    .expect("Invalid path in topics path");
-
-                if !topics.contains_key(&id) {
-                    warn!(
-                        "Found path without corresponding topic, removing {}",
-                        path.display()
-                    );
-
-                    remove_dir_all(path).expect("Failed to remove topic dir");
-                }
-            } else {
-                warn!("unexpected path component {component:?}");
+        for &topic_id in topic_dirs.keys() {
+            // The metadata topic is loaded before its own entry exists
+            if topic_id == 0 || topics.contains_key(&topic_id) {
+                continue;
             }
+
+            let path = config.topic_path(topic_id);
+            // A skipped entry could have created this topic, so the data is kept for a rebuild
+            if !report.invalid_entries.is_empty() {
+                warn!("Found path without corresponding topic, keeping {path}");
+                report.kept_topic_dirs.push(topic_id);
+                continue;
+            }
+
+            warn!("Found path without corresponding topic, moving {path} to trash");
+            let state = move_topic_to_trash(
+                &config,
+                topic_id,
+                None,
+                TopicConfig::default(),
+                topic_dirs[&topic_id],
+            )
+            .await?;
+            report.trashed_topic_dirs.push(state.trash_id);
         }
 
         let next_topic_id = *topics.keys().max().unwrap_or(&0);
+        let acls = metadata.acls;
+        let quotas = metadata
+            .quotas
+            .into_iter()
+            .map(|state| (state.entity, state.quota))
+            .collect();
+
+        let disk_space = Arc::new(DiskSpace::new(&config));
+        log_watermark_crossings(disk_space.refresh()?, config.disk.min_free_bytes);
 
         info!("Finished initialising App state from disk");
         info!("Loaded {} topics", topics.len());
@@ -97,17 +214,56 @@ impl App {
             topic_ids,
             next_topic_id,
             listeners: HashMap::new(),
+            credentials: Credentials::default(),
+            queues: Queues::default(),
+            acls,
+            metadata_snapshot_offset,
+            disk_space,
+            quotas,
+            quota_buckets: QuotaBuckets::default(),
         };
 
-        if app.topics.is_empty() {
+        for entry in &report.rebuilt_entries {
+            app.append_metadata(entry.clone()).await?;
+        }
+        for entry in placements {
+            app.append_metadata(entry).await?;
+        }
+
+        if !app.topics.contains_key(&0) {
             info!("No metadata topic found, creating __metadata");
-            app.create_topic_internal(Some(0), "__metadata", Some(1))
+            app.create_topic_internal(Some(0), "__metadata", Some(1), TopicConfig::default())
                 .await
                 .expect("Failed to initialise __metadata");
         }
 
+        if let Ok(topic) = app.get_topic_by_name_mut(CREDENTIALS_TOPIC) {
+            debug!("Loading credential records");
+            let credential_messages = topic.read_all_from_partition(0).await?;
+
+            app.credentials = Credentials::from_records(credential_messages);
+            info!("Loaded {} credentials", app.credentials.tokens.len());
+        }
+
+        if let Ok(topic) = app.get_topic_by_name_mut(QUEUES_TOPIC) {
+            debug!("Loading queue records");
+            let queue_messages = topic.read_all_from_partition(0).await?;
+
+            app.queues = Queues::from_records(queue_messages);
+            // A topic deleted before its queue entry was appended
+            app.queues
+                .queues
+                .retain(|topic_id, _| app.topics.contains_key(topic_id));
+            info!("Loaded {} queues", app.queues.queues.len());
+        }
+
+        if !report.is_empty() {
+            warn!("{report}");
+        }
+
         Ok(Self {
             app: Arc::new(RwLock::new(app)),
+            shutdown: watch::Sender::new(false),
         })
     }
 
@@ -124,6 +280,7 @@ impl Clone for App {
     fn clone(&self) -> Self {
         App {
             app: self.app.clone(),
+            shutdown: self.shutdown.clone(),
         }
     }
 }
@@ -134,6 +291,13 @@ pub struct AppLock {
     topics: HashMap<u64, Topic>,
     topic_ids: HashMap<String, u64>,
     listeners: HashMap<u64, broadcast::Sender<(u64, Arc<Record>)>>,
+    credentials: Credentials,
+    queues: Queues,
+    acls: BTreeMap<u64, AclState>,
+    metadata_snapshot_offset: Option<u64>,
+    disk_space: Arc<DiskSpace>,
+    quotas: BTreeMap<QuotaEntity, Quota>,
+    quota_buckets: QuotaBuckets,
 }
 
 #[cfg(test)]
@@ -0,0 +1,223 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{self, ErrorKind},
+    ops::Range,
+};
+
+use shared::{
+    data::{identifier::Identifier, timestamp::Timestamp},
+    state::{log_dir_state::LogDirState, partition_state::PartitionState},
+};
+use tokio::fs;
+use tracing::{info, warn};
+
+use crate::{
+    config::{Config, LogDir},
+    dur::{self, inspect::find_log_dir, topic::Topic},
+    meta::{MetadataEntry, place_partitions_entry::PlacePartitionsEntry},
+};
+
+use super::{
+    App, AppLock,
+    error::{Error, Result},
+};
+
+/// Disk usage and partition count of the configured log directories
+pub(super) struct LogDirUsage(Vec<(LogDir, u64, usize)>);
+
+impl LogDirUsage {
+    pub(super) fn new(config: &Config) -> Self {
+        Self(
+            config
+                .log_dirs()
+                .into_iter()
+                .map(|log_dir| (log_dir, 0, 0))
+                .collect(),
+        )
+    }
+
+    pub(super) fn add(&mut self, topic: &Topic) {
+        for (log_dir, disk_usage) in topic.placements() {
+            if let Some(usage) = self.0.iter_mut().find(|usage| usage.0 == log_dir) {
+                usage.1 += disk_usage;
+                usage.2 += 1;
+            }
+        }
+    }
+
+    /// The directory for a new partition, the one with the least bytes and then the fewest
+    /// partitions. The partition is counted right away so a new topic is spread out
+    pub(super) fn place(&mut self) -> LogDir {
+        let usage = self
+            .0
+            .iter_mut()
+            .min_by_key(|usage| (usage.1, usage.2))
+            .expect("At least one log directory is configured");
+        usage.2 += 1;
+
+        usage.0.clone()
+    }
+}
+
+/// Removes copies of partitions whose move was interrupted, the partitions are still read from
+/// their old directory
+pub(super) async fn remove_interrupted_moves(config: &Config) -> io::Result<()> {
+    for log_dir in config.all_log_dirs() {
+        match fs::remove_dir_all(log_dir.moving_path()).await {
+            Ok(()) => warn!("Removed interrupted partition moves in {log_dir}"),
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+impl AppLock {
+    pub(super) fn log_dir_usage(&self) -> LogDirUsage {
+        let mut usage = LogDirUsage::new(&self.config);
+        for topic in self.topics.values() {
+            usage.add(topic);
+        }
+
+        usage
+    }
+
+    pub fn log_dir_states(&self) -> Vec<LogDirState> {
+        self.log_dir_usage()
+            .0
+            .into_iter()
+            .map(|(log_dir, disk_usage, partitions)| LogDirState {
+                free_bytes: self.disk_space.free_bytes(&log_dir).unwrap_or_default(),
+                path: log_dir.to_string(),
+                partitions,
+                disk_usage,
+            })
+            .collect()
+    }
+
+    /// Log directories for new partitions of a topic. Partitions that are already on disk, such
+    /// as a restored trash entry, stay where they are. The metadata topic is always kept in `path`
+    pub(super) async fn place_partitions(
+        &self,
+        topic_id: u64,
+        partition_ids: Range<u64>,
+    ) -> Result<Vec<LogDir>> {
+        if topic_id == 0 {
+            return Ok(partition_ids.map(|_| self.config.base_log_dir()).collect());
+        }
+
+        let mut usage = self.log_dir_usage();
+        let mut log_dirs = Vec::new();
+        for partition_id in partition_ids {
+            let log_dir = find_log_dir(&self.config, topic_id, partition_id, None)
+                .await
+                .map_err(dur::error::Error::from)?;
+            log_dirs.push(log_dir.unwrap_or_else(|| usage.place()));
+        }
+
+        Ok(log_dirs)
+    }
+
+    /// The log directories of the partitions to record in the metadata, the metadata topic is
+    /// always in `path`
+    pub(super) fn placements(
+        &self,
+        topic_id: u64,
+        partition_ids: Range<u64>,
+    ) -> Result<BTreeMap<u64, String>> {
+        if topic_id == 0 {
+            return Ok(BTreeMap::new());
+        }
+
+        let topic = self.get_topic_by_id(topic_id)?;
+        partition_ids
+            .map(|partition_id| Ok((partition_id, topic.log_dir(partition_id)?.to_string())))
+            .collect()
+    }
+
+    async fn complete_partition_move(
+        &mut self,
+        topic_id: u64,
+        partition_id: u64,
+        target: LogDir,
+        staging: &LogDir,
+        copied: &HashSet<u64>,
+    ) -> Result<PartitionState> {
+        let topic = self.get_topic_by_id_mut(topic_id)?;
+        let source = topic.log_dir(partition_id)?.clone();
+        if source != target {
+            topic
+                .relocate_partition(partition_id, target.clone(), staging, copied)
+                .await?;
+            self.append_metadata(MetadataEntry::PlacePartitions(PlacePartitionsEntry {
+                topic_id,
+                log_dirs: self.placements(topic_id, partition_id..partition_id + 1)?,
+            }))
+            .await?;
+
+            info!("Moved topic {topic_id} partition {partition_id} from {source} to {target}");
+        }
+
+        Ok(self.get_topic_by_id(topic_id)?.state().partitions[partition_id as usize].clone())
+    }
+}
+
+impl App {
+    /// Moves a partition to another log directory while it stays online. Closed segments are
+    /// never written to, so they are copied without holding the lock, only the segments that
+    /// changed in the meantime are copied while appends wait
+    pub async fn move_partition(
+        &self,
+        identifier: &Identifier,
+        partition_id: u64,
+        log_dir: &str,
+    ) -> Result<PartitionState> {
+        let target = LogDir::new(log_dir);
+        let (topic_id, files) = {
+            let lock = self.read().await;
+            if !lock.config.log_dirs().contains(&target) {
+                return Err(Error::UnknownLogDir(log_dir.to_string()));
+            }
+
+            let topic = lock.get_topic(identifier)?;
+            if topic.is_internal() {
+                return Err(Error::InternalTopicName(topic.name().to_string()));
+            }
+
+            (topic.id(), topic.closed_segment_files(partition_id)?)
+        };
+
+        let move_id = format!("{}-{topic_id}-{partition_id}", Timestamp::now().as_micros());
+        let staging = target.moving_dir(&move_id);
+        let target_moving_path = target.moving_path();
+
+        let result = async {
+            fs::create_dir_all(staging.partition_path(topic_id, partition_id))
+                .await
+                .map_err(dur::error::Error::from)?;
+
+            let mut copied = HashSet::new();
+            for files in files {
+                if files.copy_to(&staging).await? {
+                    copied.insert(files.start_offset);
+                }
+            }
+
+            self.write()
+                .await
+                .complete_partition_move(topic_id, partition_id, target, &staging, &copied)
+                .await
+        }
+        .await;
+
+        if let Err(e) = fs::remove_dir_all(staging.path()).await
+            && e.kind() != ErrorKind::NotFound
+        {
+            warn!("Failed to remove {staging}: {e}");
+        }
+        fs::remove_dir(target_moving_path).await.ok();
+
+        result
+    }
+}
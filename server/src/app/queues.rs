@@ -0,0 +1,341 @@
+use bytes::Bytes;
+use shared::{
+    data::{
+        acl::AclOperation, identifier::Identifier, lease::Lease, offset_selection::OffsetSelection,
+        timestamp::Timestamp, topic_config::TopicConfig,
+    },
+    state::queue_state::QueueState,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    auth::Principal,
+    dur::record::{Delivery, Record},
+    queue::{
+        MAX_QUEUE_DELAY_MS, QUEUES_TOPIC, QueueEntry, QueueMessage, ack_entry::AckEntry,
+        delete_queue_entry::DeleteQueueEntry, nack_entry::NackEntry, receive_entry::ReceiveEntry,
+    },
+    record_batch::RecordBatch,
+};
+
+use super::{
+    AppLock,
+    error::{Error, Result},
+};
+
+impl AppLock {
+    /// Hands up to `max_messages` messages of the topic to a worker, they are invisible to other
+    /// receives until the visibility timeout runs out or they are nacked. Messages that were not
+    /// acked in time are redelivered before new ones. `queue.max_messages` bounds a receive
+    pub async fn receive(
+        &mut self,
+        principal: &Principal,
+        identifier: &Identifier,
+        max_messages: usize,
+        visibility_timeout_ms: Option<u64>,
+    ) -> Result<Vec<QueueMessage>> {
+        let topic_id = self.queue_topic_id(principal, identifier)?;
+
+        let limit = self.config.queue.max_messages;
+        if max_messages as u64 > limit {
+            return Err(Error::TooManyMessages(max_messages as u64, limit));
+        }
+
+        let now = Timestamp::now();
+        let visible_at = visible_after(
+            now,
+            visibility_timeout_ms.unwrap_or(self.config.queue.visibility_timeout_ms),
+        )?;
+        let max_deliveries = self.config.queue.max_deliveries;
+
+        let mut messages = Vec::new();
+        for (partition_id, offset, deliveries) in self.queues.visible(topic_id, now) {
+            if messages.len() >= max_messages {
+                break;
+            }
+
+            let record = self
+                .get_topic_by_id(topic_id)?
+                .read_exact(partition_id, offset)
+                .await?;
+            // Removed by retention or expired while waiting for a redelivery
+            let Some(record) = record.filter(|record| record.delivery(now) != Delivery::Expired)
+            else {
+                self.append_queue_entry(QueueEntry::Ack(AckEntry {
+                    topic_id,
+                    partition_id,
+                    offset,
+                }))
+                .await?;
+                continue;
+            };
+
+            if deliveries >= max_deliveries {
+                self.dead_letter_message(topic_id, partition_id, record, deliveries)
+                    .await?;
+                self.append_queue_entry(QueueEntry::Ack(AckEntry {
+                    topic_id,
+                    partition_id,
+                    offset,
+                }))
+                .await?;
+                continue;
+            }
+
+            messages.push(
+                self.lease(topic_id, partition_id, record, deliveries + 1, visible_at)
+                    .await?,
+            );
+        }
+
+        let partition_count = self.get_topic_by_id(topic_id)?.partition_count();
+        for partition_id in 0..partition_count {
+            while messages.len() < max_messages {
+                let cursor = self.queues.cursor(topic_id, partition_id);
+                // Stops after the first segment with deliverable records
+                let mut batch = RecordBatch::new(0, Some(1));
+                self.get_topic_by_id(topic_id)?
+                    .read_batch(&mut batch, &OffsetSelection::From(cursor), partition_id)
+                    .await?;
+
+                let records = batch.take_records(topic_id, partition_id);
+                if records.is_empty() {
+                    break;
+                }
+
+                for record in records.into_iter().take(max_messages - messages.len()) {
+                    messages.push(
+                        self.lease(topic_id, partition_id, record, 1, visible_at)
+                            .await?,
+                    );
+                }
+            }
+        }
+
+        Ok(messages)
+    }
+
+    /// Removes the messages from the queue, fails without acking any of them when a lease was
+    /// acked before or the message was redelivered since
+    pub async fn ack(
+        &mut self,
+        principal: &Principal,
+        identifier: &Identifier,
+        leases: &[Lease],
+    ) -> Result<()> {
+        let topic_id = self.queue_topic_id(principal, identifier)?;
+        self.check_leases(topic_id, leases)?;
+
+        for lease in leases {
+            self.append_queue_entry(QueueEntry::Ack(AckEntry {
+                topic_id,
+                partition_id: lease.partition_id,
+                offset: lease.offset,
+            }))
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Makes the messages visible again after `delay_ms`, they count as delivered once more when
+    /// they are received again
+    pub async fn nack(
+        &mut self,
+        principal: &Principal,
+        identifier: &Identifier,
+        leases: &[Lease],
+        delay_ms: Option<u64>,
+    ) -> Result<()> {
+        let topic_id = self.queue_topic_id(principal, identifier)?;
+        self.check_leases(topic_id, leases)?;
+
+        let visible_at = visible_after(Timestamp::now(), delay_ms.unwrap_or(0))?;
+        for lease in leases {
+            self.append_queue_entry(QueueEntry::Nack(NackEntry {
+                topic_id,
+                partition_id: lease.partition_id,
+                offset: lease.offset,
+                visible_at,
+            }))
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    pub fn queue_state(
+        &self,
+        principal: &Principal,
+        identifier: &Identifier,
+    ) -> Result<QueueState> {
+        let topic = self.get_topic(identifier)?;
+
+        self.authorize(principal, AclOperation::Describe, topic.name())?;
+
+        let now = Timestamp::now();
+        let queue = self.queues.queues.get(&topic.id());
+        let (visible, in_flight) = queue
+            .map(|queue| {
+                queue
+                    .leases
+                    .values()
+                    .partition::<Vec<_>, _>(|lease| lease.visible_at.as_micros() <= now.as_micros())
+            })
+            .unwrap_or_default();
+
+        Ok(QueueState {
+            topic_id: topic.id(),
+            name: topic.name().to_string(),
+            cursors: (0..topic.partition_count())
+                .map(|partition_id| self.queues.cursor(topic.id(), partition_id))
+                .collect(),
+            in_flight: in_flight.len() as u64,
+            visible: visible.len() as u64,
+        })
+    }
+
+    /// Forgets the queue of a deleted topic, so a topic that reuses its id starts empty
+    pub(super) async fn delete_queue(&mut self, topic_id: u64) -> Result<()> {
+        if !self.queues.queues.contains_key(&topic_id) {
+            return Ok(());
+        }
+
+        self.append_queue_entry(QueueEntry::DeleteQueue(DeleteQueueEntry { topic_id }))
+            .await
+    }
+
+    fn queue_topic_id(&self, principal: &Principal, identifier: &Identifier) -> Result<u64> {
+        let topic = self.get_topic(identifier)?;
+
+        if topic.is_internal() {
+            return Err(Error::InternalTopicName(topic.name().to_string()));
+        }
+
+        self.authorize(principal, AclOperation::Fetch, topic.name())?;
+
+        Ok(topic.id())
+    }
+
+    fn check_leases(&self, topic_id: u64, leases: &[Lease]) -> Result<()> {
+        match leases
+            .iter()
+            .find(|lease| !self.queues.is_leased(topic_id, lease))
+        {
+            Some(lease) => Err(Error::LeaseNotFound(
+                lease.lease_id,
+                lease.partition_id,
+                lease.offset,
+            )),
+            None => Ok(()),
+        }
+    }
+
+    async fn lease(
+        &mut self,
+        topic_id: u64,
+        partition_id: u64,
+        record: Record,
+        deliveries: u64,
+        visible_at: Timestamp,
+    ) -> Result<QueueMessage> {
+        let lease = Lease {
+            partition_id,
+            offset: record.offset,
+            lease_id: self.queues.next_lease_id(),
+        };
+
+        self.append_queue_entry(QueueEntry::Receive(ReceiveEntry {
+            topic_id,
+            partition_id,
+            offset: record.offset,
+            lease_id: lease.lease_id,
+            deliveries,
+            visible_at,
+        }))
+        .await?;
+
+        Ok(QueueMessage {
+            lease,
+            deliveries,
+            visible_at,
+            record,
+        })
+    }
+
+    async fn append_queue_entry(&mut self, entry: QueueEntry) -> Result<()> {
+        self.write_queue_entry(&entry).await?;
+        self.queues.apply(entry);
+
+        // The entry is appended, a failed snapshot is retried after the next one
+        if self.queues.entries_since_snapshot() >= self.config.queue.snapshot_min_entries
+            && let Err(e) = self.snapshot_queues().await
+        {
+            warn!("Failed to snapshot queues {e}");
+        }
+
+        Ok(())
+    }
+
+    /// Appends the state of every queue, the segments before it are no longer needed to restore
+    /// the queues and are removed
+    async fn snapshot_queues(&mut self) -> Result<()> {
+        let entry = QueueEntry::Snapshot(self.queues.snapshot());
+        let offset = self.write_queue_entry(&entry).await?;
+        self.queues.apply(entry);
+
+        let removed = self
+            .get_topic_by_name_mut(QUEUES_TOPIC)?
+            .delete_segments_before(0, offset)
+            .await?;
+        info!("Wrote queues snapshot at offset {offset}, removed {removed} segments before it");
+
+        Ok(())
+    }
+
+    /// Returns the offset of the entry
+    async fn write_queue_entry(&mut self, entry: &QueueEntry) -> Result<u64> {
+        if !self.topic_ids.contains_key(QUEUES_TOPIC) {
+            info!("Creating {QUEUES_TOPIC} topic");
+            self.create_topic_internal(None, QUEUES_TOPIC, Some(1), TopicConfig::default())
+                .await?;
+        }
+
+        let log_dir = self.get_topic_by_name(QUEUES_TOPIC)?.log_dir(0)?.clone();
+        self.check_disk_space(&log_dir)?;
+
+        let disk_space = self.disk_space.clone();
+        let topic = self.get_topic_by_name_mut(QUEUES_TOPIC)?;
+
+        let record = topic
+            .append(
+                0,
+                Bytes::new(),
+                serde_json::to_string(entry)
+                    .expect("serde_json to_string failed")
+                    .into(),
+                Vec::new(),
+            )
+            .await
+            .inspect_err(|e| {
+                warn!("Failed to append queue entry {e}");
+                disk_space.handle_append_error(&log_dir, e);
+            })?;
+
+        debug!("Appended queue entry with offset {}", record.offset);
+
+        Ok(record.offset)
+    }
+}
+
+/// The time `delay_ms` after `now`, delays are bounded so the timestamp can not overflow
+fn visible_after(now: Timestamp, delay_ms: u64) -> Result<Timestamp> {
+    if delay_ms > MAX_QUEUE_DELAY_MS {
+        return Err(Error::QueueDelayTooLong(delay_ms, MAX_QUEUE_DELAY_MS));
+    }
+
+    Ok(Timestamp::from(
+        now.as_micros()
+            .saturating_add(delay_ms.saturating_mul(1000)),
+    ))
+}
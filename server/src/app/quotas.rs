@@ -0,0 +1,283 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use shared::{
+    data::quota::{Quota, QuotaEntity},
+    state::quota_state::QuotaState,
+};
+use tracing::info;
+
+use crate::{
+    auth::Principal,
+    meta::{MetadataEntry, delete_quota_entry::DeleteQuotaEntry, set_quota_entry::SetQuotaEntry},
+    metrics::METRICS,
+};
+
+use super::{
+    AppLock,
+    error::{Error, Result},
+};
+
+/// The rate of a quota a bucket is filled at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum QuotaRate {
+    ProduceBytes,
+    FetchBytes,
+    Requests,
+}
+
+impl QuotaRate {
+    fn of(self, quota: &Quota) -> Option<u64> {
+        match self {
+            QuotaRate::ProduceBytes => quota.produce_bytes_per_sec,
+            QuotaRate::FetchBytes => quota.fetch_bytes_per_sec,
+            QuotaRate::Requests => quota.requests_per_sec,
+        }
+    }
+}
+
+/// Holds at most one second of its rate, so an idle client can only burst that much
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, now: Instant) -> Self {
+        Self {
+            tokens: rate,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, rate: f64, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(rate);
+        self.updated = now;
+    }
+
+    /// Time until `amount` can be taken. Amounts larger than one second of the rate only wait for
+    /// a full bucket and leave it in debt
+    fn wait_time(&self, rate: f64, amount: f64) -> Duration {
+        let needed = amount.min(rate);
+        if self.tokens >= needed {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((needed - self.tokens) / rate)
+        }
+    }
+
+    /// Time until the bucket is out of debt
+    fn debt(&self, rate: f64) -> Duration {
+        Duration::from_secs_f64((-self.tokens).max(0.0) / rate)
+    }
+}
+
+/// Token buckets of the clients and topics with a quota. Clients without their own quota that
+/// fall back to the default quota still get a bucket of their own
+#[derive(Debug, Default)]
+pub(super) struct QuotaBuckets(Mutex<HashMap<(QuotaEntity, QuotaRate), TokenBucket>>);
+
+impl QuotaBuckets {
+    fn clear(&self) {
+        self.0.lock().expect("Quota buckets poisoned").clear();
+    }
+}
+
+/// An amount taken from the bucket of an entity
+pub(super) struct Charge {
+    entity: QuotaEntity,
+    rate: QuotaRate,
+    per_sec: f64,
+    amount: f64,
+}
+
+impl AppLock {
+    /// The quota of the entity, or the default quota of its kind when it has none
+    fn quota(&self, entity: &QuotaEntity) -> Option<&Quota> {
+        self.quotas
+            .get(entity)
+            .or_else(|| self.quotas.get(&entity.default_entity()))
+    }
+
+    fn charge(&self, entity: QuotaEntity, rate: QuotaRate, amount: u64) -> Option<Charge> {
+        let per_sec = rate.of(self.quota(&entity)?)?;
+
+        Some(Charge {
+            entity,
+            rate,
+            per_sec: per_sec as f64,
+            amount: amount as f64,
+        })
+    }
+
+    /// Rejects a produce when the client or the topic is over its quota. Returns the charges to
+    /// pass to [`AppLock::charge_produce_quota`] once the record is appended, so a rejected or
+    /// failed produce is not counted
+    pub(super) fn check_produce_quota(
+        &self,
+        principal: &Principal,
+        topic_name: &str,
+        size: u64,
+    ) -> Result<Vec<Charge>> {
+        let client = QuotaEntity::Client(principal.name.clone());
+        let topic = QuotaEntity::Topic(topic_name.to_string());
+        let charges = [
+            self.charge(client.clone(), QuotaRate::Requests, 1),
+            self.charge(client, QuotaRate::ProduceBytes, size),
+            self.charge(topic.clone(), QuotaRate::Requests, 1),
+            self.charge(topic, QuotaRate::ProduceBytes, size),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+        let now = Instant::now();
+        let mut buckets = self.quota_buckets.0.lock().expect("Quota buckets poisoned");
+        let mut wait_time = Duration::ZERO;
+        for charge in &charges {
+            let bucket = buckets
+                .entry((charge.entity.clone(), charge.rate))
+                .or_insert_with(|| TokenBucket::new(charge.per_sec, now));
+            bucket.refill(charge.per_sec, now);
+            wait_time = wait_time.max(bucket.wait_time(charge.per_sec, charge.amount));
+        }
+
+        if !wait_time.is_zero() {
+            METRICS.throttled_requests.inc(topic_name);
+            return Err(Error::Throttled(wait_time.as_millis() as u64 + 1));
+        }
+
+        Ok(charges)
+    }
+
+    /// Takes the charges of an appended record from the buckets checked before the append
+    pub(super) fn charge_produce_quota(&self, charges: Vec<Charge>) {
+        let mut buckets = self.quota_buckets.0.lock().expect("Quota buckets poisoned");
+        for charge in charges {
+            if let Some(bucket) = buckets.get_mut(&(charge.entity, charge.rate)) {
+                bucket.tokens -= charge.amount;
+            }
+        }
+    }
+
+    /// Counts a fetch that returned `topic_bytes` against the quotas of the client and the
+    /// topics, returns how long the response has to be delayed to stay within them
+    pub fn charge_fetch_quota(
+        &self,
+        principal: &Principal,
+        topic_bytes: &[(String, u64)],
+    ) -> Duration {
+        let client = QuotaEntity::Client(principal.name.clone());
+        let total = topic_bytes.iter().map(|(_, bytes)| bytes).sum();
+        let mut charges = vec![
+            self.charge(client.clone(), QuotaRate::Requests, 1),
+            self.charge(client, QuotaRate::FetchBytes, total),
+        ];
+        for (topic_name, bytes) in topic_bytes {
+            let topic = QuotaEntity::Topic(topic_name.to_string());
+            charges.push(self.charge(topic.clone(), QuotaRate::Requests, 1));
+            charges.push(self.charge(topic, QuotaRate::FetchBytes, *bytes));
+        }
+
+        let now = Instant::now();
+        let mut buckets = self.quota_buckets.0.lock().expect("Quota buckets poisoned");
+        charges
+            .into_iter()
+            .flatten()
+            .map(|charge| {
+                let bucket = buckets
+                    .entry((charge.entity, charge.rate))
+                    .or_insert_with(|| TokenBucket::new(charge.per_sec, now));
+                bucket.refill(charge.per_sec, now);
+                bucket.tokens -= charge.amount;
+                bucket.debt(charge.per_sec)
+            })
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Replaces the quota of a client or topic, the buckets start full with the new rates
+    pub async fn set_quota(&mut self, entity: QuotaEntity, quota: Quota) -> Result<QuotaState> {
+        if entity.name().is_empty() {
+            return Err(Error::InvalidQuota("name can not be empty".to_string()));
+        }
+        if [
+            quota.produce_bytes_per_sec,
+            quota.fetch_bytes_per_sec,
+            quota.requests_per_sec,
+        ]
+        .contains(&Some(0))
+        {
+            return Err(Error::InvalidQuota(
+                "rates must be larger than 0".to_string(),
+            ));
+        }
+
+        info!("Setting quota of {entity} to {quota:?}");
+        self.append_metadata(MetadataEntry::SetQuota(SetQuotaEntry {
+            entity: entity.clone(),
+            quota: quota.clone(),
+        }))
+        .await?;
+
+        self.quotas.insert(entity.clone(), quota.clone());
+        self.quota_buckets.clear();
+
+        Ok(QuotaState { entity, quota })
+    }
+
+    pub async fn delete_quota(&mut self, entity: QuotaEntity) -> Result<()> {
+        if !self.quotas.contains_key(&entity) {
+            return Err(Error::QuotaNotFound(entity.to_string()));
+        }
+
+        info!("Deleting quota of {entity}");
+        self.append_metadata(MetadataEntry::DeleteQuota(DeleteQuotaEntry {
+            entity: entity.clone(),
+        }))
+        .await?;
+
+        self.quotas.remove(&entity);
+        self.quota_buckets.clear();
+
+        Ok(())
+    }
+
+    /// Moves the quota of a renamed topic to its new name, so a new topic under the old name does
+    /// not inherit it
+    pub(super) async fn rename_topic_quota(&mut self, old_name: &str, name: &str) -> Result<()> {
+        let old_entity = QuotaEntity::Topic(old_name.to_string());
+        let Some(quota) = self.quotas.get(&old_entity).cloned() else {
+            return Ok(());
+        };
+
+        self.set_quota(QuotaEntity::Topic(name.to_string()), quota)
+            .await?;
+        self.delete_quota(old_entity).await
+    }
+
+    /// Forgets the quota of a deleted topic, so a topic created under its name starts without one
+    pub(super) async fn delete_topic_quota(&mut self, name: &str) -> Result<()> {
+        let entity = QuotaEntity::Topic(name.to_string());
+        if !self.quotas.contains_key(&entity) {
+            return Ok(());
+        }
+
+        self.delete_quota(entity).await
+    }
+
+    pub fn quota_states(&self) -> Vec<QuotaState> {
+        self.quotas
+            .iter()
+            .map(|(entity, quota)| QuotaState {
+                entity: entity.clone(),
+                quota: quota.clone(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,29 @@
+use std::sync::Arc;
+
+use tracing::{info, warn};
+
+use crate::config::{Config, reload::ConfigChanges};
+
+use super::AppLock;
+
+impl AppLock {
+    /// Swaps in a new config, settings that require a restart keep their current value
+    pub fn reload_config(&mut self, mut config: Config) -> ConfigChanges {
+        let changes = config.changes_from(&self.config);
+
+        for key in &changes.applied {
+            info!("Applied config change to {key}");
+        }
+        for key in &changes.restart_required {
+            warn!("Config change to {key} requires a restart to take effect");
+        }
+
+        let config = Arc::new(config);
+        for topic in self.topics.values_mut() {
+            topic.set_broker_config(config.clone());
+        }
+        self.config = config;
+
+        changes
+    }
+}
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use tokio::{task::JoinHandle, time};
+use tracing::{info, warn};
+
+use super::{App, AppLock, error::Result};
+
+impl AppLock {
+    /// Removes segments that fall outside of the retention of their topic, internal topics are
+    /// never cleaned up
+    pub async fn enforce_retention(&mut self) -> Result<usize> {
+        let mut removed = 0;
+
+        for topic in self.topics.values_mut() {
+            if topic.is_internal() {
+                continue;
+            }
+
+            removed += topic.enforce_retention().await?;
+        }
+
+        Ok(removed)
+    }
+}
+
+impl App {
+    /// The interval is read again after every run, so a config reload applies to the next run
+    pub async fn spawn_retention_task(&self) -> JoinHandle<()> {
+        let app = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                match app.write().await.enforce_retention().await {
+                    Ok(0) => {}
+                    Ok(removed) => info!("Retention removed {removed} segments"),
+                    Err(e) => warn!("Failed to enforce retention {e}"),
+                }
+
+                match app.offload_segments().await {
+                    Ok(0) => {}
+                    Ok(offloaded) => info!("Offloaded {offloaded} segments to tiered storage"),
+                    Err(e) => warn!("Failed to offload segments {e}"),
+                }
+
+                match app.read().await.purge_trash().await {
+                    Ok(0) => {}
+                    Ok(removed) => info!("Purged {removed} trash entries"),
+                    Err(e) => warn!("Failed to purge trash {e}"),
+                }
+
+                let interval = app.read().await.config.retention.check_interval_ms;
+                time::sleep(Duration::from_millis(interval)).await;
+            }
+        })
+    }
+}
@@ -0,0 +1,73 @@
+use shared::{data::identifier::Identifier, state::segment_state::SegmentState};
+use tracing::info;
+
+use super::{
+    AppLock,
+    error::{Error, Result},
+};
+
+impl AppLock {
+    pub fn segment_states(
+        &self,
+        identifier: &Identifier,
+        partition_id: u64,
+    ) -> Result<Vec<SegmentState>> {
+        let topic = self.get_topic(identifier)?;
+
+        Ok(topic.segment_states(partition_id)?)
+    }
+
+    /// Closes the active segment of a partition, a segment without records is not rolled
+    pub async fn roll_segment(
+        &mut self,
+        identifier: &Identifier,
+        partition_id: u64,
+    ) -> Result<Vec<SegmentState>> {
+        let topic = self.get_topic_mut(identifier)?;
+
+        if topic.roll_segment(partition_id).await? {
+            info!(
+                "Rolled segment of topic {} partition {partition_id}",
+                topic.name()
+            );
+        }
+
+        Ok(topic.segment_states(partition_id)?)
+    }
+
+    /// Removes the segments of a partition before `offset`. Records are removed per segment, so
+    /// `offset` has to be the start of a segment. The active segment is kept, roll it first to
+    /// remove it as well
+    pub async fn truncate_partition(
+        &mut self,
+        identifier: &Identifier,
+        partition_id: u64,
+        offset: u64,
+    ) -> Result<Vec<SegmentState>> {
+        let topic = self.get_topic_mut(identifier)?;
+
+        // Internal topics are replayed from the start, truncating them loses state
+        if topic.is_internal() {
+            return Err(Error::InternalTopicName(topic.name().to_string()));
+        }
+
+        // Records before the offset in the same segment could still be fetched
+        let segments = topic.segment_states(partition_id)?;
+        let first_offset = segments.first().map_or(0, |segment| segment.start_offset);
+        if offset > first_offset
+            && !segments
+                .iter()
+                .any(|segment| segment.start_offset == offset)
+        {
+            return Err(Error::OffsetNotOnSegmentBoundary(offset));
+        }
+
+        let removed = topic.delete_segments_before(partition_id, offset).await?;
+        info!(
+            "Truncated {removed} segments of topic {} partition {partition_id} before offset {offset}",
+            topic.name()
+        );
+
+        Ok(topic.segment_states(partition_id)?)
+    }
+}
@@ -0,0 +1,31 @@
+use tokio::sync::watch;
+use tracing::info;
+
+use super::{App, AppLock, error::Result};
+
+impl AppLock {
+    /// Syncs every segment to disk, holding the write lock guarantees no append is in progress
+    pub async fn sync(&self) -> Result<()> {
+        for topic in self.topics.values() {
+            topic.sync().await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl App {
+    /// Marks the app as shutting down, pending long-polls complete with the records they have
+    pub fn shutdown(&self) {
+        info!("Shutting down");
+        self.shutdown.send_replace(true);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+
+    pub fn shutdown_receiver(&self) -> watch::Receiver<bool> {
+        self.shutdown.subscribe()
+    }
+}
@@ -0,0 +1,261 @@
+use shared::data::{
+    acl::{AclOperation, AclPattern},
+    identifier::Identifier,
+    topic_config::TopicConfig,
+};
+use tempfile::tempdir;
+
+use crate::{
+    app::{App, error::Error},
+    auth::Principal,
+    config::Config,
+};
+
+fn principal(name: &str) -> Principal {
+    Principal {
+        name: name.to_string(),
+        admin: false,
+    }
+}
+
+fn acl_config() -> Config {
+    let mut config = Config::default();
+    config.acl.enabled = true;
+    config
+}
+
+#[tokio::test]
+async fn test_acl_denies_without_matching_acl() {
+    let app = App::load_from_disk(acl_config())
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+
+    let result = lock
+        .create_topic(
+            &principal("alice"),
+            None,
+            "foo",
+            None,
+            TopicConfig::default(),
+        )
+        .await;
+    assert!(
+        matches!(result, Err(Error::AclDenied(_, AclOperation::Create, _))),
+        "Expected Error::AclDenied but got {result:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_acl_literal_allows_operation() {
+    let app = App::load_from_disk(acl_config())
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+
+    lock.create_acl("alice", AclPattern::Literal("foo".to_string()), vec![
+        AclOperation::Create,
+        AclOperation::Produce,
+    ])
+    .await
+    .expect("Failed to create_acl");
+
+    let topic_id = lock
+        .create_topic(
+            &principal("alice"),
+            None,
+            "foo",
+            Some(1),
+            TopicConfig::default(),
+        )
+        .await
+        .expect("Failed to create_topic");
+
+    lock.produce(
+        &principal("alice"),
+        Identifier::Id(topic_id),
+        0,
+        "Hello".into(),
+        "World".into(),
+        vec![],
+    )
+    .await
+    .expect("Failed to produce record");
+
+    let result = lock.topic_state(&principal("alice"), &Identifier::Id(topic_id));
+    assert!(matches!(
+        result,
+        Err(Error::AclDenied(_, AclOperation::Describe, _))
+    ));
+
+    let result = lock
+        .produce(
+            &principal("bob"),
+            Identifier::Id(topic_id),
+            0,
+            "Hello".into(),
+            "World".into(),
+            vec![],
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(Error::AclDenied(_, AclOperation::Produce, _))
+    ));
+}
+
+#[tokio::test]
+async fn test_acl_prefix_and_wildcard_principal() {
+    let app = App::load_from_disk(acl_config())
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+
+    lock.create_acl("*", AclPattern::Prefixed("team.".to_string()), vec![
+        AclOperation::Create,
+        AclOperation::Describe,
+    ])
+    .await
+    .expect("Failed to create_acl");
+
+    lock.create_topic(
+        &principal("alice"),
+        None,
+        "team.events",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+
+    let result = lock
+        .create_topic(
+            &principal("bob"),
+            None,
+            "other.events",
+            Some(1),
+            TopicConfig::default(),
+        )
+        .await;
+    assert!(matches!(result, Err(Error::AclDenied(..))));
+
+    let states = lock.topic_states(&principal("bob"));
+    assert_eq!(states.len(), 1);
+    assert!(states.values().all(|state| state.name == "team.events"));
+}
+
+#[tokio::test]
+async fn test_admin_bypasses_acls() {
+    let app = App::load_from_disk(acl_config())
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+
+    let admin = Principal {
+        name: "admin".to_string(),
+        admin: true,
+    };
+    lock.create_topic(&admin, None, "foo", Some(1), TopicConfig::default())
+        .await
+        .expect("Failed to create_topic");
+}
+
+#[tokio::test]
+async fn test_acls_persist_on_reload() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = dir.path().to_str().unwrap().to_string();
+
+    let mut config = acl_config();
+    config.path = path.to_string();
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let acl = lock
+        .create_acl("alice", AclPattern::Any, vec![AclOperation::Create])
+        .await
+        .expect("Failed to create_acl");
+    let deleted = lock
+        .create_acl("bob", AclPattern::Any, vec![AclOperation::Create])
+        .await
+        .expect("Failed to create_acl");
+    lock.delete_acl(deleted.acl_id)
+        .await
+        .expect("Failed to delete_acl");
+    drop(lock);
+    drop(app);
+
+    let mut config = acl_config();
+    config.path = path;
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    assert_eq!(lock.acl_states(), vec![acl]);
+
+    lock.create_topic(
+        &principal("alice"),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+}
+
+#[tokio::test]
+async fn test_rename_topic_is_authorized_before_the_name_check() {
+    let app = App::load_from_disk(acl_config())
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+
+    lock.create_acl("alice", AclPattern::Literal("foo".to_string()), vec![
+        AclOperation::Create,
+        AclOperation::Alter,
+    ])
+    .await
+    .expect("Failed to create_acl");
+    let admin = Principal {
+        name: "admin".to_string(),
+        admin: true,
+    };
+    for name in ["foo", "bar"] {
+        lock.create_topic(&admin, None, name, Some(1), TopicConfig::default())
+            .await
+            .expect("Failed to create_topic");
+    }
+
+    // Renaming to a taken name alice cannot create does not reveal that it exists
+    let result = lock
+        .rename_topic(
+            &principal("alice"),
+            &Identifier::Name("foo".to_string()),
+            "bar",
+        )
+        .await;
+    assert!(
+        matches!(result, Err(Error::AclDenied(_, AclOperation::Create, _))),
+        "Expected Error::AclDenied but got {result:?}"
+    );
+
+    let result = lock
+        .rename_topic(
+            &principal("bob"),
+            &Identifier::Name("foo".to_string()),
+            "bar",
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(Error::AclDenied(_, AclOperation::Alter, _))
+    ));
+}
@@ -0,0 +1,104 @@
+use shared::data::{identifier::Identifier, topic_config::TopicConfig};
+
+use crate::{
+    app::{App, error::Error},
+    auth::Principal,
+    config::Config,
+    dur::record::RecordHeader,
+};
+
+#[tokio::test]
+async fn test_export_and_import_topic() {
+    let app = App::load_from_disk(Config::default())
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let principal = Principal::anonymous();
+    lock.create_topic(&principal, None, "foo", Some(2), TopicConfig::default())
+        .await
+        .expect("Failed to create_topic");
+
+    for i in 0..3 {
+        lock.produce(
+            &principal,
+            Identifier::Name("foo".to_string()),
+            1,
+            format!("key-{i}").into(),
+            format!("value-{i}").into(),
+            vec![RecordHeader {
+                key: "trace".to_string(),
+                value: "abc".into(),
+            }],
+        )
+        .await
+        .expect("Failed to produce record");
+    }
+
+    let export = lock
+        .export_topic(&Identifier::Name("foo".to_string()))
+        .expect("Failed to export_topic");
+    assert_eq!(export.header.partitions, 2);
+    assert_eq!(export.end_offsets, vec![0, 3]);
+
+    let records = lock
+        .export_records(export.topic_id, 1, 0, 3)
+        .await
+        .expect("Failed to export_records");
+    assert_eq!(records.len(), 3);
+    let timestamps = records
+        .iter()
+        .map(|record| record.timestamp)
+        .collect::<Vec<_>>();
+
+    let topic_id = lock
+        .import_topic(&principal, &export.header, Some("bar"))
+        .await
+        .expect("Failed to import_topic");
+    // Skip the first record, so the kept offsets do not start at 0
+    for record in records.into_iter().skip(1) {
+        lock.import_record(topic_id, record, true)
+            .await
+            .expect("Failed to import_record");
+    }
+
+    let imported = lock
+        .export_records(topic_id, 1, 0, 3)
+        .await
+        .expect("Failed to export_records");
+    assert_eq!(
+        imported
+            .iter()
+            .map(|record| record.offset)
+            .collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+    assert_eq!(imported[0].timestamp, timestamps[1]);
+    assert_eq!(imported[0].headers.len(), 1);
+    assert_eq!(imported[0].headers[0].key, "trace");
+
+    // Offsets have to increase when they are kept
+    let record = lock
+        .export_records(export.topic_id, 1, 0, 1)
+        .await
+        .expect("Failed to export_records")
+        .remove(0);
+    let result = lock.import_record(topic_id, record, true).await;
+    assert!(matches!(result, Err(Error::InvalidArchive(_))));
+
+    // Without keeping offsets the record is appended after the others
+    let record = lock
+        .export_records(export.topic_id, 1, 0, 1)
+        .await
+        .expect("Failed to export_records")
+        .remove(0);
+    lock.import_record(topic_id, record, false)
+        .await
+        .expect("Failed to import_record");
+    let imported = lock
+        .export_records(topic_id, 1, 3, 4)
+        .await
+        .expect("Failed to export_records");
+    assert_eq!(imported.len(), 1);
+    assert_ne!(imported[0].timestamp, timestamps[0]);
+}
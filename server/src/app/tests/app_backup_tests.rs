@@ -0,0 +1,89 @@
+use shared::data::{identifier::Identifier, topic_config::TopicConfig};
+use tempfile::tempdir;
+
+use crate::{
+    app::{App, AppLock},
+    auth::Principal,
+    config::Config,
+};
+
+async fn produce(lock: &mut AppLock, count: usize) {
+    for _ in 0..count {
+        lock.produce(
+            &Principal::anonymous(),
+            Identifier::Name("foo".to_string()),
+            0,
+            "Hello".into(),
+            "World".into(),
+            vec![],
+        )
+        .await
+        .expect("Failed to produce record");
+    }
+}
+
+#[tokio::test]
+async fn test_backup_is_point_in_time() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let config = Config {
+        path: dir.path().to_str().unwrap().to_string(),
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let topic = Identifier::Name("foo".to_string());
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+    lock.snapshot_metadata()
+        .await
+        .expect("Failed to snapshot metadata");
+
+    // One closed segment that is linked and an active segment that is copied
+    produce(&mut lock, 2).await;
+    lock.roll_segment(&topic, 0)
+        .await
+        .expect("Failed to roll segment");
+    produce(&mut lock, 2).await;
+
+    let backup = lock.create_backup().await.expect("Failed to create backup");
+    assert!(backup.metadata_snapshot_offset.is_some());
+    let partition = backup
+        .partitions
+        .iter()
+        .find(|partition| partition.topic_id != 0)
+        .expect("Backup is missing the partition");
+    assert_eq!(partition.end_offset, 4);
+
+    // Records appended after the backup are not part of it
+    produce(&mut lock, 3).await;
+    assert_eq!(lock.backup_states().await.unwrap(), vec![backup.clone()]);
+    drop(lock);
+
+    let restored = App::load_from_disk(Config {
+        path: backup.path.clone(),
+        ..Default::default()
+    })
+    .await
+    .expect("Failed to load backup");
+    let lock = restored.read().await;
+    let foo = lock.get_topic(&topic).expect("Backup is missing the topic");
+    assert_eq!(foo.state().partitions[0].current_offset, 4);
+    assert_eq!(foo.state().partitions[0].segment_count, 2);
+    assert_eq!(
+        foo.read_from_partition(0, 0)
+            .await
+            .expect("Failed to read backup")
+            .len(),
+        4
+    );
+}
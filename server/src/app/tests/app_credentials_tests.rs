@@ -0,0 +1,161 @@
+use tempfile::tempdir;
+
+use crate::{
+    app::{App, error::Error},
+    auth::CREDENTIALS_TOPIC,
+    config::Config,
+};
+
+#[tokio::test]
+async fn test_create_token_and_authenticate() {
+    let config = Config::default();
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+
+    let created = lock
+        .create_token("foo", false)
+        .await
+        .expect("Failed to create_token");
+
+    let principal = lock
+        .authenticate(&created.token)
+        .expect("Failed to authenticate");
+    assert_eq!(principal.name, "foo");
+    assert!(!principal.admin);
+
+    let result = lock.authenticate("invalid");
+    assert!(matches!(result, Err(Error::Unauthorized)));
+}
+
+#[tokio::test]
+async fn test_revoke_token() {
+    let config = Config::default();
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+
+    let created = lock
+        .create_token("foo", true)
+        .await
+        .expect("Failed to create_token");
+
+    lock.revoke_token("foo")
+        .await
+        .expect("Failed to revoke_token");
+
+    let result = lock.authenticate(&created.token);
+    assert!(matches!(result, Err(Error::Unauthorized)));
+
+    let result = lock.revoke_token("foo").await;
+    assert!(matches!(result, Err(Error::TokenNameNotFound(_))));
+}
+
+#[tokio::test]
+async fn test_cannot_create_same_token_name_twice() {
+    let config = Config::default();
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+
+    lock.create_token("foo", false)
+        .await
+        .expect("Failed to create_token");
+
+    let result = lock.create_token("foo", false).await;
+    assert!(matches!(result, Err(Error::TokenNameInUse(_))));
+
+    let result = lock.create_token("", false).await;
+    assert!(matches!(result, Err(Error::InvalidTokenName(_))));
+}
+
+#[tokio::test]
+async fn test_tokens_persist_on_reload() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = dir.path().to_str().unwrap().to_string();
+
+    let config = Config {
+        path: path.to_string(),
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let created = app
+        .write()
+        .await
+        .create_token("foo", true)
+        .await
+        .expect("Failed to create_token");
+    drop(app);
+
+    let config = Config {
+        path,
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let lock = app.read().await;
+    let principal = lock
+        .authenticate(&created.token)
+        .expect("Failed to authenticate");
+    assert_eq!(principal.name, "foo");
+    assert!(principal.admin);
+}
+
+#[tokio::test]
+async fn test_admin_token_from_config() {
+    let mut config = Config::default();
+    config.auth.admin_token = Some("secret".to_string());
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let lock = app.read().await;
+
+    let principal = lock.authenticate("secret").expect("Failed to authenticate");
+    assert!(principal.admin);
+}
+
+#[tokio::test]
+async fn test_corrupt_credential_entries_are_skipped() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let config = || Config {
+        path: dir.path().to_str().unwrap().to_string(),
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config())
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let created = lock
+        .create_token("foo", false)
+        .await
+        .expect("Failed to create_token");
+    lock.get_topic_by_name_mut(CREDENTIALS_TOPIC)
+        .unwrap()
+        .append(0, "".into(), "{not json".into(), vec![])
+        .await
+        .expect("Failed to append");
+    drop(lock);
+
+    let app = App::load_from_disk(config())
+        .await
+        .expect("load_from_disk failed");
+    let principal = app
+        .read()
+        .await
+        .authenticate(&created.token)
+        .expect("Failed to authenticate");
+    assert_eq!(principal.name, "foo");
+}
@@ -0,0 +1,172 @@
+use shared::{
+    consts::{
+        DEAD_LETTER_ATTEMPTS_HEADER, DEAD_LETTER_ERROR_HEADER, DEAD_LETTER_OFFSET_HEADER,
+        DEAD_LETTER_PARTITION_HEADER, DEAD_LETTER_TOPIC_HEADER, EXPIRES_AT_HEADER,
+    },
+    data::{
+        encoding::Encoding, identifier::Identifier, offset_selection::OffsetSelection,
+        timestamp::Timestamp, topic_config::TopicConfig,
+    },
+};
+
+use crate::{
+    app::{App, error::Error},
+    auth::Principal,
+    config::Config,
+    dur::record::RecordHeader,
+    record_batch::RecordBatch,
+};
+
+#[tokio::test]
+async fn test_dead_letter_record() {
+    let app = App::load_from_disk(Config::default())
+        .await
+        .expect("load_from_disk failed");
+    let mut lock = app.write().await;
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(2),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+    lock.produce(
+        &Principal::anonymous(),
+        Identifier::Name("foo".to_string()),
+        1,
+        "Hello".into(),
+        "World".into(),
+        vec![RecordHeader {
+            key: "trace".to_string(),
+            value: "abc".into(),
+        }],
+    )
+    .await
+    .expect("Failed to produce record");
+
+    let result = lock
+        .dead_letter_record(
+            &Principal::anonymous(),
+            &Identifier::Name("foo".to_string()),
+            1,
+            1,
+            "Invalid payload",
+            3,
+        )
+        .await;
+    assert!(matches!(result, Err(Error::RecordNotFound(1, 1))));
+
+    // The dead-letter topic is created on first use and the record stays in its topic
+    let response = lock
+        .dead_letter_record(
+            &Principal::anonymous(),
+            &Identifier::Name("foo".to_string()),
+            1,
+            0,
+            "Invalid payload",
+            3,
+        )
+        .await
+        .expect("Failed to dead-letter record");
+    assert_eq!(response.topic, "foo.dlq");
+    assert_eq!(response.offset, 0);
+
+    let records = lock
+        .get_topic(&Identifier::Name("foo.dlq".to_string()))
+        .unwrap()
+        .read_from_partition(response.partition_id, 0)
+        .await
+        .expect("Failed to read dead letters");
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].value, "World");
+    let headers = records[0]
+        .headers
+        .iter()
+        .map(|header| (header.key.as_str(), header.value.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(headers, vec![
+        ("trace", "abc".into()),
+        (DEAD_LETTER_TOPIC_HEADER, "foo".into()),
+        (DEAD_LETTER_PARTITION_HEADER, "1".into()),
+        (DEAD_LETTER_OFFSET_HEADER, "0".into()),
+        (DEAD_LETTER_ERROR_HEADER, "Invalid payload".into()),
+        (DEAD_LETTER_ATTEMPTS_HEADER, "3".into()),
+    ]);
+
+    let result = lock
+        .dead_letter_record(
+            &Principal::anonymous(),
+            &Identifier::Name("__metadata".to_string()),
+            0,
+            0,
+            "Invalid payload",
+            1,
+        )
+        .await;
+    assert!(matches!(result, Err(Error::InternalTopicName(_))));
+}
+
+#[tokio::test]
+async fn test_dead_letter_expired_record() {
+    let app = App::load_from_disk(Config::default())
+        .await
+        .expect("load_from_disk failed");
+    let mut lock = app.write().await;
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+    lock.produce(
+        &Principal::anonymous(),
+        Identifier::Name("foo".to_string()),
+        0,
+        "Hello".into(),
+        "World".into(),
+        vec![RecordHeader::timestamp(
+            EXPIRES_AT_HEADER,
+            Timestamp::from(Timestamp::now().as_micros() + 1000),
+        )],
+    )
+    .await
+    .expect("Failed to produce record");
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+    let response = lock
+        .dead_letter_record(
+            &Principal::anonymous(),
+            &Identifier::Name("foo".to_string()),
+            0,
+            0,
+            "Expired before it was processed",
+            1,
+        )
+        .await
+        .expect("Failed to dead-letter record");
+
+    // The dead letter does not expire with the record it was copied from
+    let mut batch = RecordBatch::new(0, None);
+    lock.read_batch(
+        &Principal::anonymous(),
+        &mut batch,
+        &OffsetSelection::From(0),
+        response.partition_id,
+        &Identifier::Name("foo.dlq".to_string()),
+    )
+    .await
+    .expect("Failed to read batch");
+    let records = batch.to_response(Encoding::Utf8).unwrap().records;
+    assert_eq!(records.len(), 1);
+    assert!(
+        records[0]
+            .headers
+            .iter()
+            .all(|header| header.key != EXPIRES_AT_HEADER)
+    );
+}
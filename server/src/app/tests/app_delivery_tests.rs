@@ -0,0 +1,155 @@
+use shared::{
+    consts::{DEAD_LETTER_TOPIC_HEADER, DELIVER_AT_HEADER, EXPIRES_AT_HEADER},
+    data::{
+        encoding::Encoding, identifier::Identifier, offset_selection::OffsetSelection,
+        timestamp::Timestamp, topic_config::TopicConfig,
+    },
+};
+
+use crate::{
+    app::{App, AppLock, error::Error},
+    auth::Principal,
+    config::Config,
+    dur::record::RecordHeader,
+    record_batch::RecordBatch,
+};
+
+async fn produce(lock: &mut AppLock, headers: Vec<RecordHeader>) {
+    lock.produce(
+        &Principal::anonymous(),
+        Identifier::Name("foo".to_string()),
+        0,
+        "Hello".into(),
+        "World".into(),
+        headers,
+    )
+    .await
+    .expect("Failed to produce record");
+}
+
+async fn fetch(lock: &AppLock, offset: u64) -> (Vec<u64>, RecordBatch) {
+    let mut batch = RecordBatch::new(0, None);
+    lock.read_batch(
+        &Principal::anonymous(),
+        &mut batch,
+        &OffsetSelection::From(offset),
+        0,
+        &Identifier::Name("foo".to_string()),
+    )
+    .await
+    .expect("Failed to read batch");
+
+    let offsets = batch
+        .to_response(Encoding::Utf8)
+        .unwrap()
+        .records
+        .iter()
+        .map(|record| record.offset)
+        .collect();
+
+    (offsets, batch)
+}
+
+#[tokio::test]
+async fn test_expired_and_delayed_records() {
+    let app = App::load_from_disk(Config::default())
+        .await
+        .expect("load_from_disk failed");
+    let mut lock = app.write().await;
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+
+    let now = Timestamp::now().as_micros();
+    let hour = 60 * 60 * 1_000_000;
+    produce(&mut lock, vec![]).await;
+    produce(&mut lock, vec![RecordHeader::timestamp(
+        EXPIRES_AT_HEADER,
+        Timestamp::from(now - hour),
+    )])
+    .await;
+    produce(&mut lock, vec![RecordHeader::timestamp(
+        EXPIRES_AT_HEADER,
+        Timestamp::from(now + hour),
+    )])
+    .await;
+    produce(&mut lock, vec![RecordHeader::timestamp(
+        DELIVER_AT_HEADER,
+        Timestamp::from(now + hour),
+    )])
+    .await;
+    produce(&mut lock, vec![]).await;
+
+    // The expired record is skipped, the delayed record holds back the records after it
+    let (offsets, batch) = fetch(&lock, 0).await;
+    assert_eq!(offsets, vec![0, 2]);
+    assert!(batch.is_held_back(1, 0));
+    assert_eq!(
+        batch
+            .next_delivery()
+            .map(|deliver_at| deliver_at.as_micros()),
+        Some(now + hour)
+    );
+
+    let (offsets, batch) = fetch(&lock, 4).await;
+    assert_eq!(offsets, vec![4]);
+    assert_eq!(batch.next_delivery(), None);
+}
+
+#[tokio::test]
+async fn test_reserved_headers_are_validated() {
+    let app = App::load_from_disk(Config::default())
+        .await
+        .expect("load_from_disk failed");
+    let mut lock = app.write().await;
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+
+    let now = Timestamp::now().as_micros();
+    let day = 24 * 60 * 60 * 1_000_000;
+    for header in [
+        RecordHeader {
+            key: DEAD_LETTER_TOPIC_HEADER.to_string(),
+            value: "bar".into(),
+        },
+        RecordHeader {
+            key: EXPIRES_AT_HEADER.to_string(),
+            value: "tomorrow".into(),
+        },
+        RecordHeader::timestamp(DELIVER_AT_HEADER, Timestamp::from(now + 2 * day)),
+    ] {
+        let result = lock
+            .produce(
+                &Principal::anonymous(),
+                Identifier::Name("foo".to_string()),
+                0,
+                "Hello".into(),
+                "World".into(),
+                vec![header],
+            )
+            .await;
+        assert!(
+            matches!(result, Err(Error::InvalidReservedHeader(..))),
+            "Expected Error::InvalidReservedHeader but got {result:?}"
+        );
+    }
+
+    produce(&mut lock, vec![RecordHeader::timestamp(
+        DELIVER_AT_HEADER,
+        Timestamp::from(now + day / 2),
+    )])
+    .await;
+}
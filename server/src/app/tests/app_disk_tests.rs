@@ -0,0 +1,108 @@
+use shared::data::{identifier::Identifier, topic_config::TopicConfig};
+use tempfile::tempdir;
+
+use crate::{
+    app::{App, AppLock, error::Error},
+    auth::Principal,
+    config::{Config, DiskConfig},
+    dur,
+};
+
+async fn produce(lock: &mut AppLock) -> Result<u64, Error> {
+    lock.produce(
+        &Principal::anonymous(),
+        Identifier::Name("foo".to_string()),
+        0,
+        "Hello".into(),
+        "World".into(),
+        vec![],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_produce_rejected_below_min_free_bytes() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let config = |min_free_bytes| Config {
+        path: dir.path().to_str().unwrap().to_string(),
+        disk: DiskConfig {
+            min_free_bytes,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let app = App::load_from_disk(config(0))
+        .await
+        .expect("load_from_disk failed");
+    let mut lock = app.write().await;
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+    produce(&mut lock).await.expect("Failed to produce record");
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "bar",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+
+    // No filesystem has this much space free
+    lock.reload_config(config(i64::MAX as u64));
+    let result = produce(&mut lock).await;
+    assert!(matches!(
+        result,
+        Err(Error::Durrability(dur::error::Error::DiskFull(_)))
+    ));
+
+    // Fetches keep working
+    let records = lock
+        .get_topic(&Identifier::Name("foo".to_string()))
+        .unwrap()
+        .read_from_partition(0, 0)
+        .await
+        .expect("Failed to read records");
+    assert_eq!(records.len(), 1);
+
+    // Credential and queue changes are rejected too, deleting a topic to free space is not
+    let result = lock.create_token("foo", false).await;
+    assert!(matches!(
+        result,
+        Err(Error::Durrability(dur::error::Error::DiskFull(_)))
+    ));
+    let result = lock
+        .receive(
+            &Principal::anonymous(),
+            &Identifier::Name("foo".to_string()),
+            1,
+            None,
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(Error::Durrability(dur::error::Error::DiskFull(_)))
+    ));
+    lock.delete_topic(
+        &Principal::anonymous(),
+        &Identifier::Name("bar".to_string()),
+    )
+    .await
+    .expect("Failed to delete_topic");
+
+    // Produces resume once enough space is free, without a restart
+    lock.reload_config(config(0));
+    drop(lock);
+    app.refresh_disk_space()
+        .await
+        .expect("Failed to refresh disk space");
+    assert_eq!(produce(&mut *app.write().await).await.unwrap(), 1);
+}
@@ -0,0 +1,346 @@
+use std::fs;
+
+use shared::data::{identifier::Identifier, topic_config::TopicConfig};
+use tempfile::tempdir;
+
+use crate::{
+    app::{App, error::Error},
+    auth::Principal,
+    config::{Config, SegmentConfig},
+    dur,
+};
+
+fn config(path: &str) -> Config {
+    // Every metadata entry gets its own segment
+    Config {
+        path: path.to_string(),
+        segment: SegmentConfig { size: 1 },
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_snapshot_metadata() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = dir.path().to_str().unwrap().to_string();
+
+    let app = App::load_from_disk(config(&path))
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let foo_id = lock
+        .create_topic(
+            &Principal::anonymous(),
+            None,
+            "foo",
+            Some(1),
+            TopicConfig::default(),
+        )
+        .await
+        .expect("Failed to create_topic");
+
+    let offset = lock
+        .snapshot_metadata()
+        .await
+        .expect("Failed to snapshot_metadata");
+    assert_eq!(offset, Some(1));
+
+    let bar_id = lock
+        .create_topic(
+            &Principal::anonymous(),
+            None,
+            "bar",
+            Some(2),
+            TopicConfig::default(),
+        )
+        .await
+        .expect("Failed to create_topic");
+
+    let offset = lock
+        .snapshot_metadata()
+        .await
+        .expect("Failed to snapshot_metadata");
+    assert_eq!(offset, Some(2));
+
+    // Segments covered by the oldest retained snapshot are removed
+    let min_offset = lock.get_topic_by_id(0).unwrap().min_offset(0).unwrap();
+    assert_eq!(min_offset, Some(2));
+
+    lock.delete_topic(&Principal::anonymous(), &Identifier::Id(foo_id))
+        .await
+        .expect("Failed to delete_topic");
+    drop(lock);
+    drop(app);
+
+    let app = App::load_from_disk(config(&path))
+        .await
+        .expect("load_from_disk failed");
+
+    let lock = app.read().await;
+    let result = lock.get_topic_by_id(foo_id);
+    assert!(matches!(result, Err(Error::TopicIdNotFound(_))));
+
+    let state = lock
+        .topic_state(
+            &Principal::anonymous(),
+            &Identifier::Name("bar".to_string()),
+        )
+        .expect("Failed to get topic_state");
+    assert_eq!(state.topic_id, bar_id);
+    assert_eq!(state.partitions.len(), 2);
+}
+
+#[tokio::test]
+async fn test_snapshot_metadata_falls_back_on_unreadable_snapshot() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = dir.path().to_str().unwrap().to_string();
+
+    let app = App::load_from_disk(config(&path))
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+    lock.snapshot_metadata()
+        .await
+        .expect("Failed to snapshot_metadata");
+
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "bar",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+    let offset = lock
+        .snapshot_metadata()
+        .await
+        .expect("Failed to snapshot_metadata")
+        .expect("Expected a snapshot offset");
+
+    fs::write(lock.config.snapshot_path(offset), "not json").unwrap();
+    drop(lock);
+    drop(app);
+
+    let app = App::load_from_disk(config(&path))
+        .await
+        .expect("load_from_disk failed");
+
+    let lock = app.read().await;
+    lock.get_topic_by_name("foo")
+        .expect("Expected foo to be loaded");
+    lock.get_topic_by_name("bar")
+        .expect("Expected bar to be loaded");
+}
+
+#[tokio::test]
+async fn test_missing_metadata_entries_fail_to_load() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = dir.path().to_str().unwrap().to_string();
+
+    let app = App::load_from_disk(config(&path))
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let mut offsets = Vec::new();
+    for name in ["foo", "bar"] {
+        lock.create_topic(
+            &Principal::anonymous(),
+            None,
+            name,
+            Some(1),
+            TopicConfig::default(),
+        )
+        .await
+        .expect("Failed to create_topic");
+        offsets.push(
+            lock.snapshot_metadata()
+                .await
+                .expect("Failed to snapshot_metadata")
+                .expect("Expected a snapshot offset"),
+        );
+    }
+
+    // The segments before the oldest snapshot are gone, without snapshots they can not be replayed
+    for offset in offsets {
+        fs::remove_file(lock.config.snapshot_path(offset)).unwrap();
+    }
+    drop(lock);
+    drop(app);
+
+    let result = App::load_from_disk(config(&path)).await;
+    assert!(matches!(
+        result,
+        Err(dur::error::Error::MissingLogEntries(2, 0))
+    ));
+}
+
+#[tokio::test]
+async fn test_invalid_metadata_entries_are_skipped() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = dir.path().to_str().unwrap().to_string();
+
+    let app = App::load_from_disk(config(&path))
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+
+    let record = lock
+        .get_topic_by_id_mut(0)
+        .unwrap()
+        .append(0, "".into(), vec![0xff, 0xfe].into(), vec![])
+        .await
+        .expect("Failed to append invalid entry");
+
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "bar",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+    drop(lock);
+    drop(app);
+
+    fs::create_dir_all(format!("{path}/topics/not-a-topic")).unwrap();
+
+    let app = App::load_from_disk(config(&path))
+        .await
+        .expect("load_from_disk failed");
+
+    let lock = app.read().await;
+    lock.get_topic_by_name("foo")
+        .expect("Expected foo to be loaded");
+    lock.get_topic_by_name("bar")
+        .expect("Expected bar to be loaded");
+
+    let quarantined = fs::read(lock.config.quarantine_entry_path(record.offset))
+        .expect("Expected the invalid entry to be quarantined");
+    assert_eq!(quarantined, vec![0xff, 0xfe]);
+}
+
+#[tokio::test]
+async fn test_rebuild_metadata() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = dir.path().to_str().unwrap().to_string();
+
+    let app = App::load_from_disk(config(&path))
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let topic_id = lock
+        .create_topic(
+            &Principal::anonymous(),
+            None,
+            "foo",
+            Some(2),
+            TopicConfig::default(),
+        )
+        .await
+        .expect("Failed to create_topic");
+    lock.produce(
+        &Principal::anonymous(),
+        Identifier::Id(topic_id),
+        1,
+        "Hello".into(),
+        "World".into(),
+        vec![],
+    )
+    .await
+    .expect("Failed to produce record");
+
+    // Lose all metadata entries
+    fs::remove_dir_all(lock.config.base_log_dir().partition_path(0, 0)).unwrap();
+    drop(lock);
+    drop(app);
+
+    let mut rebuild_config = config(&path);
+    rebuild_config.metadata.rebuild = true;
+    let app = App::load_from_disk(rebuild_config)
+        .await
+        .expect("load_from_disk failed");
+    drop(app);
+
+    let app = App::load_from_disk(config(&path))
+        .await
+        .expect("load_from_disk failed");
+
+    let lock = app.read().await;
+    let state = lock
+        .topic_state(&Principal::anonymous(), &Identifier::Id(topic_id))
+        .expect("Failed to get topic_state");
+    assert_eq!(state.name, format!("recovered-{topic_id}"));
+    assert_eq!(state.partitions.len(), 2);
+
+    let record = lock
+        .read_exact(&Identifier::Id(topic_id), 1, 0)
+        .await
+        .expect("Failed to read record")
+        .expect("Did not receive a record");
+    assert_eq!(record.value, "World");
+}
+
+#[tokio::test]
+async fn test_rebuild_metadata_avoids_name_collisions() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = dir.path().to_str().unwrap().to_string();
+
+    let app = App::load_from_disk(config(&path))
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "recovered-2",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+
+    // A topic directory without an entry, whose recovered name is taken
+    fs::create_dir_all(lock.config.base_log_dir().partition_path(2, 0)).unwrap();
+    drop(lock);
+    drop(app);
+
+    let mut rebuild_config = config(&path);
+    rebuild_config.metadata.rebuild = true;
+    let app = App::load_from_disk(rebuild_config)
+        .await
+        .expect("load_from_disk failed");
+
+    let lock = app.read().await;
+    let state = lock
+        .topic_state(&Principal::anonymous(), &Identifier::Id(2))
+        .expect("Failed to get topic_state");
+    assert_eq!(state.name, "recovered-2-1");
+    lock.get_topic_by_name("recovered-2")
+        .expect("Expected recovered-2 to keep its name");
+}
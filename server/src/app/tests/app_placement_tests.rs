@@ -0,0 +1,149 @@
+use std::path::Path;
+
+use shared::data::{identifier::Identifier, topic_config::TopicConfig};
+use tempfile::tempdir;
+
+use crate::{
+    app::{App, AppLock, error::Error},
+    auth::Principal,
+    config::Config,
+};
+
+async fn produce(lock: &mut AppLock, count: usize) {
+    for _ in 0..count {
+        lock.produce(
+            &Principal::anonymous(),
+            Identifier::Name("foo".to_string()),
+            0,
+            "Hello".into(),
+            "World".into(),
+            vec![],
+        )
+        .await
+        .expect("Failed to produce record");
+    }
+}
+
+async fn read(app: &App) -> usize {
+    app.read()
+        .await
+        .get_topic(&Identifier::Name("foo".to_string()))
+        .unwrap()
+        .read_from_partition(0, 0)
+        .await
+        .expect("Failed to read records")
+        .len()
+}
+
+fn log_dirs(app_lock: &AppLock) -> Vec<String> {
+    app_lock
+        .get_topic(&Identifier::Name("foo".to_string()))
+        .unwrap()
+        .state()
+        .partitions
+        .into_iter()
+        .map(|partition| partition.log_dir)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_partition_placement_and_move() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+    let config = || Config {
+        path: path("data"),
+        log_dirs: vec![path("a"), path("b")],
+        ..Default::default()
+    };
+
+    let app = App::load_from_disk(config())
+        .await
+        .expect("load_from_disk failed");
+    let topic = Identifier::Name("foo".to_string());
+
+    let mut lock = app.write().await;
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(4),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+
+    // New partitions are spread over the log directories, the metadata stays in path
+    let placed = log_dirs(&lock);
+    assert_eq!(
+        placed
+            .iter()
+            .filter(|log_dir| **log_dir == path("a"))
+            .count(),
+        2
+    );
+    assert_eq!(
+        placed
+            .iter()
+            .filter(|log_dir| **log_dir == path("b"))
+            .count(),
+        2
+    );
+    assert!(Path::new(&path("data")).join("topics/0").exists());
+
+    produce(&mut lock, 2).await;
+    lock.roll_segment(&topic, 0)
+        .await
+        .expect("Failed to roll segment");
+    produce(&mut lock, 2).await;
+    drop(lock);
+
+    let source = placed[0].clone();
+    let target = if source == path("a") {
+        path("b")
+    } else {
+        path("a")
+    };
+
+    let result = app.move_partition(&topic, 0, &path("data")).await;
+    assert!(matches!(result, Err(Error::UnknownLogDir(_))));
+
+    let state = app
+        .move_partition(&topic, 0, &target)
+        .await
+        .expect("Failed to move partition");
+    assert_eq!(state.log_dir, target);
+    assert_eq!(state.segment_count, 2);
+    assert!(!Path::new(&source).join("topics/1/partitions/0").exists());
+    assert!(!Path::new(&target).join("moving").exists());
+    assert_eq!(read(&app).await, 4);
+
+    // Appends go to the new directory
+    produce(&mut *app.write().await, 1).await;
+    drop(app);
+
+    // The placement is remembered across restarts
+    let app = App::load_from_disk(config())
+        .await
+        .expect("load_from_disk failed");
+    let mut moved = placed.clone();
+    moved[0] = target.clone();
+    assert_eq!(log_dirs(&*app.read().await), moved);
+    assert_eq!(read(&app).await, 5);
+
+    // A backup copies each log directory within itself
+    let backup = app
+        .write()
+        .await
+        .create_backup()
+        .await
+        .expect("Failed to create backup");
+    assert_eq!(backup.log_dirs.len(), 2);
+    let backup_app = App::load_from_disk(Config {
+        path: backup.path.clone(),
+        log_dirs: backup.log_dirs.clone(),
+        ..Default::default()
+    })
+    .await
+    .expect("Failed to load backup");
+    assert_eq!(read(&backup_app).await, 5);
+}
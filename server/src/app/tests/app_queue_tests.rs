@@ -0,0 +1,354 @@
+use std::time::Duration;
+
+use shared::{
+    consts::{
+        DEAD_LETTER_ATTEMPTS_HEADER, DEAD_LETTER_OFFSET_HEADER, DEAD_LETTER_TOPIC_HEADER,
+        EXPIRES_AT_HEADER,
+    },
+    data::{identifier::Identifier, timestamp::Timestamp, topic_config::TopicConfig},
+};
+use tempfile::tempdir;
+
+use crate::{
+    app::{App, AppLock, error::Error},
+    auth::Principal,
+    config::{Config, QueueConfig, SegmentConfig},
+    dur::record::RecordHeader,
+    queue::{QUEUES_TOPIC, QueueMessage},
+};
+
+fn foo() -> Identifier {
+    Identifier::Name("foo".to_string())
+}
+
+async fn receive(lock: &mut AppLock, max_messages: usize, timeout_ms: u64) -> Vec<QueueMessage> {
+    lock.receive(
+        &Principal::anonymous(),
+        &foo(),
+        max_messages,
+        Some(timeout_ms),
+    )
+    .await
+    .expect("Failed to receive")
+}
+
+fn offsets(messages: &[QueueMessage]) -> Vec<(u64, u64)> {
+    messages
+        .iter()
+        .map(|message| (message.record.offset, message.deliveries))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_queue_leases() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let config = || Config {
+        path: dir.path().to_str().unwrap().to_string(),
+        queue: QueueConfig {
+            max_deliveries: 2,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let app = App::load_from_disk(config())
+        .await
+        .expect("load_from_disk failed");
+    let mut lock = app.write().await;
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+    for _ in 0..3 {
+        lock.produce(
+            &Principal::anonymous(),
+            foo(),
+            0,
+            "Hello".into(),
+            "World".into(),
+            vec![],
+        )
+        .await
+        .expect("Failed to produce record");
+    }
+
+    // Received messages are invisible to the next receive
+    let first = receive(&mut lock, 2, 60_000).await;
+    assert_eq!(offsets(&first), vec![(0, 1), (1, 1)]);
+    let second = receive(&mut lock, 2, 60_000).await;
+    assert_eq!(offsets(&second), vec![(2, 1)]);
+    assert!(receive(&mut lock, 2, 60_000).await.is_empty());
+
+    lock.ack(&Principal::anonymous(), &foo(), &[first[0].lease])
+        .await
+        .expect("Failed to ack");
+    let result = lock
+        .ack(&Principal::anonymous(), &foo(), &[first[0].lease])
+        .await;
+    assert!(matches!(result, Err(Error::LeaseNotFound(..))));
+
+    // A nacked message is redelivered with a new lease, the old lease can not be used anymore
+    lock.nack(&Principal::anonymous(), &foo(), &[first[1].lease], None)
+        .await
+        .expect("Failed to nack");
+    let redelivered = receive(&mut lock, 2, 60_000).await;
+    assert_eq!(offsets(&redelivered), vec![(1, 2)]);
+    let result = lock
+        .ack(&Principal::anonymous(), &foo(), &[first[1].lease])
+        .await;
+    assert!(matches!(result, Err(Error::LeaseNotFound(..))));
+
+    // Delays are bounded
+    let result = lock
+        .nack(
+            &Principal::anonymous(),
+            &foo(),
+            &[second[0].lease],
+            Some(u64::MAX),
+        )
+        .await;
+    assert!(matches!(result, Err(Error::QueueDelayTooLong(..))));
+    let result = lock
+        .receive(&Principal::anonymous(), &foo(), 1, Some(u64::MAX))
+        .await;
+    assert!(matches!(result, Err(Error::QueueDelayTooLong(..))));
+
+    // A nack can delay the redelivery
+    lock.nack(&Principal::anonymous(), &foo(), &[second[0].lease], Some(1))
+        .await
+        .expect("Failed to nack");
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    let state = lock
+        .queue_state(&Principal::anonymous(), &foo())
+        .expect("Failed to get queue state");
+    assert_eq!(
+        (state.cursors, state.in_flight, state.visible),
+        (vec![3], 1, 1)
+    );
+    drop(lock);
+
+    // Leases survive a restart, messages over the delivery limit go to the dead-letter topic
+    let app = App::load_from_disk(config())
+        .await
+        .expect("load_from_disk failed");
+    let mut lock = app.write().await;
+    // Without a visibility timeout the message is visible again right away
+    let timed_out = receive(&mut lock, 2, 0).await;
+    assert_eq!(offsets(&timed_out), vec![(2, 2)]);
+
+    lock.nack(
+        &Principal::anonymous(),
+        &foo(),
+        &[redelivered[0].lease],
+        None,
+    )
+    .await
+    .expect("Failed to nack");
+    assert!(receive(&mut lock, 2, 60_000).await.is_empty());
+    let result = lock
+        .ack(&Principal::anonymous(), &foo(), &[timed_out[0].lease])
+        .await;
+    assert!(matches!(result, Err(Error::LeaseNotFound(..))));
+
+    let dead_letters = lock
+        .get_topic(&Identifier::Name("foo.dlq".to_string()))
+        .expect("Dead-letter topic was not created")
+        .read_from_partition(0, 0)
+        .await
+        .expect("Failed to read dead letters");
+    assert_eq!(dead_letters.len(), 2);
+    let header = |key: &str| {
+        dead_letters[0]
+            .headers
+            .iter()
+            .find(|header| header.key == key)
+            .map(|header| header.value.clone())
+    };
+    assert_eq!(header(DEAD_LETTER_TOPIC_HEADER), Some("foo".into()));
+    assert_eq!(header(DEAD_LETTER_OFFSET_HEADER), Some("1".into()));
+    assert_eq!(header(DEAD_LETTER_ATTEMPTS_HEADER), Some("2".into()));
+}
+
+#[tokio::test]
+async fn test_corrupt_queue_entries_are_skipped() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let config = || Config {
+        path: dir.path().to_str().unwrap().to_string(),
+        ..Default::default()
+    };
+
+    let app = App::load_from_disk(config())
+        .await
+        .expect("load_from_disk failed");
+    let mut lock = app.write().await;
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+    lock.produce(
+        &Principal::anonymous(),
+        foo(),
+        0,
+        "Hello".into(),
+        "World".into(),
+        vec![],
+    )
+    .await
+    .expect("Failed to produce record");
+    let received = receive(&mut lock, 1, 60_000).await;
+    lock.get_topic_by_name_mut(QUEUES_TOPIC)
+        .unwrap()
+        .append(0, "".into(), "{not json".into(), vec![])
+        .await
+        .expect("Failed to append");
+    drop(lock);
+
+    let app = App::load_from_disk(config())
+        .await
+        .expect("load_from_disk failed");
+    let mut lock = app.write().await;
+    lock.ack(&Principal::anonymous(), &foo(), &[received[0].lease])
+        .await
+        .expect("Failed to ack");
+}
+
+#[tokio::test]
+async fn test_queue_snapshots() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let config = || Config {
+        path: dir.path().to_str().unwrap().to_string(),
+        // Every queue entry gets its own segment
+        segment: SegmentConfig { size: 1 },
+        queue: QueueConfig {
+            snapshot_min_entries: 3,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let app = App::load_from_disk(config())
+        .await
+        .expect("load_from_disk failed");
+    let mut lock = app.write().await;
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+    for _ in 0..4 {
+        lock.produce(
+            &Principal::anonymous(),
+            foo(),
+            0,
+            "Hello".into(),
+            "World".into(),
+            vec![],
+        )
+        .await
+        .expect("Failed to produce record");
+    }
+
+    let first = receive(&mut lock, 2, 60_000).await;
+    lock.ack(&Principal::anonymous(), &foo(), &[first[0].lease])
+        .await
+        .expect("Failed to ack");
+    let second = receive(&mut lock, 1, 60_000).await;
+    assert_eq!(offsets(&second), vec![(2, 1)]);
+
+    // The entries before the snapshot after the third entry are removed
+    let min_offset = lock
+        .get_topic_by_name(QUEUES_TOPIC)
+        .unwrap()
+        .min_offset(0)
+        .unwrap();
+    assert_eq!(min_offset, Some(3));
+    drop(lock);
+
+    let app = App::load_from_disk(config())
+        .await
+        .expect("load_from_disk failed");
+    let mut lock = app.write().await;
+    let state = lock
+        .queue_state(&Principal::anonymous(), &foo())
+        .expect("Failed to get queue state");
+    assert_eq!((state.cursors, state.in_flight), (vec![3], 2));
+
+    lock.ack(&Principal::anonymous(), &foo(), &[
+        first[1].lease,
+        second[0].lease,
+    ])
+    .await
+    .expect("Failed to ack");
+    let third = receive(&mut lock, 2, 60_000).await;
+    assert_eq!(offsets(&third), vec![(3, 1)]);
+    assert!(third[0].lease.lease_id > second[0].lease.lease_id);
+}
+
+#[tokio::test]
+async fn test_dead_lettered_message_keeps_no_delivery_headers() {
+    let config = Config {
+        queue: QueueConfig {
+            max_deliveries: 1,
+            max_messages: 10,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+    let mut lock = app.write().await;
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+    lock.produce(
+        &Principal::anonymous(),
+        foo(),
+        0,
+        "Hello".into(),
+        "World".into(),
+        vec![RecordHeader::timestamp(
+            EXPIRES_AT_HEADER,
+            Timestamp::from(Timestamp::now().as_micros() + 60 * 60 * 1_000_000),
+        )],
+    )
+    .await
+    .expect("Failed to produce record");
+
+    let result = lock
+        .receive(&Principal::anonymous(), &foo(), 11, None)
+        .await;
+    assert!(matches!(result, Err(Error::TooManyMessages(11, 10))));
+
+    assert_eq!(offsets(&receive(&mut lock, 1, 0).await), vec![(0, 1)]);
+    assert!(receive(&mut lock, 1, 0).await.is_empty());
+
+    let dead_letters = lock
+        .get_topic(&Identifier::Name("foo.dlq".to_string()))
+        .expect("Dead-letter topic was not created")
+        .read_from_partition(0, 0)
+        .await
+        .expect("Failed to read dead letters");
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].expires_at(), None);
+}
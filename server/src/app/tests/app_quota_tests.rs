@@ -0,0 +1,211 @@
+use shared::data::{
+    identifier::Identifier,
+    quota::{DEFAULT_QUOTA_NAME, Quota, QuotaEntity},
+    topic_config::TopicConfig,
+};
+use tempfile::tempdir;
+
+use crate::{
+    app::{App, AppLock, error::Error},
+    auth::Principal,
+    config::Config,
+};
+
+async fn produce(lock: &mut AppLock) -> Result<u64, Error> {
+    lock.produce(
+        &Principal::anonymous(),
+        Identifier::Name("foo".to_string()),
+        0,
+        "Hello".into(),
+        "World".into(),
+        vec![],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_quotas() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let config = || Config {
+        path: dir.path().to_str().unwrap().to_string(),
+        ..Default::default()
+    };
+
+    let app = App::load_from_disk(config())
+        .await
+        .expect("load_from_disk failed");
+    let mut lock = app.write().await;
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+
+    let result = lock
+        .set_quota(QuotaEntity::Topic("foo".to_string()), Quota {
+            requests_per_sec: Some(0),
+            ..Default::default()
+        })
+        .await;
+    assert!(matches!(result, Err(Error::InvalidQuota(_))));
+
+    lock.set_quota(QuotaEntity::Topic("foo".to_string()), Quota {
+        requests_per_sec: Some(1),
+        ..Default::default()
+    })
+    .await
+    .expect("Failed to set quota");
+    lock.set_quota(QuotaEntity::Client(DEFAULT_QUOTA_NAME.to_string()), Quota {
+        fetch_bytes_per_sec: Some(5),
+        ..Default::default()
+    })
+    .await
+    .expect("Failed to set quota");
+    drop(lock);
+
+    // Quotas are kept in the metadata
+    let app = App::load_from_disk(config())
+        .await
+        .expect("load_from_disk failed");
+    let mut lock = app.write().await;
+    assert_eq!(lock.quota_states().len(), 2);
+
+    // The bucket holds one request, a rejected produce is not appended
+    assert_eq!(produce(&mut lock).await.unwrap(), 0);
+    let result = produce(&mut lock).await;
+    assert!(
+        matches!(result, Err(Error::Throttled(retry_after_ms)) if retry_after_ms > 0),
+        "Expected Error::Throttled but got {result:?}"
+    );
+
+    // Fetches over the default client quota are delayed until the debt is paid off
+    let delay = lock.charge_fetch_quota(&Principal::anonymous(), &[("foo".to_string(), 10)]);
+    assert!(
+        delay.as_millis() >= 900,
+        "Expected a delay but got {delay:?}"
+    );
+
+    lock.delete_quota(QuotaEntity::Topic("foo".to_string()))
+        .await
+        .expect("Failed to delete quota");
+    assert_eq!(produce(&mut lock).await.unwrap(), 1);
+
+    let result = lock
+        .delete_quota(QuotaEntity::Topic("foo".to_string()))
+        .await;
+    assert!(matches!(result, Err(Error::QuotaNotFound(_))));
+}
+
+#[tokio::test]
+async fn test_topic_quota_follows_topic() {
+    let app = App::load_from_disk(Config::default())
+        .await
+        .expect("load_from_disk failed");
+    let mut lock = app.write().await;
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+    lock.set_quota(QuotaEntity::Topic("foo".to_string()), Quota {
+        requests_per_sec: Some(1),
+        ..Default::default()
+    })
+    .await
+    .expect("Failed to set quota");
+
+    // A failed produce is not counted
+    let result = lock
+        .produce(
+            &Principal::anonymous(),
+            Identifier::Name("foo".to_string()),
+            1,
+            "Hello".into(),
+            "World".into(),
+            vec![],
+        )
+        .await;
+    assert!(result.is_err());
+    assert_eq!(produce(&mut lock).await.unwrap(), 0);
+
+    // The quota moves with a rename, a new topic under the old name has none
+    lock.rename_topic(
+        &Principal::anonymous(),
+        &Identifier::Name("foo".to_string()),
+        "bar",
+    )
+    .await
+    .expect("Failed to rename_topic");
+    let entities = lock
+        .quota_states()
+        .into_iter()
+        .map(|state| state.entity)
+        .collect::<Vec<_>>();
+    assert_eq!(entities, vec![QuotaEntity::Topic("bar".to_string())]);
+
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+    produce(&mut lock).await.expect("Failed to produce record");
+    produce(&mut lock).await.expect("Failed to produce record");
+
+    // The quota is removed with its topic
+    lock.delete_topic(
+        &Principal::anonymous(),
+        &Identifier::Name("bar".to_string()),
+    )
+    .await
+    .expect("Failed to delete_topic");
+    assert!(lock.quota_states().is_empty());
+}
+
+#[tokio::test]
+async fn test_topic_cannot_be_named_after_the_default_quota() {
+    let app = App::load_from_disk(Config::default())
+        .await
+        .expect("load_from_disk failed");
+    let mut lock = app.write().await;
+
+    let result = lock
+        .create_topic(
+            &Principal::anonymous(),
+            None,
+            DEFAULT_QUOTA_NAME,
+            Some(1),
+            TopicConfig::default(),
+        )
+        .await;
+    assert!(matches!(result, Err(Error::InvalidName(_))));
+
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+    let result = lock
+        .rename_topic(
+            &Principal::anonymous(),
+            &Identifier::Name("foo".to_string()),
+            DEFAULT_QUOTA_NAME,
+        )
+        .await;
+    assert!(matches!(result, Err(Error::InvalidName(_))));
+}
@@ -0,0 +1,123 @@
+use shared::data::{identifier::Identifier, topic_config::TopicConfig};
+use tempfile::tempdir;
+
+use crate::{
+    app::{App, error::Error},
+    auth::Principal,
+    config::Config,
+};
+
+#[tokio::test]
+async fn test_roll_and_truncate_segments() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = dir.path().to_str().unwrap().to_string();
+
+    let config = Config {
+        path: path.to_string(),
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let topic = Identifier::Name("foo".to_string());
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+
+    for _ in 0..3 {
+        lock.produce(
+            &Principal::anonymous(),
+            topic.clone(),
+            0,
+            "Hello".into(),
+            "World".into(),
+            vec![],
+        )
+        .await
+        .expect("Failed to produce record");
+    }
+
+    let segments = lock
+        .roll_segment(&topic, 0)
+        .await
+        .expect("Failed to roll segment");
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[0].min_offset, Some(0));
+    assert_eq!(segments[0].max_offset, Some(2));
+    assert_eq!(segments[1].start_offset, 3);
+    assert_eq!(segments[1].max_offset, None);
+
+    // The active segment is empty, so there is nothing to roll
+    let segments = lock
+        .roll_segment(&topic, 0)
+        .await
+        .expect("Failed to roll segment");
+    assert_eq!(segments.len(), 2);
+
+    // Offset 2 is in the middle of the first segment, records 0 and 1 would stay readable
+    let result = lock.truncate_partition(&topic, 0, 2).await;
+    assert!(matches!(result, Err(Error::OffsetNotOnSegmentBoundary(2))));
+    let segments = lock
+        .segment_states(&topic, 0)
+        .expect("Failed to get segment states");
+    assert_eq!(segments.len(), 2);
+    let records = lock
+        .get_topic(&topic)
+        .unwrap()
+        .read_from_partition(0, 0)
+        .await
+        .expect("Failed to read records");
+    assert_eq!(records.len(), 3);
+
+    // Before the first segment there is nothing to remove
+    let segments = lock
+        .truncate_partition(&topic, 0, 0)
+        .await
+        .expect("Failed to truncate partition");
+    assert_eq!(segments.len(), 2);
+
+    let segments = lock
+        .truncate_partition(&topic, 0, 3)
+        .await
+        .expect("Failed to truncate partition");
+    assert_eq!(segments.len(), 1);
+    assert_eq!(segments[0].start_offset, 3);
+
+    let result = lock
+        .truncate_partition(&Identifier::Name("__metadata".to_string()), 0, 3)
+        .await;
+    assert!(matches!(result, Err(Error::InternalTopicName(_))));
+    drop(lock);
+    drop(app);
+
+    let config = Config {
+        path,
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let offset = app
+        .write()
+        .await
+        .produce(
+            &Principal::anonymous(),
+            topic.clone(),
+            0,
+            "Hello".into(),
+            "World".into(),
+            vec![],
+        )
+        .await
+        .expect("Failed to produce record");
+    assert_eq!(offset, 3);
+}
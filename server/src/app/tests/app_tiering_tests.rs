@@ -0,0 +1,189 @@
+use std::path::Path;
+use std::sync::atomic::Ordering;
+
+use shared::data::{identifier::Identifier, topic_config::TopicConfig};
+use tempfile::tempdir;
+
+use crate::{
+    app::{App, AppLock},
+    auth::Principal,
+    config::{Config, StorageConfig, TieringConfig},
+    metrics::METRICS,
+};
+
+async fn produce(lock: &mut AppLock, count: usize) {
+    for _ in 0..count {
+        lock.produce(
+            &Principal::anonymous(),
+            Identifier::Name("foo".to_string()),
+            0,
+            "Hello".into(),
+            "World".into(),
+            vec![],
+        )
+        .await
+        .expect("Failed to produce record");
+    }
+}
+
+fn tiering_config(path: &str, archive: &str) -> Config {
+    Config {
+        path: path.to_string(),
+        tiering: TieringConfig {
+            after_ms: Some(0),
+            storage: Some(StorageConfig::Local {
+                path: archive.to_string(),
+            }),
+            backend: None,
+        },
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_offload_segments() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = dir.path().join("data").to_str().unwrap().to_string();
+    let archive = dir.path().join("archive").to_str().unwrap().to_string();
+
+    let app = App::load_from_disk(tiering_config(&path, &archive))
+        .await
+        .expect("load_from_disk failed");
+    let topic = Identifier::Name("foo".to_string());
+
+    let mut lock = app.write().await;
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "foo",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+    produce(&mut lock, 2).await;
+    lock.roll_segment(&topic, 0)
+        .await
+        .expect("Failed to roll segment");
+    produce(&mut lock, 2).await;
+    drop(lock);
+
+    // Only the closed segment is offloaded, and only once
+    assert_eq!(app.offload_segments().await.unwrap(), 1);
+    assert_eq!(app.offload_segments().await.unwrap(), 0);
+
+    let segments = app.read().await.segment_states(&topic, 0).unwrap();
+    let key = segments[0]
+        .tiered_key
+        .clone()
+        .expect("Segment was not offloaded");
+    assert!(segments[1].tiered_key.is_none());
+    assert!(!Path::new(&segments[0].log_path).exists());
+    assert!(Path::new(&archive).join(&key).exists());
+
+    let read = |app: App| async move {
+        app.read()
+            .await
+            .get_topic(&Identifier::Name("foo".to_string()))
+            .unwrap()
+            .read_from_partition(0, 0)
+            .await
+            .expect("Failed to read records")
+            .len()
+    };
+    assert_eq!(read(app.clone()).await, 4);
+    drop(app);
+
+    // Offloaded segments are found through their marker after a restart
+    let app = App::load_from_disk(tiering_config(&path, &archive))
+        .await
+        .expect("load_from_disk failed");
+    assert_eq!(read(app.clone()).await, 4);
+
+    // A backup gets its own copy of the offloaded log
+    let backup = app
+        .write()
+        .await
+        .create_backup()
+        .await
+        .expect("Failed to create backup");
+    let backup_app = App::load_from_disk(tiering_config(&backup.path, &archive))
+        .await
+        .expect("Failed to load backup");
+    let backup_key = backup_app.read().await.segment_states(&topic, 0).unwrap()[0]
+        .tiered_key
+        .clone()
+        .expect("Backup segment is not offloaded");
+    assert_ne!(backup_key, key);
+    drop(backup_app);
+
+    app.write()
+        .await
+        .truncate_partition(&topic, 0, 2)
+        .await
+        .expect("Failed to truncate partition");
+    assert!(!Path::new(&archive).join(&key).exists());
+    assert!(Path::new(&archive).join(&backup_key).exists());
+    assert_eq!(read(app).await, 2);
+}
+
+#[tokio::test]
+async fn test_offload_continues_after_a_failed_upload() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = dir.path().join("data").to_str().unwrap().to_string();
+    let archive = dir.path().join("archive").to_str().unwrap().to_string();
+
+    let app = App::load_from_disk(tiering_config(&path, &archive))
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    for name in ["bar", "foo"] {
+        let topic = Identifier::Name(name.to_string());
+        lock.create_topic(
+            &Principal::anonymous(),
+            None,
+            name,
+            Some(1),
+            TopicConfig::default(),
+        )
+        .await
+        .expect("Failed to create_topic");
+        lock.produce(
+            &Principal::anonymous(),
+            topic.clone(),
+            0,
+            "Hello".into(),
+            "World".into(),
+            vec![],
+        )
+        .await
+        .expect("Failed to produce record");
+        lock.roll_segment(&topic, 0)
+            .await
+            .expect("Failed to roll segment");
+    }
+
+    // The upload of bar fails because its log is gone
+    let bar = Identifier::Name("bar".to_string());
+    let log_path = lock.segment_states(&bar, 0).unwrap()[0].log_path.clone();
+    std::fs::remove_file(log_path).expect("Failed to remove log");
+    drop(lock);
+
+    let failures = METRICS.offload_failures.load(Ordering::Relaxed);
+    assert_eq!(app.offload_segments().await.unwrap(), 1);
+    assert!(METRICS.offload_failures.load(Ordering::Relaxed) > failures);
+
+    let lock = app.read().await;
+    assert!(
+        lock.segment_states(&bar, 0).unwrap()[0]
+            .tiered_key
+            .is_none()
+    );
+    let foo = Identifier::Name("foo".to_string());
+    assert!(
+        lock.segment_states(&foo, 0).unwrap()[0]
+            .tiered_key
+            .is_some()
+    );
+}
@@ -1,8 +1,14 @@
-use shared::data::identifier::Identifier;
+use shared::data::{
+    identifier::Identifier,
+    topic_config::{CleanupPolicy, TopicConfig, TopicConfigKey},
+};
+use tempfile::tempdir;
 
 use crate::{
     app::{App, error::Error},
-    config::Config,
+    auth::Principal,
+    config::{Config, LimitsConfig, SegmentConfig},
+    dur::record::RecordHeader,
 };
 
 #[tokio::test]
@@ -15,13 +21,25 @@ async fn test_create_topics() {
     let mut lock = app.write().await;
 
     let topic_id = lock
-        .create_topic(Some(1), "foo", None)
+        .create_topic(
+            &Principal::anonymous(),
+            Some(1),
+            "foo",
+            None,
+            TopicConfig::default(),
+        )
         .await
         .expect("Failed to create_topic");
     assert_eq!(topic_id, 1);
 
     let topic_id = lock
-        .create_topic(None, "bar", None)
+        .create_topic(
+            &Principal::anonymous(),
+            None,
+            "bar",
+            None,
+            TopicConfig::default(),
+        )
         .await
         .expect("Failed to create_topic");
     assert_eq!(topic_id, 2);
@@ -37,13 +55,20 @@ async fn test_create_topic_and_append() {
     let mut lock = app.write().await;
 
     let topic_id = lock
-        .create_topic(None, "foo", None)
+        .create_topic(
+            &Principal::anonymous(),
+            None,
+            "foo",
+            None,
+            TopicConfig::default(),
+        )
         .await
         .expect("Failed to create_topic");
     assert_eq!(topic_id, 1);
 
     let offset = lock
         .produce(
+            &Principal::anonymous(),
             Identifier::Id(topic_id),
             1,
             "Hello".into(),
@@ -65,12 +90,26 @@ async fn test_cannot_create_same_id_twice() {
     let mut lock = app.write().await;
 
     let topic_id = lock
-        .create_topic(Some(1), "foo", None)
+        .create_topic(
+            &Principal::anonymous(),
+            Some(1),
+            "foo",
+            None,
+            TopicConfig::default(),
+        )
         .await
         .expect("Failed to create_topic");
     assert_eq!(topic_id, 1);
 
-    let result = lock.create_topic(Some(1), "bar", None).await;
+    let result = lock
+        .create_topic(
+            &Principal::anonymous(),
+            Some(1),
+            "bar",
+            None,
+            TopicConfig::default(),
+        )
+        .await;
     assert!(
         matches!(result, Err(Error::TopicIdInUse(1))),
         "Expected Error::TopicIdInUse(1) but got {result:?}"
@@ -87,12 +126,26 @@ async fn test_cannot_create_same_name_twice() {
     let mut lock = app.write().await;
 
     let topic_id = lock
-        .create_topic(None, "foo", None)
+        .create_topic(
+            &Principal::anonymous(),
+            None,
+            "foo",
+            None,
+            TopicConfig::default(),
+        )
         .await
         .expect("Failed to create_topic");
     assert_eq!(topic_id, 1);
 
-    let result = lock.create_topic(None, "foo", None).await;
+    let result = lock
+        .create_topic(
+            &Principal::anonymous(),
+            None,
+            "foo",
+            None,
+            TopicConfig::default(),
+        )
+        .await;
     assert!(matches!(result, Err(Error::TopicNameInUse(_))));
 }
 
@@ -107,6 +160,7 @@ async fn test_cannot_produce_on_internal_topics() {
 
     let result = lock
         .produce(
+            &Principal::anonymous(),
             Identifier::Name("__metadata".to_string()),
             0,
             "Hello".into(),
@@ -116,3 +170,504 @@ async fn test_cannot_produce_on_internal_topics() {
         .await;
     assert!(matches!(result, Err(Error::InternalTopicName(_))));
 }
+
+#[tokio::test]
+async fn test_alter_topic_config() {
+    let config = Config::default();
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+
+    let topic_id = lock
+        .create_topic(&Principal::anonymous(), None, "foo", Some(1), TopicConfig {
+            retention_ms: Some(1000),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create_topic");
+
+    let state = lock
+        .alter_topic_config(
+            &Principal::anonymous(),
+            &Identifier::Id(topic_id),
+            &TopicConfig {
+                max_record_size: Some(5),
+                ..Default::default()
+            },
+            &[],
+        )
+        .await
+        .expect("Failed to alter_topic_config");
+
+    assert_eq!(state.config, TopicConfig {
+        retention_ms: Some(1000),
+        max_record_size: Some(5),
+        ..Default::default()
+    });
+
+    let result = lock
+        .produce(
+            &Principal::anonymous(),
+            Identifier::Id(topic_id),
+            0,
+            "Hello".into(),
+            "World".into(),
+            vec![],
+        )
+        .await;
+    assert!(
+        matches!(result, Err(Error::RecordTooLarge(10, 5))),
+        "Expected Error::RecordTooLarge(10, 5) but got {result:?}"
+    );
+
+    // A reset value falls back to the broker default
+    let state = lock
+        .alter_topic_config(
+            &Principal::anonymous(),
+            &Identifier::Id(topic_id),
+            &TopicConfig {
+                segment_size: Some(2048),
+                ..Default::default()
+            },
+            &[TopicConfigKey::RetentionMs, TopicConfigKey::MaxRecordSize],
+        )
+        .await
+        .expect("Failed to alter_topic_config");
+
+    assert_eq!(state.config, TopicConfig {
+        segment_size: Some(2048),
+        ..Default::default()
+    });
+}
+
+#[tokio::test]
+async fn test_broker_record_limits() {
+    let config = Config {
+        limits: LimitsConfig {
+            max_record_size: 8,
+            max_headers: 1,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+
+    // A topic can not raise the broker limit
+    let topic_id = lock
+        .create_topic(&Principal::anonymous(), None, "foo", Some(1), TopicConfig {
+            max_record_size: Some(100),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create_topic");
+
+    let result = lock
+        .produce(
+            &Principal::anonymous(),
+            Identifier::Id(topic_id),
+            0,
+            "Hello".into(),
+            "World".into(),
+            vec![],
+        )
+        .await;
+    assert!(
+        matches!(result, Err(Error::RecordTooLarge(10, 8))),
+        "Expected Error::RecordTooLarge(10, 8) but got {result:?}"
+    );
+
+    let header = || RecordHeader {
+        key: "a".to_string(),
+        value: "b".into(),
+    };
+    let result = lock
+        .produce(
+            &Principal::anonymous(),
+            Identifier::Id(topic_id),
+            0,
+            "k".into(),
+            "v".into(),
+            vec![header(), header()],
+        )
+        .await;
+    assert!(
+        matches!(result, Err(Error::TooManyHeaders(2, 1))),
+        "Expected Error::TooManyHeaders(2, 1) but got {result:?}"
+    );
+
+    lock.produce(
+        &Principal::anonymous(),
+        Identifier::Id(topic_id),
+        0,
+        "k".into(),
+        "v".into(),
+        vec![header()],
+    )
+    .await
+    .expect("Failed to produce record within the limits");
+}
+
+#[tokio::test]
+async fn test_topic_config_persists_on_reload() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = dir.path().to_str().unwrap().to_string();
+
+    let config = Config {
+        path: path.to_string(),
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let topic_id = lock
+        .create_topic(&Principal::anonymous(), None, "foo", Some(1), TopicConfig {
+            segment_size: Some(1024),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create_topic");
+    lock.alter_topic_config(
+        &Principal::anonymous(),
+        &Identifier::Id(topic_id),
+        &TopicConfig {
+            cleanup_policy: Some(CleanupPolicy::Retain),
+            ..Default::default()
+        },
+        &[],
+    )
+    .await
+    .expect("Failed to alter_topic_config");
+    drop(lock);
+    drop(app);
+
+    let config = Config {
+        path,
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let lock = app.read().await;
+    let state = lock
+        .topic_state(&Principal::anonymous(), &Identifier::Id(topic_id))
+        .expect("Failed to get topic_state");
+    assert_eq!(state.config, TopicConfig {
+        segment_size: Some(1024),
+        cleanup_policy: Some(CleanupPolicy::Retain),
+        ..Default::default()
+    });
+}
+
+#[tokio::test]
+async fn test_alter_partitions() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = dir.path().to_str().unwrap().to_string();
+
+    let config = Config {
+        path: path.to_string(),
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let topic_id = lock
+        .create_topic(
+            &Principal::anonymous(),
+            None,
+            "foo",
+            Some(2),
+            TopicConfig::default(),
+        )
+        .await
+        .expect("Failed to create_topic");
+
+    let state = lock
+        .alter_partitions(&Principal::anonymous(), &Identifier::Id(topic_id), 4)
+        .await
+        .expect("Failed to alter_partitions");
+    assert_eq!(state.partitions.len(), 4);
+
+    let offset = lock
+        .produce(
+            &Principal::anonymous(),
+            Identifier::Id(topic_id),
+            3,
+            "Hello".into(),
+            "World".into(),
+            vec![],
+        )
+        .await
+        .expect("Failed to produce record");
+    assert_eq!(offset, 0);
+
+    let result = lock
+        .alter_partitions(&Principal::anonymous(), &Identifier::Id(topic_id), 3)
+        .await;
+    assert!(
+        matches!(result, Err(Error::PartitionCountDecrease(4, 3))),
+        "Expected Error::PartitionCountDecrease(4, 3) but got {result:?}"
+    );
+    drop(lock);
+    drop(app);
+
+    let config = Config {
+        path,
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let lock = app.read().await;
+    let record = lock
+        .read_exact(&Identifier::Id(topic_id), 3, 0)
+        .await
+        .expect("Failed to read record")
+        .expect("Did not receive a record");
+    assert_eq!(record.value, "World");
+}
+
+#[tokio::test]
+async fn test_rename_topic() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = dir.path().to_str().unwrap().to_string();
+
+    let config = Config {
+        path: path.to_string(),
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let topic_id = lock
+        .create_topic(
+            &Principal::anonymous(),
+            None,
+            "foo",
+            Some(1),
+            TopicConfig::default(),
+        )
+        .await
+        .expect("Failed to create_topic");
+    lock.create_topic(
+        &Principal::anonymous(),
+        None,
+        "bar",
+        Some(1),
+        TopicConfig::default(),
+    )
+    .await
+    .expect("Failed to create_topic");
+
+    lock.produce(
+        &Principal::anonymous(),
+        Identifier::Id(topic_id),
+        0,
+        "Hello".into(),
+        "World".into(),
+        vec![],
+    )
+    .await
+    .expect("Failed to produce record");
+
+    let result = lock
+        .rename_topic(&Principal::anonymous(), &Identifier::Id(topic_id), "bar")
+        .await;
+    assert!(matches!(result, Err(Error::TopicNameInUse(_))));
+
+    let result = lock
+        .rename_topic(&Principal::anonymous(), &Identifier::Id(topic_id), "__foo")
+        .await;
+    assert!(matches!(result, Err(Error::ReservedTopicName)));
+
+    let state = lock
+        .rename_topic(
+            &Principal::anonymous(),
+            &Identifier::Name("foo".to_string()),
+            "baz",
+        )
+        .await
+        .expect("Failed to rename_topic");
+    assert_eq!(state.name, "baz");
+    assert_eq!(state.topic_id, topic_id);
+
+    let result = lock.get_topic_by_name("foo");
+    assert!(matches!(result, Err(Error::TopicNameNotFound(_))));
+    drop(lock);
+    drop(app);
+
+    let config = Config {
+        path,
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let lock = app.read().await;
+    let record = lock
+        .read_exact(&Identifier::Name("baz".to_string()), 0, 0)
+        .await
+        .expect("Failed to read record")
+        .expect("Did not receive a record");
+    assert_eq!(record.value, "World");
+}
+
+#[tokio::test]
+async fn test_reload_config_applies_to_topics() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = dir.path().to_str().unwrap().to_string();
+
+    let config = Config {
+        path: path.to_string(),
+        segment: SegmentConfig { size: 1 },
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let topic_id = lock
+        .create_topic(
+            &Principal::anonymous(),
+            None,
+            "foo",
+            Some(1),
+            TopicConfig::default(),
+        )
+        .await
+        .expect("Failed to create_topic");
+
+    for _ in 0..3 {
+        lock.produce(
+            &Principal::anonymous(),
+            Identifier::Id(topic_id),
+            0,
+            "Hello".into(),
+            "World".into(),
+            vec![],
+        )
+        .await
+        .expect("Failed to produce record");
+    }
+
+    let removed = lock
+        .enforce_retention()
+        .await
+        .expect("Failed to enforce retention");
+    assert_eq!(removed, 0);
+
+    let mut config = Config {
+        path,
+        ..Default::default()
+    };
+    config.topic.retention_bytes = Some(1);
+    let changes = lock.reload_config(config);
+    assert_eq!(changes.applied, vec!["topic.retention_bytes"]);
+    assert_eq!(changes.restart_required, vec!["segment.size"]);
+
+    let removed = lock
+        .enforce_retention()
+        .await
+        .expect("Failed to enforce retention");
+    assert_eq!(removed, 2);
+}
+
+#[tokio::test]
+async fn test_encode_metrics() {
+    let config = Config::default();
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let topic_id = lock
+        .create_topic(
+            &Principal::anonymous(),
+            None,
+            "metrics-foo",
+            Some(2),
+            TopicConfig::default(),
+        )
+        .await
+        .expect("Failed to create_topic");
+
+    lock.produce(
+        &Principal::anonymous(),
+        Identifier::Id(topic_id),
+        1,
+        "Hello".into(),
+        "World".into(),
+        vec![],
+    )
+    .await
+    .expect("Failed to produce record");
+
+    let text = lock.encode_metrics();
+    assert!(
+        text.contains("pigeon_partition_log_end_offset{topic=\"metrics-foo\",partition=\"1\"} 1\n")
+    );
+    assert!(text.contains("pigeon_partition_segments{topic=\"metrics-foo\",partition=\"0\"} 1\n"));
+    assert!(text.contains("pigeon_topic_disk_usage_bytes{topic=\"metrics-foo\"} "));
+    assert!(text.contains("pigeon_fetch_subscribers{topic=\"metrics-foo\"} 0\n"));
+    assert!(text.contains("pigeon_bytes_in_total{topic=\"metrics-foo\"} 10\n"));
+}
+
+#[tokio::test]
+async fn test_shutdown_syncs_topics() {
+    let config = Config::default();
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let topic_id = app
+        .write()
+        .await
+        .create_topic(
+            &Principal::anonymous(),
+            None,
+            "foo",
+            Some(1),
+            TopicConfig::default(),
+        )
+        .await
+        .expect("Failed to create_topic");
+    app.write()
+        .await
+        .produce(
+            &Principal::anonymous(),
+            Identifier::Id(topic_id),
+            0,
+            "Hello".into(),
+            "World".into(),
+            vec![],
+        )
+        .await
+        .expect("Failed to produce record");
+
+    let mut shutdown = app.shutdown_receiver();
+    assert!(!app.is_shutting_down());
+
+    app.shutdown();
+    assert!(app.is_shutting_down());
+    shutdown
+        .wait_for(|shutdown| *shutdown)
+        .await
+        .expect("Shutdown sender dropped");
+
+    app.write().await.sync().await.expect("Failed to sync");
+}
@@ -0,0 +1,181 @@
+use std::fs;
+
+use shared::data::{identifier::Identifier, topic_config::TopicConfig};
+use tempfile::tempdir;
+
+use crate::{
+    app::{App, error::Error},
+    auth::Principal,
+    config::{Config, TrashConfig},
+};
+
+#[tokio::test]
+async fn test_stray_topic_dir_is_moved_to_trash() {
+    let dir = tempdir().expect("Failed to create tempdir");
+    let path = dir.path().to_str().unwrap().to_string();
+
+    let config = Config {
+        path: path.to_string(),
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let topic_id = lock
+        .create_topic(
+            &Principal::anonymous(),
+            None,
+            "foo",
+            Some(2),
+            TopicConfig::default(),
+        )
+        .await
+        .expect("Failed to create_topic");
+    lock.produce(
+        &Principal::anonymous(),
+        Identifier::Id(topic_id),
+        1,
+        "Hello".into(),
+        "World".into(),
+        vec![],
+    )
+    .await
+    .expect("Failed to produce record");
+
+    // Lose all metadata entries, leaving the topic directory behind
+    fs::remove_dir_all(lock.config.base_log_dir().partition_path(0, 0)).unwrap();
+    drop(lock);
+    drop(app);
+
+    let config = Config {
+        path,
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let trash = lock.trash_states().await.expect("Failed to list trash");
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].topic_id, topic_id);
+    assert_eq!(trash[0].name, None);
+    assert_eq!(trash[0].partitions, 2);
+
+    let result = lock.restore_trash(&trash[0].trash_id, None).await;
+    assert!(matches!(result, Err(Error::InvalidName(_))));
+
+    let state = lock
+        .restore_trash(&trash[0].trash_id, Some("restored"))
+        .await
+        .expect("Failed to restore_trash");
+    assert_eq!(state.name, "restored");
+    assert_eq!(state.partitions.len(), 2);
+
+    let record = lock
+        .read_exact(&Identifier::Id(state.topic_id), 1, 0)
+        .await
+        .expect("Failed to read record")
+        .expect("Did not receive a record");
+    assert_eq!(record.value, "World");
+
+    let trash = lock.trash_states().await.expect("Failed to list trash");
+    assert!(trash.is_empty());
+}
+
+#[tokio::test]
+async fn test_soft_delete_topic() {
+    let config = Config {
+        trash: TrashConfig {
+            retention_ms: None,
+            soft_delete: true,
+        },
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let topic_id = lock
+        .create_topic(
+            &Principal::anonymous(),
+            None,
+            "foo",
+            Some(1),
+            TopicConfig::default(),
+        )
+        .await
+        .expect("Failed to create_topic");
+    lock.produce(
+        &Principal::anonymous(),
+        Identifier::Id(topic_id),
+        0,
+        "Hello".into(),
+        "World".into(),
+        vec![],
+    )
+    .await
+    .expect("Failed to produce record");
+
+    lock.delete_topic(&Principal::anonymous(), &Identifier::Id(topic_id))
+        .await
+        .expect("Failed to delete_topic");
+
+    let trash = lock.trash_states().await.expect("Failed to list trash");
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].name.as_deref(), Some("foo"));
+
+    let result = lock.restore_trash("../topics", None).await;
+    assert!(matches!(result, Err(Error::TrashNotFound(_))));
+
+    let state = lock
+        .restore_trash(&trash[0].trash_id, None)
+        .await
+        .expect("Failed to restore_trash");
+    assert_eq!(state.name, "foo");
+
+    let record = lock
+        .read_exact(&Identifier::Name("foo".to_string()), 0, 0)
+        .await
+        .expect("Failed to read record")
+        .expect("Did not receive a record");
+    assert_eq!(record.value, "World");
+}
+
+#[tokio::test]
+async fn test_purge_trash() {
+    let config = Config {
+        trash: TrashConfig {
+            retention_ms: Some(0),
+            soft_delete: true,
+        },
+        ..Default::default()
+    };
+    let app = App::load_from_disk(config)
+        .await
+        .expect("load_from_disk failed");
+
+    let mut lock = app.write().await;
+    let topic_id = lock
+        .create_topic(
+            &Principal::anonymous(),
+            None,
+            "foo",
+            Some(1),
+            TopicConfig::default(),
+        )
+        .await
+        .expect("Failed to create_topic");
+    lock.delete_topic(&Principal::anonymous(), &Identifier::Id(topic_id))
+        .await
+        .expect("Failed to delete_topic");
+
+    let removed = lock.purge_trash().await.expect("Failed to purge_trash");
+    assert_eq!(removed, 1);
+
+    let trash = lock.trash_states().await.expect("Failed to list trash");
+    assert!(trash.is_empty());
+}
@@ -1 +1,15 @@
+mod app_acl_tests;
+mod app_archive_tests;
+mod app_backup_tests;
+mod app_credentials_tests;
+mod app_dead_letter_tests;
+mod app_delivery_tests;
+mod app_disk_tests;
+mod app_metadata_tests;
+mod app_placement_tests;
+mod app_queue_tests;
+mod app_quota_tests;
+mod app_segment_tests;
+mod app_tiering_tests;
 mod app_topic_tests;
+mod app_trash_tests;
@@ -0,0 +1,92 @@
+use std::sync::atomic::Ordering;
+use std::time::{Duration, SystemTime};
+
+use shared::data::timestamp::Timestamp;
+use tracing::{debug, warn};
+
+use crate::{dur::storage::OffloadCandidate, metrics::METRICS};
+
+use super::{App, AppLock, error::Result};
+
+impl AppLock {
+    /// Closed segments of non-internal topics whose newest record is older than
+    /// `tiering.after_ms`
+    pub async fn offload_candidates(&self) -> Result<Vec<OffloadCandidate>> {
+        let Some(after_ms) = self.config.tiering.after_ms else {
+            return Ok(Vec::new());
+        };
+        let before = Timestamp::from(SystemTime::now() - Duration::from_millis(after_ms));
+
+        let mut candidates = Vec::new();
+        for topic in self.topics.values() {
+            if topic.is_internal() {
+                continue;
+            }
+
+            candidates.append(&mut topic.offload_candidates(before).await?);
+        }
+
+        Ok(candidates)
+    }
+
+    /// Switches a segment over to its uploaded log, returns false when the segment is gone
+    pub async fn complete_offload(&mut self, candidate: OffloadCandidate) -> Result<bool> {
+        let Some(storage) = self.config.tiering.backend.clone() else {
+            return Ok(false);
+        };
+        let Some(topic) = self.topics.get_mut(&candidate.topic_id) else {
+            return Ok(false);
+        };
+
+        Ok(topic
+            .complete_offload(
+                candidate.partition_id,
+                candidate.start_offset,
+                storage,
+                candidate.key,
+            )
+            .await?)
+    }
+}
+
+impl App {
+    /// Moves the logs of old closed segments to tiered storage, returns the amount of offloaded
+    /// segments. Closed segments are never written to, so logs are uploaded without holding the
+    /// lock. A failed segment is logged and counted, and retried on the next run
+    pub async fn offload_segments(&self) -> Result<usize> {
+        let (candidates, storage) = {
+            let lock = self.read().await;
+            let Some(storage) = lock.config.tiering.backend.clone() else {
+                return Ok(0);
+            };
+
+            (lock.offload_candidates().await?, storage)
+        };
+
+        let mut offloaded = 0;
+        for candidate in candidates {
+            let key = candidate.key.clone();
+            if let Err(e) = storage.put(&key, candidate.log_path.as_ref()).await {
+                warn!("Failed to upload {} as {key} {e}", candidate.log_path);
+                METRICS.offload_failures.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+
+            match self.write().await.complete_offload(candidate).await {
+                Ok(true) => offloaded += 1,
+                Ok(false) => {
+                    debug!("Segment was removed during its offload, deleting {key}");
+                    if let Err(e) = storage.delete(&key).await {
+                        warn!("Failed to delete {key} {e}");
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to complete the offload of {key} {e}");
+                    METRICS.offload_failures.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+
+        Ok(offloaded)
+    }
+}
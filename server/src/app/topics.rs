@@ -3,17 +3,26 @@ use std::collections::hash_map::Entry;
 use std::sync::Arc;
 
 use bytes::Bytes;
+use shared::consts::{DELIVER_AT_HEADER, EXPIRES_AT_HEADER, RESERVED_HEADER_PREFIX};
+use shared::data::acl::AclOperation;
 use shared::data::identifier::Identifier;
 use shared::data::offset_selection::OffsetSelection;
+use shared::data::quota::DEFAULT_QUOTA_NAME;
+use shared::data::timestamp::Timestamp;
+use shared::data::topic_config::{TopicConfig, TopicConfigKey};
 use shared::state::topic_state::TopicState;
 use tokio::sync::broadcast;
 use tracing::{debug, info, warn};
 
+use crate::auth::{CREDENTIALS_TOPIC, Principal};
 use crate::dur::record::{Record, RecordHeader};
 use crate::dur::topic::Topic;
 use crate::meta::MetadataEntry;
+use crate::meta::add_partitions_entry::AddPartitionsEntry;
+use crate::meta::alter_topic_config_entry::AlterTopicConfigEntry;
 use crate::meta::create_topic_entry::CreateTopicEntry;
 use crate::meta::delete_topic_entry::DeleteTopicEntry;
+use crate::meta::rename_topic_entry::RenameTopicEntry;
 use crate::record_batch::RecordBatch;
 
 use super::AppLock;
@@ -25,24 +34,18 @@ impl AppLock {
         topic_id: Option<u64>,
         name: &str,
         partition_count: Option<u64>,
+        config: TopicConfig,
     ) -> Result<u64> {
-        if name.is_empty() {
+        // `*` names the default topic quota
+        if name.is_empty() || name == DEFAULT_QUOTA_NAME {
             return Err(Error::InvalidName(name.to_string()));
         }
 
+        validate_topic_config(&config)?;
+
         let topic_id = match topic_id {
             Some(topic_id) => topic_id,
-            None => loop {
-                let topic_id = self.next_topic_id;
-                self.next_topic_id += 1;
-                if !self.topics.contains_key(&topic_id) {
-                    break topic_id;
-                }
-
-                if topic_id == u64::MAX {
-                    return Err(Error::MaxTopicIdReached);
-                }
-            },
+            None => self.next_free_topic_id()?,
         };
 
         if self.topics.contains_key(&topic_id) {
@@ -56,8 +59,10 @@ impl AppLock {
         let partition_count = partition_count.unwrap_or(self.config.topic.num_partitions);
 
         info!("Creating topic with topic_id: {topic_id} and name {name}");
-        let topic =
-            Topic::load_from_disk(self.config.clone(), topic_id, name, partition_count).await?;
+        let log_dirs = self.place_partitions(topic_id, 0..partition_count).await?;
+        let mut topic =
+            Topic::load_from_disk(self.config.clone(), topic_id, name, log_dirs).await?;
+        topic.set_config(config.clone());
 
         self.topics.insert(topic_id, topic);
         self.topic_ids.insert(name.to_string(), topic_id);
@@ -66,33 +71,182 @@ impl AppLock {
             topic_id,
             name: name.to_string(),
             partitions: partition_count,
+            config,
+            log_dirs: self.placements(topic_id, 0..partition_count)?,
         }))
         .await?;
 
         Ok(topic_id)
     }
 
+    pub(super) fn next_free_topic_id(&mut self) -> Result<u64> {
+        loop {
+            let topic_id = self.next_topic_id;
+            self.next_topic_id += 1;
+            if !self.topics.contains_key(&topic_id) {
+                return Ok(topic_id);
+            }
+
+            if topic_id == u64::MAX {
+                return Err(Error::MaxTopicIdReached);
+            }
+        }
+    }
+
     pub async fn create_topic(
         &mut self,
+        principal: &Principal,
         topic_id: Option<u64>,
         name: &str,
         partition_count: Option<u64>,
+        config: TopicConfig,
     ) -> Result<u64> {
         if name.starts_with("__") {
             return Err(Error::ReservedTopicName);
         }
 
-        self.create_topic_internal(topic_id, name, partition_count)
+        self.authorize(principal, AclOperation::Create, name)?;
+
+        self.create_topic_internal(topic_id, name, partition_count, config)
             .await
     }
 
-    pub async fn delete_topic(&mut self, identifer: &Identifier) -> Result<()> {
+    pub async fn alter_topic_config(
+        &mut self,
+        principal: &Principal,
+        identifier: &Identifier,
+        config: &TopicConfig,
+        reset: &[TopicConfigKey],
+    ) -> Result<TopicState> {
+        let topic = self.get_topic(identifier)?;
+
+        if topic.is_internal() {
+            return Err(Error::InternalTopicName(topic.name().to_string()));
+        }
+
+        self.authorize(principal, AclOperation::Alter, topic.name())?;
+
+        let topic_id = topic.id();
+        let config = topic.topic_config().reset(reset).merge(config);
+        validate_topic_config(&config)?;
+
+        info!("Altering config of topic {topic_id} to {config:?}");
+        self.append_metadata(MetadataEntry::AlterTopicConfig(AlterTopicConfigEntry {
+            topic_id,
+            config: config.clone(),
+        }))
+        .await?;
+
+        let topic = self.get_topic_by_id_mut(topic_id)?;
+        topic.set_config(config);
+
+        Ok(topic.state())
+    }
+
+    pub async fn alter_partitions(
+        &mut self,
+        principal: &Principal,
+        identifier: &Identifier,
+        partition_count: u64,
+    ) -> Result<TopicState> {
+        let topic = self.get_topic(identifier)?;
+
+        if topic.is_internal() {
+            return Err(Error::InternalTopicName(topic.name().to_string()));
+        }
+
+        self.authorize(principal, AclOperation::Alter, topic.name())?;
+
+        let topic_id = topic.id();
+        let current = topic.partition_count();
+        if partition_count < current {
+            return Err(Error::PartitionCountDecrease(current, partition_count));
+        }
+
+        if partition_count == current {
+            return Ok(topic.state());
+        }
+
+        info!("Increasing partitions of topic {topic_id} from {current} to {partition_count}");
+        let log_dirs = self
+            .place_partitions(topic_id, current..partition_count)
+            .await?;
+        self.get_topic_by_id_mut(topic_id)?
+            .add_partitions(log_dirs)
+            .await?;
+
+        self.append_metadata(MetadataEntry::AddPartitions(AddPartitionsEntry {
+            topic_id,
+            partitions: partition_count,
+            log_dirs: self.placements(topic_id, current..partition_count)?,
+        }))
+        .await?;
+
+        Ok(self.get_topic_by_id(topic_id)?.state())
+    }
+
+    pub async fn rename_topic(
+        &mut self,
+        principal: &Principal,
+        identifier: &Identifier,
+        name: &str,
+    ) -> Result<TopicState> {
+        // `*` names the default topic quota
+        if name.is_empty() || name == DEFAULT_QUOTA_NAME {
+            return Err(Error::InvalidName(name.to_string()));
+        }
+
+        if name.starts_with("__") {
+            return Err(Error::ReservedTopicName);
+        }
+
+        let topic = self.get_topic(identifier)?;
+
+        if topic.is_internal() {
+            return Err(Error::InternalTopicName(topic.name().to_string()));
+        }
+
+        // Authorized first, so a denied principal does not learn which names are in use
+        self.authorize(principal, AclOperation::Alter, topic.name())?;
+        self.authorize(principal, AclOperation::Create, name)?;
+
+        if self.topic_ids.contains_key(name) {
+            return Err(Error::TopicNameInUse(name.to_string()));
+        }
+
+        let topic_id = topic.id();
+        let old_name = topic.name().to_string();
+
+        info!("Renaming topic {topic_id} from {old_name} to {name}");
+        self.append_metadata(MetadataEntry::RenameTopic(RenameTopicEntry {
+            topic_id,
+            name: name.to_string(),
+        }))
+        .await?;
+
+        self.topic_ids.remove(&old_name);
+        self.topic_ids.insert(name.to_string(), topic_id);
+        self.rename_topic_quota(&old_name, name).await?;
+
+        let topic = self.get_topic_by_id_mut(topic_id)?;
+        topic.rename(name);
+
+        Ok(topic.state())
+    }
+
+    pub async fn delete_topic(
+        &mut self,
+        principal: &Principal,
+        identifer: &Identifier,
+    ) -> Result<()> {
         let topic = self.get_topic(identifer)?;
 
         if topic.is_internal() {
             return Err(Error::InternalTopicName(topic.name().to_string()));
         }
 
+        self.authorize(principal, AclOperation::Delete, topic.name())?;
+
         let topic_id = topic.id();
         let topic_name = topic.name().to_string();
 
@@ -100,6 +254,8 @@ impl AppLock {
 
         self.append_metadata(MetadataEntry::DeleteTopic(DeleteTopicEntry { topic_id }))
             .await?;
+        self.delete_queue(topic_id).await?;
+        self.delete_topic_quota(&topic_name).await?;
 
         self.topic_ids.remove(&topic_name);
         if let Some(topic) = self.topics.remove(&topic_id) {
@@ -122,6 +278,7 @@ impl AppLock {
 
     pub async fn read_batch(
         &self,
+        principal: &Principal,
         batch: &mut RecordBatch,
         offset: &OffsetSelection,
         partition_id: u64,
@@ -129,6 +286,12 @@ impl AppLock {
     ) -> Result<()> {
         let topic = self.get_topic(identifier)?;
 
+        if topic.name() == CREDENTIALS_TOPIC {
+            return Err(Error::InternalTopicName(topic.name().to_string()));
+        }
+
+        self.authorize(principal, AclOperation::Fetch, topic.name())?;
+
         Ok(topic.read_batch(batch, offset, partition_id).await?)
     }
 
@@ -177,29 +340,132 @@ impl AppLock {
             .inspect_err(|e| warn!("get_topic_by_name {e}"))
     }
 
+    pub fn max_request_size(&self) -> usize {
+        self.config.http.max_request_size
+    }
+
+    /// Producers can only set the delivery headers of the reserved headers
     pub async fn produce(
         &mut self,
+        principal: &Principal,
         identifier: Identifier,
         partition_id: u64,
         key: Bytes,
         value: Bytes,
         headers: Vec<RecordHeader>,
     ) -> Result<u64> {
-        let topic = self.get_topic_mut(&identifier)?;
+        self.validate_reserved_headers(&headers)?;
+
+        self.produce_record(principal, identifier, partition_id, key, value, headers)
+            .await
+    }
+
+    /// Produces a record with any reserved headers, for records the broker adds headers to
+    pub(super) async fn produce_record(
+        &mut self,
+        principal: &Principal,
+        identifier: Identifier,
+        partition_id: u64,
+        key: Bytes,
+        value: Bytes,
+        headers: Vec<RecordHeader>,
+    ) -> Result<u64> {
+        let topic = self.get_topic(&identifier)?;
 
         if topic.is_internal() {
             return Err(Error::InternalTopicName(topic.name().to_string()));
         }
 
+        self.authorize(principal, AclOperation::Produce, topic.name())?;
+
+        let size = self.validate_record(topic, &key, &value, &headers)?;
+        let charges = self.check_produce_quota(principal, topic.name(), size)?;
+
+        let log_dir = topic.log_dir(partition_id)?.clone();
+        self.check_disk_space(&log_dir)?;
+
+        let disk_space = self.disk_space.clone();
+        let topic = self.get_topic_mut(&identifier)?;
+
         let record = topic
             .append(partition_id, key, value, headers)
             .await
-            .inspect_err(|e| warn!("Produce error: {e}"))?;
+            .inspect_err(|e| {
+                warn!("Produce error: {e}");
+                disk_space.handle_append_error(&log_dir, e);
+            })?;
 
         debug!("Appended record to {identifier} offset: {}", record.offset);
 
         let topic_id = topic.id();
         let offset = record.offset;
+        self.charge_produce_quota(charges);
+        self.notify_listeners(topic_id, partition_id, record);
+
+        Ok(offset)
+    }
+
+    /// Rejects records over the size or header limits of the topic, which also keep the lengths
+    /// within the prefixes of the log format. Returns the size of the record
+    pub(super) fn validate_record(
+        &self,
+        topic: &Topic,
+        key: &[u8],
+        value: &[u8],
+        headers: &[RecordHeader],
+    ) -> Result<u64> {
+        let max_headers = self.config.limits.max_headers;
+        if headers.len() as u64 > max_headers {
+            return Err(Error::TooManyHeaders(headers.len() as u64, max_headers));
+        }
+
+        let max_record_size = topic.max_record_size();
+        let size = (key.len() + value.len() + headers.iter().map(RecordHeader::size).sum::<usize>())
+            as u64;
+        if size > max_record_size {
+            return Err(Error::RecordTooLarge(size, max_record_size));
+        }
+
+        Ok(size)
+    }
+
+    /// Rejects reserved headers other than the delivery headers, delivery headers that do not
+    /// hold a timestamp and a `__deliver_at` further away than `limits.max_deliver_delay_ms`
+    fn validate_reserved_headers(&self, headers: &[RecordHeader]) -> Result<()> {
+        let invalid =
+            |key: &str, reason: String| Err(Error::InvalidReservedHeader(key.to_string(), reason));
+
+        for header in headers {
+            let key = header.key.as_str();
+            if !key.starts_with(RESERVED_HEADER_PREFIX) {
+                continue;
+            }
+            if key != EXPIRES_AT_HEADER && key != DELIVER_AT_HEADER {
+                return invalid(key, "only the delivery headers can be produced".to_string());
+            }
+
+            let Some(timestamp) = str::from_utf8(&header.value)
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+            else {
+                return invalid(key, "expected a timestamp in microseconds".to_string());
+            };
+
+            let max_delay_ms = self.config.limits.max_deliver_delay_ms;
+            let delay_ms = timestamp.saturating_sub(Timestamp::now().as_micros()) / 1000;
+            if key == DELIVER_AT_HEADER && delay_ms > max_delay_ms {
+                return invalid(
+                    key,
+                    format!("delay of {delay_ms} ms exceeds the maximum of {max_delay_ms} ms"),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Passes an appended record to the fetches long-polling the topic
+    pub(super) fn notify_listeners(&self, topic_id: u64, partition_id: u64, record: Record) {
         let notify_count = self
             .listeners
             .get(&topic_id)
@@ -207,15 +473,22 @@ impl AppLock {
             .unwrap_or(0);
 
         debug!("Notified {notify_count} listeners for topic {topic_id}");
-
-        Ok(offset)
     }
 
     pub fn subscribe(
         &mut self,
+        principal: &Principal,
         identifer: &Identifier,
     ) -> Result<broadcast::Receiver<(u64, Arc<Record>)>> {
-        let topic_id = self.get_topic(identifer)?.id();
+        let topic = self.get_topic(identifer)?;
+
+        if topic.name() == CREDENTIALS_TOPIC {
+            return Err(Error::InternalTopicName(topic.name().to_string()));
+        }
+
+        self.authorize(principal, AclOperation::Fetch, topic.name())?;
+
+        let topic_id = topic.id();
 
         let rx = match self.listeners.entry(topic_id) {
             Entry::Occupied(occupied_entry) => occupied_entry.get().subscribe(),
@@ -229,10 +502,43 @@ impl AppLock {
         Ok(rx)
     }
 
-    pub fn topic_states(&self) -> HashMap<u64, TopicState> {
+    pub fn topic_state(
+        &self,
+        principal: &Principal,
+        identifier: &Identifier,
+    ) -> Result<TopicState> {
+        let topic = self.get_topic(identifier)?;
+
+        self.authorize(principal, AclOperation::Describe, topic.name())?;
+
+        Ok(topic.state())
+    }
+
+    /// States of all topics the principal is allowed to describe
+    pub fn topic_states(&self, principal: &Principal) -> HashMap<u64, TopicState> {
         self.topics
             .iter()
+            .filter(|entry| {
+                self.authorize(principal, AclOperation::Describe, entry.1.name())
+                    .is_ok()
+            })
             .map(|entry| (*entry.0, entry.1.state()))
             .collect()
     }
 }
+
+fn validate_topic_config(config: &TopicConfig) -> Result<()> {
+    if config.segment_size == Some(0) {
+        return Err(Error::InvalidTopicConfig(
+            "segment_size must be larger than 0".to_string(),
+        ));
+    }
+
+    if config.max_record_size == Some(0) {
+        return Err(Error::InvalidTopicConfig(
+            "max_record_size must be larger than 0".to_string(),
+        ));
+    }
+
+    Ok(())
+}
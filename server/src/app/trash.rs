@@ -0,0 +1,73 @@
+use std::time::{Duration, SystemTime};
+
+use shared::{
+    data::timestamp::Timestamp,
+    state::{topic_state::TopicState, trash_state::TrashState},
+};
+
+use crate::dur::trash;
+
+use super::{
+    AppLock,
+    error::{Error, Result},
+};
+
+impl AppLock {
+    pub async fn trash_states(&self) -> Result<Vec<TrashState>> {
+        Ok(trash::list(&self.config).await?)
+    }
+
+    /// Recreates a topic from a trash entry under a new topic id, `name` overrides the original
+    /// name of the topic
+    pub async fn restore_trash(
+        &mut self,
+        trash_id: &str,
+        name: Option<&str>,
+    ) -> Result<TopicState> {
+        let state = trash::read(&self.config, trash_id)
+            .await?
+            .ok_or(Error::TrashNotFound(trash_id.to_string()))?;
+
+        let name = name
+            .map(str::to_string)
+            .or(state.name)
+            .ok_or(Error::InvalidName(String::new()))?;
+
+        if name.is_empty() {
+            return Err(Error::InvalidName(name));
+        }
+
+        if name.starts_with("__") {
+            return Err(Error::ReservedTopicName);
+        }
+
+        if self.topic_ids.contains_key(&name) {
+            return Err(Error::TopicNameInUse(name));
+        }
+
+        let topic_id = self.next_free_topic_id()?;
+        trash::restore(&self.config, trash_id, topic_id).await?;
+
+        self.create_topic_internal(
+            Some(topic_id),
+            &name,
+            Some(state.partitions.max(1)),
+            state.config,
+        )
+        .await?;
+
+        Ok(self.get_topic_by_id(topic_id)?.state())
+    }
+
+    /// Permanently removes trash entries that are older than the trash retention
+    pub async fn purge_trash(&self) -> Result<usize> {
+        let Some(retention_ms) = self.config.trash.retention_ms else {
+            return Ok(0);
+        };
+
+        let before = SystemTime::now() - Duration::from_millis(retention_ms);
+        let before = Timestamp::from(before);
+
+        Ok(trash::purge(&self.config, before).await?)
+    }
+}
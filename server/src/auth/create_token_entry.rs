@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateTokenEntry {
+    pub name: String,
+    /// Hex encoded sha256 hash of the token, the plaintext token is never stored
+    pub hash: String,
+    pub admin: bool,
+}
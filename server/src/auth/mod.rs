@@ -0,0 +1,132 @@
+pub mod create_token_entry;
+pub mod revoke_token_entry;
+use core::str;
+
+use std::collections::HashMap;
+
+use create_token_entry::CreateTokenEntry;
+use rand::distr::{Alphanumeric, SampleString};
+use revoke_token_entry::RevokeTokenEntry;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use shared::state::token_state::TokenState;
+use tracing::warn;
+
+use crate::dur::record::Record;
+
+pub const CREDENTIALS_TOPIC: &str = "__credentials";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum CredentialEntry {
+    CreateToken(CreateTokenEntry),
+    RevokeToken(RevokeTokenEntry),
+}
+
+#[derive(Default, Debug)]
+pub struct Credentials {
+    pub tokens: HashMap<String, Credential>,
+}
+
+#[derive(Debug)]
+pub struct Credential {
+    pub name: String,
+    pub hash: String,
+    pub admin: bool,
+}
+
+/// The authenticated identity of a request
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub name: String,
+    pub admin: bool,
+}
+
+impl Principal {
+    /// The principal of requests that did not identify themselves
+    pub fn anonymous() -> Self {
+        Self {
+            name: "anonymous".to_string(),
+            admin: false,
+        }
+    }
+}
+
+impl Credentials {
+    pub fn from_records(records: Vec<Record>) -> Self {
+        let mut credentials = Credentials::default();
+
+        for record in records {
+            let entry = str::from_utf8(&record.value)
+                .map_err(|e| format!("Invalid UTF8: {e}"))
+                .and_then(|value| {
+                    serde_json::from_str::<CredentialEntry>(value)
+                        .map_err(|e| format!("Invalid JSON: {e}"))
+                });
+
+            match entry {
+                Ok(entry) => credentials.apply(entry),
+                Err(reason) => warn!(
+                    "Skipping credential entry at offset {}: {reason}",
+                    record.offset
+                ),
+            }
+        }
+
+        credentials
+    }
+
+    pub fn apply(&mut self, entry: CredentialEntry) {
+        match entry {
+            CredentialEntry::CreateToken(entry) => {
+                self.tokens.insert(entry.name.to_string(), Credential {
+                    name: entry.name,
+                    hash: entry.hash,
+                    admin: entry.admin,
+                });
+            }
+            CredentialEntry::RevokeToken(entry) => {
+                self.tokens.remove(&entry.name);
+            }
+        }
+    }
+
+    pub fn authenticate(&self, token: &str) -> Option<Principal> {
+        let hash = hash_token(token);
+
+        self.tokens
+            .values()
+            .find(|credential| constant_time_eq(credential.hash.as_bytes(), hash.as_bytes()))
+            .map(|credential| Principal {
+                name: credential.name.to_string(),
+                admin: credential.admin,
+            })
+    }
+
+    pub fn states(&self) -> Vec<TokenState> {
+        let mut states: Vec<_> = self
+            .tokens
+            .values()
+            .map(|credential| TokenState {
+                name: credential.name.to_string(),
+                admin: credential.admin,
+            })
+            .collect();
+        states.sort_by(|a, b| a.name.cmp(&b.name));
+
+        states
+    }
+}
+
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Compares without returning early, so the time taken does not reveal how much of a secret
+/// matched. Only the length can be learned
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+pub fn generate_token() -> String {
+    format!("pgn_{}", Alphanumeric.sample_string(&mut rand::rng(), 40))
+}
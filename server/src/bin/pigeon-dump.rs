@@ -0,0 +1,225 @@
+use anyhow::{Result, bail};
+use clap::Parser;
+use server::config::Config;
+use server::dur::inspect::{SegmentDump, find_log_dir, list_segments, read_segment};
+use server::dur::record::Record;
+use server::meta::Metadata;
+use server::meta::recovery::scan_topic_dirs;
+use server::meta::snapshot::MetadataSnapshot;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "pigeon-dump",
+    version,
+    author,
+    about = "Inspect and verify a pigeon data directory without running the server"
+)]
+struct Cli {
+    /// Data directory, the `path` of the server config
+    path: String,
+
+    /// Log directory holding partitions, the `log_dirs` of the server config
+    #[arg(long = "log-dir")]
+    log_dirs: Vec<String>,
+
+    /// Only inspect the topic with this id
+    #[arg(long)]
+    topic: Option<u64>,
+
+    /// Only inspect the partition with this id
+    #[arg(long)]
+    partition: Option<u64>,
+
+    /// Print every record
+    #[arg(long)]
+    records: bool,
+}
+
+/// Loads the latest snapshot and replays the `__metadata` log on top, like the server does
+async fn load_metadata(config: &Config) -> Result<Metadata> {
+    let snapshot = MetadataSnapshot::load_latest(config).await?;
+    let (mut metadata, replay_offset) = match snapshot {
+        Some(snapshot) => {
+            println!("Metadata snapshot at offset {}", snapshot.offset);
+            (snapshot.metadata, snapshot.offset + 1)
+        }
+        None => (Metadata::default(), 0),
+    };
+
+    let mut records = Vec::new();
+    let log_dir = config.base_log_dir();
+    for start_offset in list_segments(&log_dir, 0, 0).await? {
+        let dump = read_segment(&log_dir, 0, 0, start_offset).await?;
+        records.extend(
+            dump.records
+                .into_iter()
+                .map(|(_, record)| record)
+                .filter(|record| record.offset >= replay_offset),
+        );
+    }
+
+    let replayed = records.len();
+    let invalid = metadata.apply_records(records);
+    println!(
+        "Replayed {replayed} metadata entries from offset {replay_offset}, {} invalid",
+        invalid.len()
+    );
+    for entry in invalid {
+        println!("  ! entry at offset {}: {}", entry.offset, entry.reason);
+    }
+
+    Ok(metadata)
+}
+
+fn print_segment(dump: &SegmentDump) {
+    let offsets = match (dump.records.first(), dump.records.last()) {
+        (Some((_, first)), Some((_, last))) => format!("{}..={}", first.offset, last.offset),
+        _ => "empty".to_string(),
+    };
+
+    if let Some(key) = &dump.tiered_key {
+        println!(
+            "    segment {}: offloaded as {key}, {} bytes, {} index entries",
+            dump.start_offset,
+            dump.log_size,
+            dump.index.len()
+        );
+        return;
+    }
+
+    println!(
+        "    segment {}: offsets {offsets}, {} records, {} bytes, {} index entries",
+        dump.start_offset,
+        dump.records.len(),
+        dump.log_size,
+        dump.index.len()
+    );
+}
+
+fn print_record(record: &Record) {
+    let headers = record
+        .headers
+        .iter()
+        .map(|header| format!("{}={}", header.key, String::from_utf8_lossy(&header.value)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    println!(
+        "      {} @{} key={:?} value={:?} headers=[{headers}]",
+        record.offset,
+        record.timestamp.as_micros(),
+        String::from_utf8_lossy(&record.key),
+        String::from_utf8_lossy(&record.value),
+    );
+}
+
+#[tokio::main]
+pub async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let config = Config {
+        path: cli.path.clone(),
+        log_dirs: cli.log_dirs.clone(),
+        ..Default::default()
+    };
+
+    let metadata = load_metadata(&config).await?;
+    let mut problems = 0;
+
+    let mut unexpected_paths = Vec::new();
+    let topic_dirs = scan_topic_dirs(&config, &mut unexpected_paths).await?;
+    for path in &unexpected_paths {
+        println!("! unexpected path {}", path.display());
+        problems += 1;
+    }
+
+    for topic_metadata in metadata.topics.values() {
+        if !topic_dirs.contains_key(&topic_metadata.topic_id) {
+            println!(
+                "! topic {} ({}) has no directory",
+                topic_metadata.topic_id, topic_metadata.name
+            );
+            problems += 1;
+        }
+    }
+
+    for (topic_id, partitions) in topic_dirs {
+        if cli.topic.is_some_and(|topic| topic != topic_id) {
+            continue;
+        }
+
+        match metadata.topics.get(&topic_id) {
+            Some(topic_metadata) => {
+                println!(
+                    "Topic {topic_id} ({}): {} partitions, {:?}",
+                    topic_metadata.name, topic_metadata.partitions, topic_metadata.config
+                );
+                if topic_metadata.partitions != partitions {
+                    println!("  ! {partitions} partition directories on disk");
+                    problems += 1;
+                }
+            }
+            None => {
+                println!("Topic {topic_id}: not in the metadata, {partitions} partitions on disk");
+                problems += 1;
+            }
+        }
+
+        for partition_id in 0..partitions {
+            if cli
+                .partition
+                .is_some_and(|partition| partition != partition_id)
+            {
+                continue;
+            }
+
+            let recorded = metadata
+                .topics
+                .get(&topic_id)
+                .and_then(|topic_metadata| topic_metadata.log_dirs.get(&partition_id));
+            let Some(log_dir) = find_log_dir(
+                &config,
+                topic_id,
+                partition_id,
+                recorded.map(String::as_str),
+            )
+            .await?
+            else {
+                println!("  ! partition {partition_id} has no directory");
+                problems += 1;
+                continue;
+            };
+
+            println!("  partition {partition_id} in {log_dir}");
+            if topic_id != 0
+                && let Some(recorded) = recorded
+                && *recorded != log_dir.to_string()
+            {
+                println!("    ! placed in {recorded}");
+                problems += 1;
+            }
+            for start_offset in list_segments(&log_dir, topic_id, partition_id).await? {
+                let dump = read_segment(&log_dir, topic_id, partition_id, start_offset).await?;
+                print_segment(&dump);
+
+                if cli.records {
+                    for (_, record) in &dump.records {
+                        print_record(record);
+                    }
+                }
+
+                for problem in dump.verify() {
+                    println!("      ! {problem}");
+                    problems += 1;
+                }
+            }
+        }
+    }
+
+    if problems > 0 {
+        bail!("Found {problems} problems");
+    }
+
+    println!("No problems found");
+
+    Ok(())
+}
@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to read config file ({0})")]
+    ReadFile(String, #[source] std::io::Error),
+    #[error("Invalid config: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Invalid config value for {0}: {1}")]
+    InvalidValue(String, String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,90 @@
+use std::fmt::{self, Display};
+
+/// A directory that holds topic partitions, see [`super::Config::log_dirs`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LogDir(String);
+
+impl LogDir {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self(path.into())
+    }
+
+    pub fn path(&self) -> &str {
+        &self.0
+    }
+
+    /// The copy of this directory made by a backup
+    pub fn backup_dir(&self, backup_id: &str) -> LogDir {
+        LogDir(format!("{}/backups/{}", self.0, backup_id))
+    }
+
+    /// Where a partition is copied to before it is moved into this directory
+    pub fn moving_dir(&self, move_id: &str) -> LogDir {
+        LogDir(format!("{}/{}", self.moving_path(), move_id))
+    }
+
+    pub fn moving_path(&self) -> String {
+        format!("{}/moving", self.0)
+    }
+
+    /// Where the partitions of a topic in this directory are kept while it is in the trash
+    pub fn trash_topic_path(&self, trash_id: &str) -> String {
+        format!("{}/trash/{}/topic", self.0, trash_id)
+    }
+
+    pub fn trash_entry_path(&self, trash_id: &str) -> String {
+        format!("{}/trash/{}", self.0, trash_id)
+    }
+
+    pub fn topics_path(&self) -> String {
+        format!("{}/topics", self.0)
+    }
+
+    pub fn topic_path(&self, topic_id: u64) -> String {
+        format!("{}/{}", self.topics_path(), topic_id)
+    }
+
+    pub fn partitions_path(&self, topic_id: u64) -> String {
+        format!("{}/partitions", self.topic_path(topic_id))
+    }
+
+    pub fn partition_path(&self, topic_id: u64, partition_id: u64) -> String {
+        format!("{}/{}", self.partitions_path(topic_id), partition_id)
+    }
+
+    pub fn segment_path(&self, topic_id: u64, partition_id: u64, start_offset: u64) -> String {
+        format!(
+            "{}/{:0>10}",
+            self.partition_path(topic_id, partition_id),
+            start_offset
+        )
+    }
+
+    pub fn log_path(&self, topic_id: u64, partition_id: u64, start_offset: u64) -> String {
+        format!(
+            "{}.log",
+            self.segment_path(topic_id, partition_id, start_offset)
+        )
+    }
+
+    pub fn index_path(&self, topic_id: u64, partition_id: u64, start_offset: u64) -> String {
+        format!(
+            "{}.index",
+            self.segment_path(topic_id, partition_id, start_offset)
+        )
+    }
+
+    /// Marker that replaces the log of a segment offloaded to tiered storage
+    pub fn tiered_path(&self, topic_id: u64, partition_id: u64, start_offset: u64) -> String {
+        format!(
+            "{}.tiered",
+            self.segment_path(topic_id, partition_id, start_offset)
+        )
+    }
+}
+
+impl Display for LogDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
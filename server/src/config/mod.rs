@@ -1,23 +1,197 @@
+pub mod error;
+mod log_dir;
+pub mod reload;
+
+use std::{env, fmt, fs, sync::Arc};
+
+use error::{Error, Result};
+pub use log_dir::LogDir;
+use serde::{Deserialize, Serialize};
+use shared::consts::DEFAULT_PORT;
+
+use crate::{dur::storage::Storage, queue::MAX_QUEUE_DELAY_MS};
 #[cfg(test)]
 use tempfile::{TempDir, tempdir};
+use toml::{Table, Value};
+
+/// Prefix of the environment variables that override the config, e.g. `PIGEON_HTTP_PORT` sets
+/// `http.port`
+const ENV_PREFIX: &str = "PIGEON_";
 
-#[derive(Debug)]
+/// Replaces secrets when the config is printed
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
     pub path: String,
+    /// Directories partitions are placed in, each new partition goes to the least used one
+    pub log_dirs: Vec<String>,
+    pub http: HttpConfig,
+    pub log: LogConfig,
     pub topic: TopicConfig,
     pub segment: SegmentConfig,
+    pub retention: RetentionConfig,
+    pub metadata: MetadataConfig,
+    pub trash: TrashConfig,
+    pub tiering: TieringConfig,
+    pub disk: DiskConfig,
+    pub limits: LimitsConfig,
+    pub queue: QueueConfig,
+    pub auth: AuthConfig,
+    pub acl: AclConfig,
     #[cfg(test)]
+    #[serde(skip)]
     pub tempdir: TempDir,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub host: String,
+    pub port: u16,
+    /// Largest request body buffered by a handler, e.g. a produce. Imports are streamed and not
+    /// limited
+    pub max_request_size: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// `tracing` filter directives, e.g. `info,server=debug`. Unset uses the verbosity flags and
+    /// `RUST_LOG`
+    pub filter: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct SegmentConfig {
     pub size: u64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct TopicConfig {
     pub num_partitions: u64,
+    /// Default retention of topics, unset keeps records forever
+    pub retention_ms: Option<u64>,
+    pub retention_bytes: Option<u64>,
+    pub max_record_size: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionConfig {
+    /// How often topics are checked for segments outside of their retention
+    pub check_interval_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetadataConfig {
+    /// How often the metadata is checked for a new snapshot
+    pub snapshot_interval_ms: u64,
+    /// Amount of metadata entries after the last snapshot before a new snapshot is written
+    pub snapshot_min_entries: u64,
+    /// Rebuilds the topics in the metadata from the topic directories on disk during startup
+    #[serde(skip)]
+    pub rebuild: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrashConfig {
+    /// How long topic directories are kept in the trash, unset keeps them forever
+    pub retention_ms: Option<u64>,
+    /// Moves deleted topics to the trash instead of removing them directly
+    pub soft_delete: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TieringConfig {
+    /// Closed segments whose newest record is older than this are offloaded to `storage`, unset
+    /// keeps every segment on the local disk
+    pub after_ms: Option<u64>,
+    pub storage: Option<StorageConfig>,
+    /// Opened from `storage` during startup
+    #[serde(skip)]
+    pub backend: Option<Arc<dyn Storage>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum StorageConfig {
+    /// A directory, e.g. on a slower mount
+    Local { path: String },
+    /// An S3 compatible bucket, such as MinIO
+    S3(S3Config),
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct S3Config {
+    pub bucket: String,
+    /// Prepended to the key of every object
+    pub prefix: Option<String>,
+    pub endpoint: Option<String>,
+    pub region: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    /// Required for endpoints without TLS
+    #[serde(default)]
+    pub allow_http: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiskConfig {
+    /// Produces, credential and queue changes to a log directory with less free space are
+    /// rejected, fetches and metadata changes such as deleting a topic keep working
+    pub min_free_bytes: u64,
+    /// How often the free space of the log directories is checked
+    pub check_interval_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Upper bound of the record size of every topic, a topic config can only lower it
+    pub max_record_size: u64,
+    pub max_headers: u64,
+    /// How far in the future a produced record can set its `__deliver_at`, a held back record
+    /// also holds back the records after it in its partition
+    pub max_deliver_delay_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QueueConfig {
+    /// How long received messages stay invisible to other workers when a receive does not set it
+    pub visibility_timeout_ms: u64,
+    /// Messages received this many times without an ack are moved to the dead-letter topic
+    pub max_deliveries: u64,
+    /// Upper bound of the messages a single receive asks for
+    pub max_messages: u64,
+    /// Amount of entries appended to `__queues` after its last snapshot before a new snapshot
+    /// is appended, the segments before a snapshot are removed
+    pub snapshot_min_entries: u64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    pub enabled: bool,
+    /// Token that is always accepted as an admin, used to bootstrap the first credentials
+    pub admin_token: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AclConfig {
+    pub enabled: bool,
+    /// Header used to identify the principal of a request when authentication is disabled
+    pub principal_header: Option<String>,
 }
 
 impl Default for Config {
@@ -25,8 +199,20 @@ impl Default for Config {
     fn default() -> Self {
         let mut result = Self {
             path: "data".to_string(),
+            log_dirs: Vec::new(),
+            http: HttpConfig::default(),
+            log: LogConfig::default(),
             topic: TopicConfig::default(),
             segment: SegmentConfig::default(),
+            retention: RetentionConfig::default(),
+            metadata: MetadataConfig::default(),
+            trash: TrashConfig::default(),
+            tiering: TieringConfig::default(),
+            disk: DiskConfig::default(),
+            limits: LimitsConfig::default(),
+            queue: QueueConfig::default(),
+            auth: AuthConfig::default(),
+            acl: AclConfig::default(),
             #[cfg(test)]
             tempdir: tempdir().expect("Failed to create tempdir"),
         };
@@ -45,6 +231,16 @@ impl Default for Config {
     }
 }
 
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: DEFAULT_PORT,
+            max_request_size: 2 * 1024 * 1024,
+        }
+    }
+}
+
 impl Default for SegmentConfig {
     fn default() -> Self {
         Self { size: 1024 * 512 } // 512MB
@@ -53,50 +249,572 @@ impl Default for SegmentConfig {
 
 impl Default for TopicConfig {
     fn default() -> Self {
-        Self { num_partitions: 10 }
+        Self {
+            num_partitions: 10,
+            retention_ms: None,
+            retention_bytes: None,
+            max_record_size: None,
+        }
+    }
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            check_interval_ms: 5 * 60 * 1000,
+        }
+    }
+}
+
+impl Default for MetadataConfig {
+    fn default() -> Self {
+        Self {
+            snapshot_interval_ms: 60 * 1000,
+            snapshot_min_entries: 1000,
+            rebuild: false,
+        }
+    }
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        Self {
+            retention_ms: Some(7 * 24 * 60 * 60 * 1000),
+            soft_delete: false,
+        }
+    }
+}
+
+impl Default for DiskConfig {
+    fn default() -> Self {
+        Self {
+            min_free_bytes: 64 * 1024 * 1024,
+            check_interval_ms: 1000,
+        }
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_record_size: 1024 * 1024,
+            max_headers: 64,
+            max_deliver_delay_ms: 24 * 60 * 60 * 1000,
+        }
+    }
+}
+
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("enabled", &self.enabled)
+            .field("admin_token", &self.admin_token.as_ref().map(|_| REDACTED))
+            .finish()
+    }
+}
+
+impl fmt::Debug for S3Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("S3Config")
+            .field("bucket", &self.bucket)
+            .field("prefix", &self.prefix)
+            .field("endpoint", &self.endpoint)
+            .field("region", &self.region)
+            .field("access_key_id", &self.access_key_id)
+            .field(
+                "secret_access_key",
+                &self.secret_access_key.as_ref().map(|_| REDACTED),
+            )
+            .field("allow_http", &self.allow_http)
+            .finish()
+    }
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            visibility_timeout_ms: 30 * 1000,
+            max_deliveries: 5,
+            max_messages: 1000,
+            snapshot_min_entries: 10_000,
+        }
     }
 }
 
 impl Config {
+    /// Loads the defaults, overridden by the config file at `path` and then by `PIGEON_*`
+    /// environment variables
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        let contents = match path {
+            Some(path) => {
+                fs::read_to_string(path).map_err(|e| Error::ReadFile(path.to_string(), e))?
+            }
+            None => String::new(),
+        };
+
+        Self::from_sources(&contents, env::vars())
+    }
+
+    fn from_sources(contents: &str, vars: impl Iterator<Item = (String, String)>) -> Result<Self> {
+        let mut table = toml::from_str::<Table>(contents)?;
+
+        let template = Table::try_from(Config::default()).expect("Failed to serialize config");
+        for (key, value) in vars {
+            if let Some(key) = key.strip_prefix(ENV_PREFIX) {
+                apply_env(&mut table, &template, &key.to_lowercase(), value)?;
+            }
+        }
+
+        Ok(Value::Table(table).try_into()?)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        fn invalid(key: &str, reason: &str) -> Result<()> {
+            Err(Error::InvalidValue(key.to_string(), reason.to_string()))
+        }
+
+        if self.path.is_empty() {
+            return invalid("path", "must not be empty");
+        }
+        if self.log_dirs.iter().any(|log_dir| log_dir.is_empty()) {
+            return invalid("log_dirs", "must not contain empty paths");
+        }
+        if self.http.host.is_empty() {
+            return invalid("http.host", "must not be empty");
+        }
+        if self.topic.num_partitions == 0 {
+            return invalid("topic.num_partitions", "must be greater than 0");
+        }
+        if self.topic.max_record_size == Some(0) {
+            return invalid("topic.max_record_size", "must be greater than 0");
+        }
+        if self.segment.size == 0 {
+            return invalid("segment.size", "must be greater than 0");
+        }
+        if self.retention.check_interval_ms == 0 {
+            return invalid("retention.check_interval_ms", "must be greater than 0");
+        }
+        if self.http.max_request_size == 0 {
+            return invalid("http.max_request_size", "must be greater than 0");
+        }
+        // Lengths are written with u32 prefixes and the header count with a u16 prefix
+        if self.limits.max_record_size == 0 || self.limits.max_record_size > u32::MAX as u64 {
+            return invalid(
+                "limits.max_record_size",
+                &format!("must be between 1 and {}", u32::MAX),
+            );
+        }
+        if self.limits.max_headers > u16::MAX as u64 {
+            return invalid(
+                "limits.max_headers",
+                &format!("must be at most {}", u16::MAX),
+            );
+        }
+        if self.limits.max_deliver_delay_ms == 0 {
+            return invalid("limits.max_deliver_delay_ms", "must be greater than 0");
+        }
+        if self.queue.visibility_timeout_ms == 0 {
+            return invalid("queue.visibility_timeout_ms", "must be greater than 0");
+        }
+        if self.queue.visibility_timeout_ms > MAX_QUEUE_DELAY_MS {
+            return invalid(
+                "queue.visibility_timeout_ms",
+                &format!("must be at most {MAX_QUEUE_DELAY_MS}"),
+            );
+        }
+        if self.queue.max_deliveries == 0 {
+            return invalid("queue.max_deliveries", "must be greater than 0");
+        }
+        if self.queue.max_messages == 0 {
+            return invalid("queue.max_messages", "must be greater than 0");
+        }
+        if self.queue.snapshot_min_entries == 0 {
+            return invalid("queue.snapshot_min_entries", "must be greater than 0");
+        }
+        if self.disk.check_interval_ms == 0 {
+            return invalid("disk.check_interval_ms", "must be greater than 0");
+        }
+        if self.metadata.snapshot_interval_ms == 0 {
+            return invalid("metadata.snapshot_interval_ms", "must be greater than 0");
+        }
+        if self.tiering.after_ms.is_some() && self.tiering.storage.is_none() {
+            return invalid(
+                "tiering.storage",
+                "is required when tiering.after_ms is set",
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The config as TOML, secrets are redacted
+    pub fn to_toml(&self) -> String {
+        let mut table = Table::try_from(self).expect("Failed to serialize config");
+
+        if let Some(Value::Table(auth)) = table.get_mut("auth")
+            && let Some(admin_token) = auth.get_mut("admin_token")
+        {
+            *admin_token = Value::String(REDACTED.to_string());
+        }
+
+        if let Some(Value::Table(tiering)) = table.get_mut("tiering")
+            && let Some(Value::Table(storage)) = tiering.get_mut("storage")
+            && let Some(secret_access_key) = storage.get_mut("secret_access_key")
+        {
+            *secret_access_key = Value::String(REDACTED.to_string());
+        }
+
+        toml::to_string_pretty(&table).expect("Failed to serialize config")
+    }
+
     pub fn base_path(&self) -> String {
         self.path.to_string()
     }
 
+    pub fn snapshots_path(&self) -> String {
+        format!("{}/snapshots", self.base_path())
+    }
+
+    pub fn snapshot_path(&self, offset: u64) -> String {
+        format!("{}/{:0>20}.json", self.snapshots_path(), offset)
+    }
+
+    pub fn quarantine_path(&self) -> String {
+        format!("{}/quarantine", self.base_path())
+    }
+
+    pub fn quarantine_entry_path(&self, offset: u64) -> String {
+        format!("{}/metadata-{:0>20}.bin", self.quarantine_path(), offset)
+    }
+
+    pub fn trash_path(&self) -> String {
+        format!("{}/trash", self.base_path())
+    }
+
+    pub fn trash_entry_path(&self, trash_id: &str) -> String {
+        format!("{}/{}", self.trash_path(), trash_id)
+    }
+
+    pub fn trash_state_path(&self, trash_id: &str) -> String {
+        format!("{}/trash.json", self.trash_entry_path(trash_id))
+    }
+
+    pub fn backups_path(&self) -> String {
+        format!("{}/backups", self.base_path())
+    }
+
+    pub fn backup_path(&self, backup_id: &str) -> String {
+        format!("{}/{}", self.backups_path(), backup_id)
+    }
+
+    pub fn backup_state_path(&self, backup_id: &str) -> String {
+        format!("{}/backup.json", self.backup_path(backup_id))
+    }
+
+    /// The directories partitions are placed in, `path` unless `log_dirs` is set
+    pub fn log_dirs(&self) -> Vec<LogDir> {
+        if self.log_dirs.is_empty() {
+            vec![self.base_log_dir()]
+        } else {
+            self.log_dirs.iter().cloned().map(LogDir::new).collect()
+        }
+    }
+
+    /// The configured log directories and `path`, which holds the metadata topic and partitions
+    /// created before `log_dirs` was set
+    pub fn all_log_dirs(&self) -> Vec<LogDir> {
+        let mut result = vec![self.base_log_dir()];
+        for log_dir in self.log_dirs() {
+            if !result.contains(&log_dir) {
+                result.push(log_dir);
+            }
+        }
+
+        result
+    }
+
+    pub fn base_log_dir(&self) -> LogDir {
+        LogDir::new(self.base_path())
+    }
+
     pub fn topics_path(&self) -> String {
-        format!("{}/topics", self.base_path())
+        self.base_log_dir().topics_path()
     }
 
     pub fn topic_path(&self, topic_id: u64) -> String {
-        format!("{}/{}", self.topics_path(), topic_id)
+        self.base_log_dir().topic_path(topic_id)
     }
+}
+
+/// Sets the config value named by an environment variable without its prefix, e.g. `segment_size`
+/// sets `size` in the `segment` table. Variables that do not name a table or top level value are
+/// ignored, so unrelated `PIGEON_*` variables can coexist
+fn apply_env(table: &mut Table, template: &Table, key: &str, value: String) -> Result<()> {
+    let env_key = || format!("{ENV_PREFIX}{}", key.to_uppercase());
 
-    pub fn partitions_path(&self, topic_id: u64) -> String {
-        format!("{}/partitions", self.topic_path(topic_id))
+    let Some(EnvKey { path, template }) = resolve_env_key(template, key) else {
+        return Ok(());
+    };
+
+    let value = parse_env_value(template, value)
+        .map_err(|reason| Error::InvalidValue(env_key(), reason))?;
+
+    let (field, tables) = path.split_last().expect("An env key has at least one name");
+    let mut section = table;
+    for name in tables {
+        let entry = section
+            .entry(name.clone())
+            .or_insert_with(|| Value::Table(Table::new()));
+        let Value::Table(entry) = entry else {
+            return Err(Error::InvalidValue(
+                name.clone(),
+                "expected a table".to_string(),
+            ));
+        };
+        section = entry;
     }
+    section.insert(field.clone(), value);
 
-    pub fn partition_path(&self, topic_id: u64, partition_id: u64) -> String {
-        format!("{}/{}", self.partitions_path(topic_id), partition_id)
+    Ok(())
+}
+
+/// The path of the config value an environment variable names, with its default value
+struct EnvKey<'a> {
+    path: Vec<String>,
+    template: Option<&'a Value>,
+}
+
+impl EnvKey<'_> {
+    /// Values with a default are preferred over values only known by the table they are in, then
+    /// the most specific tables, so `log_dirs` is not read as `dirs` in the `log` table
+    fn rank(&self) -> (bool, usize) {
+        let tables = &self.path[..self.path.len() - 1];
+
+        (
+            self.template.is_some(),
+            tables.iter().map(|name| name.len() + 1).sum(),
+        )
     }
+}
 
-    pub fn segment_path(&self, topic_id: u64, partition_id: u64, start_offset: u64) -> String {
-        format!(
-            "{}/{:0>10}",
-            self.partition_path(topic_id, partition_id),
-            start_offset
+/// Matches the key against every path of the template, values that are unset by default can
+/// still be named through the table they are in
+fn resolve_env_key<'a>(template: &'a Table, key: &str) -> Option<EnvKey<'a>> {
+    let mut best = match template.get(key) {
+        Some(Value::Table(_)) | None => None,
+        Some(value) => Some(EnvKey {
+            path: vec![key.to_string()],
+            template: Some(value),
+        }),
+    };
+
+    for (name, value) in template {
+        let Value::Table(table) = value else {
+            continue;
+        };
+        let Some(field) = key
+            .strip_prefix(name.as_str())
+            .and_then(|k| k.strip_prefix('_'))
+        else {
+            continue;
+        };
+
+        let mut candidate = resolve_env_key(table, field).unwrap_or(EnvKey {
+            path: vec![field.to_string()],
+            template: None,
+        });
+        candidate.path.insert(0, name.clone());
+
+        if best
+            .as_ref()
+            .is_none_or(|best| candidate.rank() > best.rank())
+        {
+            best = Some(candidate);
+        }
+    }
+
+    best
+}
+
+/// Parses the value as the type of the default value, optional values without a default are
+/// parsed as a TOML literal and fall back to a string
+fn parse_env_value(template: Option<&Value>, value: String) -> std::result::Result<Value, String> {
+    match template {
+        Some(Value::String(_)) => Ok(Value::String(value)),
+        Some(Value::Integer(_)) => value
+            .parse()
+            .map(Value::Integer)
+            .map_err(|_| format!("expected an integer, got {value}")),
+        Some(Value::Boolean(_)) => value
+            .parse()
+            .map(Value::Boolean)
+            .map_err(|_| format!("expected a boolean, got {value}")),
+        _ => Ok(toml::from_str::<Table>(&format!("value = {value}"))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or(Value::String(value))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use toml::Table;
+
+    use super::{Config, apply_env};
+    use crate::config::error::Error;
+
+    fn vars(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        vars.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn config_from_file_and_env() {
+        let contents = r#"
+            path = "/var/lib/pigeon"
+
+            [topic]
+            num_partitions = 3
+
+            [http]
+            port = 8000
+        "#;
+
+        let config = Config::from_sources(
+            contents,
+            vars(&[
+                ("PIGEON_HTTP_PORT", "9000"),
+                ("PIGEON_SEGMENT_SIZE", "2048"),
+                ("PIGEON_TOPIC_RETENTION_MS", "1000"),
+                ("PIGEON_AUTH_ENABLED", "true"),
+                ("PIGEON_AUTH_ADMIN_TOKEN", "secret"),
+                ("PIGEON_TOKEN", "unrelated"),
+                ("OTHER_PATH", "ignored"),
+            ]),
+        )
+        .expect("Failed to load config");
+
+        assert_eq!(config.path, "/var/lib/pigeon");
+        assert_eq!(config.http.host, "127.0.0.1");
+        assert_eq!(config.http.port, 9000);
+        assert_eq!(config.topic.num_partitions, 3);
+        assert_eq!(config.topic.retention_ms, Some(1000));
+        assert_eq!(config.segment.size, 2048);
+        assert!(config.auth.enabled);
+        assert_eq!(config.auth.admin_token.as_deref(), Some("secret"));
+
+        assert!(!config.to_toml().contains("secret"));
+        assert!(!format!("{config:?}").contains("secret"));
+    }
+
+    #[test]
+    fn config_log_dirs_from_env() {
+        let config = Config::from_sources(
+            "",
+            vars(&[
+                ("PIGEON_LOG_DIRS", r#"["/tmp/a","/tmp/b"]"#),
+                ("PIGEON_LOG_FILTER", "debug"),
+            ]),
+        )
+        .expect("Failed to load config");
+
+        assert_eq!(config.log_dirs, vec!["/tmp/a", "/tmp/b"]);
+        assert_eq!(config.log.filter.as_deref(), Some("debug"));
+    }
+
+    #[test]
+    fn env_keys_resolve_to_the_most_specific_path() {
+        let template = toml::from_str::<Table>(
+            "log_dirs = []\n[log]\nfilter = \"info\"\n[http]\nport = 1\n[http_tls]\nenabled = false",
         )
+        .unwrap();
+        let mut table = Table::new();
+        for (key, value) in [
+            ("log_dirs", "[\"a\"]"),
+            ("log_filter", "debug"),
+            ("http_tls_enabled", "true"),
+            ("http_host", "localhost"),
+        ] {
+            apply_env(&mut table, &template, key, value.to_string()).expect("Failed to apply env");
+        }
+
+        assert_eq!(
+            table,
+            toml::from_str::<Table>(
+                "log_dirs = [\"a\"]\n[log]\nfilter = \"debug\"\n[http]\nhost = \"localhost\"\n[http_tls]\nenabled = true",
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn config_rejects_invalid_values() {
+        let result = Config::from_sources("[topic]\nfoo = 1", vars(&[]));
+        assert!(matches!(result, Err(Error::Parse(_))));
+
+        let result = Config::from_sources("", vars(&[("PIGEON_SEGMENT_SIZE", "abc")]));
+        assert!(matches!(result, Err(Error::InvalidValue(..))));
+
+        let config = Config::from_sources("", vars(&[("PIGEON_TOPIC_NUM_PARTITIONS", "0")]))
+            .expect("Failed to load config");
+        let result = config.validate();
+        assert!(matches!(result, Err(Error::InvalidValue(..))));
+
+        let config = Config::from_sources("[queue]\nvisibility_timeout_ms = 86400001", vars(&[]))
+            .expect("Failed to load config");
+        let result = config.validate();
+        assert!(matches!(result, Err(Error::InvalidValue(..))));
     }
 
-    pub fn log_path(&self, topic_id: u64, partition_id: u64, start_offset: u64) -> String {
-        format!(
-            "{}.log",
-            self.segment_path(topic_id, partition_id, start_offset)
+    #[test]
+    fn config_changes_from() {
+        let current =
+            Config::from_sources("path = \"data\"", vars(&[])).expect("Failed to load config");
+        let mut config = Config::from_sources(
+            "path = \"data\"\n[http]\nport = 1234\n[topic]\nretention_ms = 1000\n[log]\nfilter = \"debug\"",
+            vars(&[]),
         )
+        .expect("Failed to load config");
+
+        let changes = config.changes_from(&current);
+        assert_eq!(changes.applied, vec!["log.filter", "topic.retention_ms"]);
+        assert_eq!(changes.restart_required, vec!["http.port"]);
+
+        assert_eq!(config.http.port, current.http.port);
+        assert_eq!(config.topic.retention_ms, Some(1000));
     }
 
-    pub fn index_path(&self, topic_id: u64, partition_id: u64, start_offset: u64) -> String {
-        format!(
-            "{}.index",
-            self.segment_path(topic_id, partition_id, start_offset)
+    #[test]
+    fn config_tiering_storage() {
+        let current = Config::from_sources(
+            "path = \"data\"\n[tiering.storage]\ntype = \"local\"\npath = \"archive\"",
+            vars(&[]),
+        )
+        .expect("Failed to load config");
+        let mut config = Config::from_sources(
+            "path = \"data\"\n[tiering]\nafter_ms = 1000\n[tiering.storage]\ntype = \"s3\"\nbucket = \"pigeon\"\nsecret_access_key = \"secret\"",
+            vars(&[]),
         )
+        .expect("Failed to load config");
+        assert!(!config.to_toml().contains("secret\""));
+        assert!(!format!("{config:?}").contains("secret\""));
+
+        let changes = config.changes_from(&current);
+        assert_eq!(changes.applied, vec!["tiering.after_ms"]);
+        assert_eq!(changes.restart_required, vec![
+            "tiering.storage.allow_http",
+            "tiering.storage.bucket",
+            "tiering.storage.path",
+            "tiering.storage.secret_access_key",
+            "tiering.storage.type"
+        ]);
+        assert_eq!(config.tiering.storage, current.tiering.storage);
+        let result = Config::from_sources(
+            "[tiering.storage]\ntype = \"s3\"\nbucket = \"pigeon\"\nsecret_key = \"secret\"",
+            vars(&[]),
+        );
+        assert!(matches!(result, Err(Error::Parse(_))));
     }
 }
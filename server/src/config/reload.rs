@@ -0,0 +1,76 @@
+use toml::{Table, Value};
+
+use super::Config;
+
+/// Settings that are only read during startup, changing them requires a restart
+const RESTART_REQUIRED: [&str; 6] = [
+    "path",
+    "log_dirs",
+    "http.host",
+    "http.port",
+    "segment.size",
+    "tiering.storage",
+];
+
+/// The settings that differ between two configs
+#[derive(Debug, Default)]
+pub struct ConfigChanges {
+    pub applied: Vec<String>,
+    pub restart_required: Vec<String>,
+}
+
+impl ConfigChanges {
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.restart_required.is_empty()
+    }
+}
+
+impl Config {
+    /// Prepares `self` to replace `current` in a running broker, settings that require a restart
+    /// keep their current value and are reported instead
+    pub fn changes_from(&mut self, current: &Config) -> ConfigChanges {
+        let mut changed = Vec::new();
+        diff(
+            "",
+            &Table::try_from(&*self).expect("Failed to serialize config"),
+            &Table::try_from(current).expect("Failed to serialize config"),
+            &mut changed,
+        );
+
+        self.path = current.path.clone();
+        self.log_dirs = current.log_dirs.clone();
+        self.http = current.http.clone();
+        self.segment = current.segment.clone();
+        self.tiering.storage = current.tiering.storage.clone();
+        self.tiering.backend = current.tiering.backend.clone();
+
+        let (restart_required, applied) = changed.into_iter().partition(|key| {
+            RESTART_REQUIRED
+                .iter()
+                .any(|setting| key == setting || key.starts_with(&format!("{setting}.")))
+        });
+
+        ConfigChanges {
+            applied,
+            restart_required,
+        }
+    }
+}
+
+fn diff(prefix: &str, new: &Table, current: &Table, changed: &mut Vec<String>) {
+    let mut keys = new.keys().chain(current.keys()).collect::<Vec<_>>();
+    keys.sort();
+    keys.dedup();
+
+    for key in keys {
+        let path = format!("{prefix}{key}");
+
+        match (new.get(key), current.get(key)) {
+            (Some(Value::Table(new)), Some(Value::Table(current))) => {
+                diff(&format!("{path}."), new, current, changed)
+            }
+            (new, current) if new != current => changed.push(path),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::io::ErrorKind;
+
+use shared::state::backup_state::BackupState;
+use tokio::fs::{self, create_dir_all, hard_link};
+use tracing::warn;
+
+use crate::config::Config;
+
+use super::error::Result;
+
+/// Data directory of a backup, it has the same layout as the data directory it was taken from
+pub fn target_config(config: &Config, backup_id: &str) -> Config {
+    Config {
+        path: config.backup_path(backup_id),
+        ..Default::default()
+    }
+}
+
+/// Hard links metadata snapshots into a backup, snapshots are never written to after creation
+pub async fn link_metadata_snapshots(
+    config: &Config,
+    target: &Config,
+    snapshot_offsets: &[u64],
+) -> Result<()> {
+    create_dir_all(target.snapshots_path()).await?;
+
+    for &offset in snapshot_offsets {
+        hard_link(config.snapshot_path(offset), target.snapshot_path(offset)).await?;
+    }
+
+    Ok(())
+}
+
+/// Writing the state completes a backup, directories without one are not listed
+pub async fn write_state(config: &Config, state: &BackupState) -> Result<()> {
+    fs::write(
+        config.backup_state_path(&state.backup_id),
+        serde_json::to_vec(state).expect("serde_json to_vec failed"),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Removes a backup that failed part way from every log directory
+pub async fn remove(config: &Config, backup_id: &str) -> Result<()> {
+    for log_dir in config.all_log_dirs() {
+        match fs::remove_dir_all(log_dir.backup_dir(backup_id).path()).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+async fn read(config: &Config, backup_id: &str) -> Result<Option<BackupState>> {
+    let bytes = match fs::read(config.backup_state_path(backup_id)).await {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    match serde_json::from_slice(&bytes) {
+        Ok(state) => Ok(Some(state)),
+        Err(e) => {
+            warn!("Ignoring unreadable backup {backup_id}: {e}");
+            Ok(None)
+        }
+    }
+}
+
+/// All completed backups, ordered from oldest to newest
+pub async fn list(config: &Config) -> Result<Vec<BackupState>> {
+    let mut states = Vec::new();
+
+    let mut stream = match fs::read_dir(config.backups_path()).await {
+        Ok(stream) => stream,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(states),
+        Err(e) => return Err(e.into()),
+    };
+
+    while let Some(entry) = stream.next_entry().await? {
+        let Some(backup_id) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+
+        if let Some(state) = read(config, &backup_id).await? {
+            states.push(state);
+        }
+    }
+
+    states.sort_by_key(|state| state.created_at.as_micros());
+    Ok(states)
+}
@@ -0,0 +1,68 @@
+use std::{
+    collections::HashMap,
+    io,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::error::Error;
+use crate::config::{Config, LogDir};
+
+/// Bytes available to the broker on the filesystem of `path`
+pub fn available_space(path: &str) -> io::Result<u64> {
+    let stat = rustix::fs::statvfs(path)?;
+
+    Ok(stat.f_bavail * stat.f_frsize)
+}
+
+/// Free space of each log directory as of the last refresh, produces check it without waiting on
+/// the filesystem
+#[derive(Debug)]
+pub struct DiskSpace {
+    free_bytes: HashMap<LogDir, AtomicU64>,
+}
+
+impl DiskSpace {
+    /// Log directories are only read at startup, so the set of directories never changes
+    pub fn new(config: &Config) -> Self {
+        Self {
+            free_bytes: config
+                .all_log_dirs()
+                .into_iter()
+                .map(|log_dir| (log_dir, AtomicU64::new(u64::MAX)))
+                .collect(),
+        }
+    }
+
+    /// Measures every log directory, returns the directories with their free space before and
+    /// after the refresh. Directories that are not created yet are measured once they exist
+    pub fn refresh(&self) -> io::Result<Vec<(LogDir, u64, u64)>> {
+        let mut changes = Vec::with_capacity(self.free_bytes.len());
+        for (log_dir, free_bytes) in &self.free_bytes {
+            let available = match available_space(log_dir.path()) {
+                Ok(available) => available,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            let previous = free_bytes.swap(available, Ordering::Relaxed);
+            changes.push((log_dir.clone(), previous, available));
+        }
+
+        Ok(changes)
+    }
+
+    pub fn free_bytes(&self, log_dir: &LogDir) -> Option<u64> {
+        self.free_bytes
+            .get(log_dir)
+            .map(|free_bytes| free_bytes.load(Ordering::Relaxed))
+    }
+
+    /// Stops produces to the directory right away when an append ran out of space, until the
+    /// next refresh shows otherwise
+    pub fn handle_append_error(&self, log_dir: &LogDir, error: &Error) {
+        if let Error::DiskFull(_) = error
+            && let Some(free_bytes) = self.free_bytes.get(log_dir)
+        {
+            free_bytes.store(0, Ordering::Relaxed);
+        }
+    }
+}
@@ -1,11 +1,13 @@
 use std::ffi::OsString;
+use std::io;
 
+use shared::response::error_response::ErrorCode;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Underlying IO error")]
-    UnderlyingIO(#[from] tokio::io::Error),
+    UnderlyingIO(#[source] io::Error),
     #[error("Segment is full and does not accept extra records")]
     SegmentFull,
     #[error("Partition ID does not exist")]
@@ -14,6 +16,41 @@ pub enum Error {
     InvalidLogFilename(OsString),
     #[error("Offset out of range of segment")]
     OffsetOutOfRange,
+    #[error("Corrupt record at position {0} of segment")]
+    CorruptRecord(u64),
+    #[error("Tiered storage error: {0}")]
+    TieredStorage(String),
+    #[error("Disk full: {0}")]
+    DiskFull(String),
+    #[error("Log starts at offset {0}, but entries from offset {1} are required")]
+    MissingLogEntries(u64, u64),
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        // Nothing can be written until space is freed, which is not a bug in the broker
+        if error.kind() == io::ErrorKind::StorageFull {
+            return Error::DiskFull(error.to_string());
+        }
+
+        Error::UnderlyingIO(error)
+    }
+}
+
+impl Error {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::UnderlyingIO(_) => ErrorCode::Io,
+            Error::SegmentFull => ErrorCode::SegmentFull,
+            Error::PartitionNotFound => ErrorCode::PartitionNotFound,
+            Error::InvalidLogFilename(_) => ErrorCode::InvalidLogFilename,
+            Error::OffsetOutOfRange => ErrorCode::OffsetOutOfRange,
+            Error::CorruptRecord(_) => ErrorCode::CorruptRecord,
+            Error::TieredStorage(_) => ErrorCode::TieredStorage,
+            Error::DiskFull(_) => ErrorCode::DiskFull,
+            Error::MissingLogEntries(..) => ErrorCode::MissingLogEntries,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
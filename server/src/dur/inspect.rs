@@ -0,0 +1,285 @@
+//! Reads segments straight from their files for offline inspection. Unlike
+//! [`super::topic::Topic::load_from_disk`] nothing is opened for writing or created
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use bytes::{Buf, Bytes};
+use tokio::fs;
+use tokio::io;
+
+use super::error::Result;
+use super::record::Record;
+use super::segment::decode_record;
+use super::segment::index::decode_entries;
+use super::storage::TieredLog;
+use crate::config::{Config, LogDir};
+
+pub struct SegmentDump {
+    pub start_offset: u64,
+    pub log_path: String,
+    pub index_path: String,
+    pub log_size: u64,
+    /// Records decoded in order from the log, together with their position in the file
+    pub records: Vec<(u64, Record)>,
+    /// Position of the first bytes of the log that do not decode as a record
+    pub corrupt_position: Option<u64>,
+    /// Index entries in the order they were written
+    pub index: Vec<(u64, u64)>,
+    /// Bytes at the end of the index that do not form a complete entry
+    pub index_trailing_bytes: usize,
+    /// Key of the log in tiered storage, its records are not read
+    pub tiered_key: Option<String>,
+}
+
+/// The log directory that has the partition on disk, the recorded one is checked first. A
+/// partition is found elsewhere after a move that was interrupted before it was recorded, or when
+/// it was created before `log_dirs` was set
+pub async fn find_log_dir(
+    config: &Config,
+    topic_id: u64,
+    partition_id: u64,
+    recorded: Option<&str>,
+) -> io::Result<Option<LogDir>> {
+    let mut log_dirs = config.all_log_dirs();
+    if let Some(recorded) = recorded
+        && let Some(index) = log_dirs
+            .iter()
+            .position(|log_dir| log_dir.path() == recorded)
+    {
+        log_dirs.swap(0, index);
+    }
+
+    for log_dir in log_dirs {
+        if fs::try_exists(log_dir.partition_path(topic_id, partition_id)).await? {
+            return Ok(Some(log_dir));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Start offsets of the segments of a partition, a segment is listed if either file exists
+pub async fn list_segments(log_dir: &LogDir, topic_id: u64, partition_id: u64) -> Result<Vec<u64>> {
+    let mut start_offsets = BTreeSet::new();
+
+    let mut stream = match fs::read_dir(log_dir.partition_path(topic_id, partition_id)).await {
+        Ok(stream) => stream,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    while let Some(entry) = stream.next_entry().await? {
+        let path = entry.path();
+        if path.extension().is_none_or(|extension| {
+            extension != "log" && extension != "index" && extension != "tiered"
+        }) {
+            continue;
+        }
+
+        if let Some(start_offset) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse().ok())
+        {
+            start_offsets.insert(start_offset);
+        }
+    }
+
+    Ok(start_offsets.into_iter().collect())
+}
+
+pub async fn read_segment(
+    log_dir: &LogDir,
+    topic_id: u64,
+    partition_id: u64,
+    start_offset: u64,
+) -> Result<SegmentDump> {
+    let log_path = log_dir.log_path(topic_id, partition_id, start_offset);
+    let index_path = log_dir.index_path(topic_id, partition_id, start_offset);
+    let (index, index_trailing_bytes) = decode_entries(&read_if_exists(&index_path).await?);
+
+    if let Some(tiered) =
+        TieredLog::read(&log_dir.tiered_path(topic_id, partition_id, start_offset)).await?
+    {
+        return Ok(SegmentDump {
+            start_offset,
+            log_path,
+            index_path,
+            log_size: tiered.log_size,
+            records: Vec::new(),
+            corrupt_position: None,
+            index,
+            index_trailing_bytes,
+            tiered_key: Some(tiered.key),
+        });
+    }
+
+    let log = read_if_exists(&log_path).await?;
+    let log_size = log.len() as u64;
+
+    let mut bytes = Bytes::from(log);
+    let mut records = Vec::new();
+    let mut corrupt_position = None;
+    while bytes.has_remaining() {
+        let position = log_size - bytes.remaining() as u64;
+
+        match decode_record(&mut bytes) {
+            Some(record) => records.push((position, record)),
+            None => {
+                corrupt_position = Some(position);
+                break;
+            }
+        }
+    }
+
+    Ok(SegmentDump {
+        start_offset,
+        log_path,
+        index_path,
+        log_size,
+        records,
+        corrupt_position,
+        index,
+        index_trailing_bytes,
+        tiered_key: None,
+    })
+}
+
+async fn read_if_exists(path: impl AsRef<Path>) -> Result<Vec<u8>> {
+    match fs::read(path).await {
+        Ok(bytes) => Ok(bytes),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl SegmentDump {
+    /// Checks the records of the log against each other and against the index
+    pub fn verify(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if let Some(position) = self.corrupt_position {
+            problems.push(format!(
+                "log has {} bytes at position {position} that do not decode as a record, the tail is truncated or corrupt",
+                self.log_size - position
+            ));
+        }
+
+        if self.index_trailing_bytes > 0 {
+            problems.push(format!(
+                "index ends with {} bytes of an incomplete entry",
+                self.index_trailing_bytes
+            ));
+        }
+
+        // The records of an offloaded log are not available to check the index against
+        if self.tiered_key.is_some() {
+            return problems;
+        }
+
+        let mut previous: Option<u64> = None;
+        for (position, record) in &self.records {
+            if record.offset < self.start_offset {
+                problems.push(format!(
+                    "record at position {position} has offset {} before the start offset",
+                    record.offset
+                ));
+            }
+            if let Some(previous) = previous
+                && record.offset <= previous
+            {
+                problems.push(format!(
+                    "record at position {position} has offset {} after offset {previous}",
+                    record.offset
+                ));
+            }
+            previous = Some(record.offset);
+        }
+
+        let offsets_by_position = self
+            .records
+            .iter()
+            .map(|(position, record)| (*position, record.offset))
+            .collect::<HashMap<_, _>>();
+        for (offset, position) in &self.index {
+            match offsets_by_position.get(position) {
+                Some(record_offset) if record_offset == offset => {}
+                Some(record_offset) => problems.push(format!(
+                    "index entry for offset {offset} points to position {position} of offset {record_offset}"
+                )),
+                None => problems.push(format!(
+                    "index entry for offset {offset} points to position {position} where no record starts"
+                )),
+            }
+        }
+
+        let indexed = self
+            .index
+            .iter()
+            .map(|(offset, _)| *offset)
+            .collect::<BTreeSet<_>>();
+        for (position, record) in &self.records {
+            if !indexed.contains(&record.offset) {
+                problems.push(format!(
+                    "record at position {position} with offset {} is missing from the index",
+                    record.offset
+                ));
+            }
+        }
+
+        problems
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{OpenOptions, create_dir_all};
+    use std::io::Write;
+
+    use super::{list_segments, read_segment};
+    use crate::{
+        config::Config,
+        dur::{record::Record, segment::Segment},
+    };
+
+    #[tokio::test]
+    async fn inspect_segment_files() {
+        let config = Config::default();
+        let log_dir = config.base_log_dir();
+        create_dir_all(log_dir.partition_path(0, 0)).unwrap();
+
+        let mut segment = Segment::load_from_disk(&config, &log_dir, 0, 0, 0)
+            .await
+            .expect("Failed to load segment");
+        for offset in 0..3 {
+            segment
+                .append(&Record::basic_with_offset(offset, "Hello", "World"))
+                .await
+                .expect("Failed to append record");
+        }
+        drop(segment);
+
+        assert_eq!(list_segments(&log_dir, 0, 0).await.unwrap(), vec![0]);
+
+        let dump = read_segment(&log_dir, 0, 0, 0)
+            .await
+            .expect("Failed to read segment");
+        assert_eq!(dump.records.len(), 3);
+        assert_eq!(dump.index.len(), 3);
+        assert!(dump.verify().is_empty(), "{:?}", dump.verify());
+
+        // An append interrupted after part of the record was written
+        let mut log = OpenOptions::new()
+            .append(true)
+            .open(log_dir.log_path(0, 0, 0))
+            .unwrap();
+        log.write_all(&[0, 0, 0, 0, 0, 0, 0, 3, 0]).unwrap();
+
+        let dump = read_segment(&log_dir, 0, 0, 0)
+            .await
+            .expect("Failed to read segment");
+        assert_eq!(dump.records.len(), 3);
+        assert_eq!(dump.corrupt_position, Some(dump.log_size - 9));
+        assert_eq!(dump.verify().len(), 1);
+    }
+}
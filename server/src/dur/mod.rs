@@ -1,8 +1,13 @@
+pub mod backup;
+pub mod disk;
 pub mod error;
+pub mod inspect;
 mod partition;
 pub mod record;
 mod segment;
+pub mod storage;
 pub mod topic;
+pub mod trash;
 
 #[cfg(test)]
 mod tests;
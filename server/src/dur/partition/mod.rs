@@ -1,30 +1,44 @@
-use std::{collections::BTreeMap, ops::Bound, path::Path, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashSet},
+    ops::Bound,
+    path::Path,
+    sync::Arc,
+};
 
 use bytes::Bytes;
 use shared::{
     data::{offset_selection::OffsetSelection, timestamp::Timestamp},
-    state::partition_state::PartitionState,
+    state::{partition_state::PartitionState, segment_state::SegmentState},
 };
-use tokio::fs::{self, create_dir_all, remove_dir};
+use tokio::fs::{self, create_dir_all, remove_dir, remove_dir_all, remove_file};
+use tracing::debug;
 
 use super::{
     error::Result,
-    record::{Record, RecordHeader},
-    segment::Segment,
+    record::{Delivery, Record, RecordHeader},
+    segment::{Segment, SegmentFiles},
+    storage::{OffloadCandidate, Storage},
+};
+use crate::{
+    config::{Config, LogDir},
+    dur::error::Error,
+    record_batch::RecordBatch,
 };
-use crate::{config::Config, dur::error::Error, record_batch::RecordBatch};
 
 pub struct Partition {
     topic_id: u64,
     partition_id: u64,
     config: Arc<Config>,
+    log_dir: LogDir,
 
     next_offset: u64,
+    segment_size: u64,
     pub(super) segments: BTreeMap<u64, Segment>,
 }
 
 async fn load_segments_form_disk(
     config: &Config,
+    log_dir: &LogDir,
     topic_id: u64,
     partition_id: u64,
     dir: &str,
@@ -33,7 +47,12 @@ async fn load_segments_form_disk(
 
     let mut stream = fs::read_dir(dir).await?;
     while let Some(entry) = stream.next_entry().await? {
-        if entry.path().extension().is_none_or(|s| s != "log") {
+        // Offloaded segments have a marker instead of their log
+        if entry
+            .path()
+            .extension()
+            .is_none_or(|s| s != "log" && s != "tiered")
+        {
             continue;
         }
 
@@ -46,7 +65,12 @@ async fn load_segments_form_disk(
             .parse::<u64>()
             .map_err(|_| Error::InvalidLogFilename(entry.file_name()))?;
 
-        let segment = Segment::load_from_disk(config, topic_id, partition_id, start_offset).await?;
+        if btree.contains_key(&start_offset) {
+            continue;
+        }
+
+        let segment =
+            Segment::load_from_disk(config, log_dir, topic_id, partition_id, start_offset).await?;
 
         btree.insert(start_offset, segment);
     }
@@ -54,7 +78,7 @@ async fn load_segments_form_disk(
     if btree.is_empty() {
         btree.insert(
             0,
-            Segment::load_from_disk(config, topic_id, partition_id, 0).await?,
+            Segment::load_from_disk(config, log_dir, topic_id, partition_id, 0).await?,
         );
     }
 
@@ -64,17 +88,23 @@ async fn load_segments_form_disk(
 impl Partition {
     pub async fn load_from_disk(
         config: Arc<Config>,
+        log_dir: LogDir,
         topic_id: u64,
         partition_id: u64,
     ) -> Result<Self> {
-        let partition_path = config.partition_path(topic_id, partition_id);
+        let partition_path = log_dir.partition_path(topic_id, partition_id);
 
         create_dir_all(Path::new(&partition_path)).await?;
 
         let segments =
-            load_segments_form_disk(&config, topic_id, partition_id, &partition_path).await?;
-
-        let mut next_offset = 0;
+            load_segments_form_disk(&config, &log_dir, topic_id, partition_id, &partition_path)
+                .await?;
+
+        // An empty active segment still marks the next offset through its start offset
+        let mut next_offset = segments
+            .last_key_value()
+            .map(|(start_offset, _)| *start_offset)
+            .unwrap_or(0);
         let mut cursos = segments.upper_bound(Bound::Unbounded);
         while let Some(segment) = cursos.prev() {
             if let Some(offset) = segment.1.max_offset() {
@@ -86,13 +116,19 @@ impl Partition {
         Ok(Self {
             partition_id,
             topic_id,
+            segment_size: config.segment.size,
             config,
+            log_dir,
 
             next_offset,
             segments,
         })
     }
 
+    pub fn set_broker_config(&mut self, config: Arc<Config>) {
+        self.config = config;
+    }
+
     pub fn min_offset(&self) -> Option<u64> {
         self.segments.iter().find_map(|e| e.1.min_offset())
     }
@@ -116,6 +152,8 @@ impl Partition {
         batch: &mut RecordBatch,
         offset: &OffsetSelection,
     ) -> Result<()> {
+        let now = Timestamp::now();
+
         // Not great iterator
         for segment in self.segments.values() {
             let mut range = segment.index().range(offset.range()).map(|e| e.0);
@@ -125,10 +163,20 @@ impl Partition {
             // TODO: determine based on max offset
             let end_offset = range.next_back().unwrap_or(start_offset);
 
-            let records = segment.read_range(*start_offset, *end_offset).await?;
+            let mut records = Vec::new();
+            for record in segment.read_range(*start_offset, *end_offset).await? {
+                match record.delivery(now) {
+                    Delivery::Deliverable => records.push(record),
+                    Delivery::Expired => {}
+                    Delivery::NotBefore(deliver_at) => {
+                        batch.hold_back(self.topic_id, self.partition_id, deliver_at);
+                        break;
+                    }
+                }
+            }
             batch.append(self.topic_id, self.partition_id, records);
 
-            if batch.is_full() {
+            if batch.is_full() || batch.is_held_back(self.topic_id, self.partition_id) {
                 break;
             }
         }
@@ -136,49 +184,338 @@ impl Partition {
         Ok(())
     }
 
+    /// Sets the size after which segments are rolled, applies to the active segment as well
+    pub fn set_segment_size(&mut self, segment_size: u64) {
+        self.segment_size = segment_size;
+
+        for segment in self.segments.values_mut() {
+            segment.set_max_log_size(segment_size);
+        }
+    }
+
+    /// Removes the oldest segments that fall outside of the retention limits, returns the amount
+    /// of removed segments. The active segment is never removed
+    pub async fn enforce_retention(
+        &mut self,
+        retention_ms: Option<u64>,
+        retention_bytes: Option<u64>,
+    ) -> Result<usize> {
+        let now = Timestamp::now().as_micros();
+        let mut removed = 0;
+
+        while self.segments.len() > 1 {
+            let total_size: u64 = self.segments.values().map(Segment::log_size).sum();
+            let (_, oldest) = self
+                .segments
+                .first_key_value()
+                .expect("A partition should always have at least 1 segment");
+
+            // Only remove a segment if the partition stays above the limit without it
+            let over_size =
+                retention_bytes.is_some_and(|max| total_size - oldest.log_size() >= max);
+
+            let expired = match retention_ms {
+                Some(retention_ms) => oldest.max_timestamp().await?.is_some_and(|timestamp| {
+                    timestamp
+                        .as_micros()
+                        .saturating_add(retention_ms.saturating_mul(1000))
+                        < now
+                }),
+                None => false,
+            };
+
+            if !over_size && !expired {
+                break;
+            }
+
+            let (_, segment) = self.segments.pop_first().unwrap();
+            debug!("Retention removing {segment}");
+            segment.delete().await?;
+            removed += 1;
+        }
+
+        Ok(removed)
+    }
+
+    /// Reads all records starting at `offset`, one read per segment
+    pub async fn read_from(&self, offset: u64) -> Result<Vec<Record>> {
+        let mut records = Vec::new();
+
+        for segment in self.segments.values() {
+            let mut range = segment.index().range(offset..).map(|e| e.0);
+            let Some(start_offset) = range.next() else {
+                continue;
+            };
+            let end_offset = range.next_back().unwrap_or(start_offset);
+
+            records.append(&mut segment.read_range(*start_offset, *end_offset).await?);
+        }
+
+        Ok(records)
+    }
+
+    /// Reads the records from `offset` to the end of the first segment that has any, so a
+    /// partition can be walked one segment at a time
+    pub async fn read_segment_from(&self, offset: u64) -> Result<Vec<Record>> {
+        for segment in self.segments.values() {
+            let mut range = segment.index().range(offset..).map(|e| e.0);
+            let Some(start_offset) = range.next() else {
+                continue;
+            };
+            let end_offset = range.next_back().unwrap_or(start_offset);
+
+            return segment.read_range(*start_offset, *end_offset).await;
+        }
+
+        Ok(Vec::new())
+    }
+
+    /// Removes closed segments that only contain records before `offset`, returns the amount of
+    /// removed segments. The active segment is never removed
+    pub async fn delete_segments_before(&mut self, offset: u64) -> Result<usize> {
+        let mut removed = 0;
+
+        while self.segments.len() > 1 {
+            let (_, oldest) = self
+                .segments
+                .first_key_value()
+                .expect("A partition should always have at least 1 segment");
+
+            if oldest
+                .max_offset()
+                .is_some_and(|max_offset| max_offset >= offset)
+            {
+                break;
+            }
+
+            let (_, segment) = self.segments.pop_first().unwrap();
+            debug!("Removing {segment} before offset {offset}");
+            segment.delete().await?;
+            removed += 1;
+        }
+
+        Ok(removed)
+    }
+
     pub async fn delete(self) -> Result<()> {
         for (_, segment) in self.segments.into_iter() {
             segment.delete().await?;
         }
 
-        remove_dir(self.config.partition_path(self.topic_id, self.partition_id)).await?;
+        remove_dir(
+            self.log_dir
+                .partition_path(self.topic_id, self.partition_id),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn sync(&self) -> Result<()> {
+        for segment in self.segments.values() {
+            segment.sync().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the records up to the log-end offset into the backup `backup_id` of its log
+    /// directory, closed segments are hard linked and only the active segment is copied. Returns
+    /// the log-end offset
+    pub async fn backup(&self, backup_id: &str) -> Result<u64> {
+        let target = &self.log_dir.backup_dir(backup_id);
+        create_dir_all(target.partition_path(self.topic_id, self.partition_id)).await?;
+
+        let (_, active) = self
+            .segments
+            .last_key_value()
+            .expect("A partition should always have at least 1 segment");
+        for segment in self.segments.values().take(self.segments.len() - 1) {
+            segment.link_to(target).await?;
+        }
+        active.copy_to(target).await?;
+
+        Ok(self.next_offset)
+    }
+
+    /// Closed segments on the local disk whose newest record is before `before`
+    pub async fn offload_candidates(&self, before: Timestamp) -> Result<Vec<OffloadCandidate>> {
+        let mut candidates = Vec::new();
+
+        for segment in self.segments.values().take(self.segments.len() - 1) {
+            if let Some(candidate) = segment.offload_candidate(before).await? {
+                candidates.push(candidate);
+            }
+        }
+
+        Ok(candidates)
+    }
+
+    /// Returns false when the segment was removed or offloaded while its log was uploaded
+    pub async fn complete_offload(
+        &mut self,
+        start_offset: u64,
+        storage: Arc<dyn Storage>,
+        key: String,
+    ) -> Result<bool> {
+        let Some(segment) = self.segments.get_mut(&start_offset) else {
+            return Ok(false);
+        };
+        if segment.is_tiered() {
+            return Ok(false);
+        }
+
+        debug!("Offloading {segment} as {key}");
+        segment.complete_offload(storage, key).await?;
+
+        Ok(true)
+    }
+
+    pub fn log_dir(&self) -> &LogDir {
+        &self.log_dir
+    }
+
+    /// The files of the closed segments on the local disk, copied by [`Self::relocate`] before
+    /// the partition is locked
+    pub fn closed_segment_files(&self) -> Vec<SegmentFiles> {
+        self.segments
+            .values()
+            .take(self.segments.len() - 1)
+            .filter_map(Segment::files)
+            .collect()
+    }
+
+    /// Moves the partition to `target`. The segments in `copied` were copied into `staging`
+    /// while they were closed, everything else is copied now. The copy is renamed into place and
+    /// the old directory is removed once the partition reads from `target`
+    pub async fn relocate(
+        &mut self,
+        target: LogDir,
+        staging: &LogDir,
+        copied: &HashSet<u64>,
+    ) -> Result<()> {
+        let staged_path = staging.partition_path(self.topic_id, self.partition_id);
+        create_dir_all(&staged_path).await?;
+        self.copy_remaining(staging, copied).await?;
+
+        // Left over from a move that was interrupted after the rename, the partition was still
+        // read from its old directory
+        let target_path = target.partition_path(self.topic_id, self.partition_id);
+        if fs::try_exists(&target_path).await? {
+            remove_dir_all(&target_path).await?;
+        }
+        create_dir_all(target.partitions_path(self.topic_id)).await?;
+        fs::rename(&staged_path, &target_path).await?;
+
+        let mut relocated = Partition::load_from_disk(
+            self.config.clone(),
+            target,
+            self.topic_id,
+            self.partition_id,
+        )
+        .await?;
+        relocated.set_segment_size(self.segment_size);
+
+        let source = std::mem::replace(self, relocated);
+        let source_dir = source.log_dir.clone();
+        drop(source);
+
+        remove_dir_all(source_dir.partition_path(self.topic_id, self.partition_id)).await?;
+        // The topic may still have other partitions in the old directory
+        remove_dir(source_dir.partitions_path(self.topic_id))
+            .await
+            .ok();
+        remove_dir(source_dir.topic_path(self.topic_id)).await.ok();
+
+        Ok(())
+    }
+
+    async fn copy_remaining(&self, target: &LogDir, copied: &HashSet<u64>) -> Result<()> {
+        // Segments removed by retention after they were copied
+        for start_offset in copied {
+            if !self.segments.contains_key(start_offset) {
+                for path in [
+                    target.log_path(self.topic_id, self.partition_id, *start_offset),
+                    target.index_path(self.topic_id, self.partition_id, *start_offset),
+                ] {
+                    remove_file(path).await?;
+                }
+            }
+        }
+
+        let active_offset = *self
+            .segments
+            .last_key_value()
+            .expect("A partition should always have at least 1 segment")
+            .0;
+        for (start_offset, segment) in &self.segments {
+            if segment.is_tiered()
+                || *start_offset == active_offset
+                || !copied.contains(start_offset)
+            {
+                segment.relocate_to(target).await?;
+            }
+        }
 
         Ok(())
     }
 
+    pub fn disk_usage(&self) -> u64 {
+        self.segments.values().map(Segment::disk_usage).sum()
+    }
+
     pub fn state(&self) -> PartitionState {
         PartitionState {
             partition_id: self.partition_id,
             current_offset: self.next_offset,
             segment_count: self.segments.len(),
+            log_dir: self.log_dir.to_string(),
         }
     }
 
+    pub fn segment_states(&self) -> Vec<SegmentState> {
+        self.segments.values().map(Segment::state).collect()
+    }
+
+    /// Starts a new active segment at the next offset, returns false when the active segment is
+    /// still empty and there is nothing to roll
+    pub async fn roll_segment(&mut self) -> Result<bool> {
+        let (_, active) = self
+            .segments
+            .last_key_value()
+            .expect("A partition should always have at least 1 segment");
+        if active.max_offset().is_none() {
+            return Ok(false);
+        }
+
+        self.push_segment().await?;
+
+        Ok(true)
+    }
+
+    async fn push_segment(&mut self) -> Result<()> {
+        let mut segment = Segment::load_from_disk(
+            &self.config,
+            &self.log_dir,
+            self.topic_id,
+            self.partition_id,
+            self.next_offset,
+        )
+        .await?;
+        segment.set_max_log_size(self.segment_size);
+
+        debug!("Rolled {segment}");
+        self.segments.insert(self.next_offset, segment);
+
+        Ok(())
+    }
+
     pub async fn append(
         &mut self,
         key: Bytes,
         value: Bytes,
         headers: Vec<RecordHeader>,
     ) -> Result<Record> {
-        if self
-            .segments
-            .last_entry()
-            .expect("A partition should always have at least 1 segment")
-            .get()
-            .is_full()
-        {
-            self.segments.insert(
-                self.next_offset,
-                Segment::load_from_disk(
-                    &self.config,
-                    self.topic_id,
-                    self.partition_id,
-                    self.next_offset,
-                )
-                .await?,
-            );
-        }
-
         let record = Record {
             timestamp: Timestamp::now(),
             key,
@@ -187,7 +524,25 @@ impl Partition {
             offset: self.next_offset,
         };
 
-        self.next_offset += 1;
+        self.append_record(record).await
+    }
+
+    /// Appends a record keeping its offset and timestamp, offsets may skip ahead but never go
+    /// back
+    pub async fn append_record(&mut self, record: Record) -> Result<Record> {
+        if record.offset < self.next_offset {
+            return Err(Error::OffsetOutOfRange);
+        }
+
+        if self
+            .segments
+            .last_entry()
+            .expect("A partition should always have at least 1 segment")
+            .get()
+            .is_full()
+        {
+            self.push_segment().await?;
+        }
 
         self.segments
             .last_entry()
@@ -196,6 +551,8 @@ impl Partition {
             .append(&record)
             .await?;
 
+        self.next_offset = record.offset + 1;
+
         Ok(record)
     }
 }
@@ -211,7 +568,7 @@ mod test {
     async fn partition_basic_read_write() {
         let config = Arc::new(Config::default());
 
-        let mut partition = Partition::load_from_disk(config, 0, 0)
+        let mut partition = Partition::load_from_disk(config.clone(), config.base_log_dir(), 0, 0)
             .await
             .expect("Failed to load partition");
 
@@ -241,7 +598,7 @@ mod test {
     async fn partition_ocntinue_on_existing() {
         let config = Arc::new(Config::default());
 
-        let mut partition = Partition::load_from_disk(config.clone(), 0, 0)
+        let mut partition = Partition::load_from_disk(config.clone(), config.base_log_dir(), 0, 0)
             .await
             .expect("Failed to load partition");
 
@@ -253,7 +610,7 @@ mod test {
 
         drop(partition);
 
-        let mut partition = Partition::load_from_disk(config, 0, 0)
+        let mut partition = Partition::load_from_disk(config.clone(), config.base_log_dir(), 0, 0)
             .await
             .expect("Failed to load partition");
 
@@ -279,7 +636,7 @@ mod test {
         config.segment.size = 1;
         let config = Arc::new(config);
 
-        let mut partition = Partition::load_from_disk(config.clone(), 0, 0)
+        let mut partition = Partition::load_from_disk(config.clone(), config.base_log_dir(), 0, 0)
             .await
             .expect("Failed to load partition");
 
@@ -306,4 +663,120 @@ mod test {
         assert_eq!(read_record.value, "bar");
         assert_eq!(read_record.offset, 0);
     }
+
+    #[tokio::test]
+    async fn partition_read_from() {
+        let mut config = Config::default();
+        config.segment.size = 1;
+        let config = Arc::new(config);
+
+        let mut partition = Partition::load_from_disk(config.clone(), config.base_log_dir(), 0, 0)
+            .await
+            .expect("Failed to load partition");
+
+        for i in 0..4 {
+            partition
+                .append("foo".into(), format!("bar{i}").into(), vec![])
+                .await
+                .expect("Failed to append record");
+        }
+
+        let records = partition.read_from(1).await.expect("Failed to read_from");
+        assert_eq!(records.iter().map(|r| r.offset).collect::<Vec<_>>(), vec![
+            1, 2, 3
+        ]);
+        assert_eq!(records[0].value, "bar1");
+    }
+
+    #[tokio::test]
+    async fn partition_delete_segments_before() {
+        let mut config = Config::default();
+        config.segment.size = 1;
+        let config = Arc::new(config);
+
+        let mut partition = Partition::load_from_disk(config.clone(), config.base_log_dir(), 0, 0)
+            .await
+            .expect("Failed to load partition");
+
+        for _ in 0..4 {
+            partition
+                .append("foo".into(), "bar".into(), vec![])
+                .await
+                .expect("Failed to append record");
+        }
+
+        let removed = partition
+            .delete_segments_before(2)
+            .await
+            .expect("Failed to delete segments");
+        assert_eq!(removed, 2);
+        assert_eq!(partition.min_offset(), Some(2));
+        drop(partition);
+
+        let partition = Partition::load_from_disk(config.clone(), config.base_log_dir(), 0, 0)
+            .await
+            .expect("Failed to load partition");
+        assert_eq!(partition.state().current_offset, 4);
+    }
+
+    #[tokio::test]
+    async fn partition_retention_bytes() {
+        let mut config = Config::default();
+        config.segment.size = 1;
+        let config = Arc::new(config);
+
+        let mut partition = Partition::load_from_disk(config.clone(), config.base_log_dir(), 0, 0)
+            .await
+            .expect("Failed to load partition");
+
+        for _ in 0..4 {
+            partition
+                .append("foo".into(), "bar".into(), vec![])
+                .await
+                .expect("Failed to append record");
+        }
+        assert_eq!(partition.segments.len(), 4);
+
+        let removed = partition
+            .enforce_retention(None, Some(1))
+            .await
+            .expect("Failed to enforce retention");
+
+        // The active segment is always kept
+        assert_eq!(removed, 3);
+        assert_eq!(partition.min_offset(), Some(3));
+        assert!(matches!(partition.read_exact(0).await, Ok(None)));
+    }
+
+    #[tokio::test]
+    async fn partition_retention_ms() {
+        let mut config = Config::default();
+        config.segment.size = 1;
+        let config = Arc::new(config);
+
+        let mut partition = Partition::load_from_disk(config.clone(), config.base_log_dir(), 0, 0)
+            .await
+            .expect("Failed to load partition");
+
+        for _ in 0..2 {
+            partition
+                .append("foo".into(), "bar".into(), vec![])
+                .await
+                .expect("Failed to append record");
+        }
+
+        let removed = partition
+            .enforce_retention(Some(60 * 1000), None)
+            .await
+            .expect("Failed to enforce retention");
+        assert_eq!(removed, 0);
+
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let removed = partition
+            .enforce_retention(Some(1), None)
+            .await
+            .expect("Failed to enforce retention");
+        assert_eq!(removed, 1);
+        assert_eq!(partition.min_offset(), Some(1));
+    }
 }
@@ -1,5 +1,6 @@
 use bytes::Bytes;
 use shared::{
+    consts::{DELIVER_AT_HEADER, EXPIRES_AT_HEADER},
     data::{
         encoding::{self, Encoding},
         timestamp::Timestamp,
@@ -23,10 +24,27 @@ pub struct Record {
     // pub crc: u32,
 }
 
+/// Whether a fetch returns a record, see [`Record::delivery`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Delivery {
+    Deliverable,
+    Expired,
+    /// Held back until the timestamp
+    NotBefore(Timestamp),
+}
+
 impl RecordHeader {
     pub fn size(&self) -> usize {
         self.key.len() + self.value.len()
     }
+
+    /// A reserved header holding a timestamp in microseconds
+    pub fn timestamp(key: &str, timestamp: Timestamp) -> Self {
+        Self {
+            key: key.to_string(),
+            value: timestamp.as_micros().to_string().into(),
+        }
+    }
 }
 
 impl Record {
@@ -36,6 +54,39 @@ impl Record {
             + self.headers.iter().map(RecordHeader::size).sum::<usize>()
     }
 
+    /// Value of a reserved timestamp header, headers that do not hold a timestamp are ignored
+    fn timestamp_header(&self, key: &str) -> Option<Timestamp> {
+        self.headers
+            .iter()
+            .find(|header| header.key == key)
+            .and_then(|header| str::from_utf8(&header.value).ok()?.parse::<u64>().ok())
+            .map(Timestamp::from)
+    }
+
+    pub fn expires_at(&self) -> Option<Timestamp> {
+        self.timestamp_header(EXPIRES_AT_HEADER)
+    }
+
+    pub fn deliver_at(&self) -> Option<Timestamp> {
+        self.timestamp_header(DELIVER_AT_HEADER)
+    }
+
+    pub fn delivery(&self, now: Timestamp) -> Delivery {
+        if self
+            .expires_at()
+            .is_some_and(|expires_at| expires_at.as_micros() <= now.as_micros())
+        {
+            return Delivery::Expired;
+        }
+
+        match self.deliver_at() {
+            Some(deliver_at) if deliver_at.as_micros() > now.as_micros() => {
+                Delivery::NotBefore(deliver_at)
+            }
+            _ => Delivery::Deliverable,
+        }
+    }
+
     pub fn to_response(
         &self,
         encoding: &Encoding,
@@ -63,6 +114,31 @@ impl Record {
     }
 }
 
+impl Record {
+    /// Decodes a record from its response, for example a line of a topic archive
+    pub fn from_response(
+        response: RecordResponse,
+        encoding: &Encoding,
+    ) -> Result<Self, encoding::Error> {
+        Ok(Record {
+            offset: response.offset,
+            timestamp: response.timestamp,
+            key: encoding.decode(&response.key)?,
+            value: encoding.decode(&response.value)?,
+            headers: response
+                .headers
+                .iter()
+                .map(|header| {
+                    Ok(RecordHeader {
+                        key: header.key.to_string(),
+                        value: encoding.decode(&header.value)?,
+                    })
+                })
+                .collect::<Result<_, encoding::Error>>()?,
+        })
+    }
+}
+
 #[cfg(test)]
 impl Record {
     pub fn basic(key: impl Into<String>, value: impl Into<String>) -> Self {
@@ -1,17 +1,22 @@
 use std::{
     collections::{BTreeMap, btree_map::Range},
     fmt::Debug,
-    io::ErrorKind,
     ops::RangeBounds,
 };
 
+use bytes::Buf;
 use tokio::{
-    fs::{File, OpenOptions, remove_file},
-    io::{self, AsyncReadExt, AsyncWriteExt, BufReader, BufWriter},
+    fs::{self, File, OpenOptions, remove_file},
+    io::{self, AsyncWriteExt, BufWriter},
 };
 
+use tracing::warn;
+
 use crate::dur::error::Result;
 
+/// Every entry is an offset followed by a position, both u64
+const ENTRY_SIZE: u64 = 16;
+
 pub struct Index {
     index: BTreeMap<u64, u64>,
     file: File,
@@ -27,32 +32,43 @@ impl Debug for Index {
     }
 }
 
+/// Decodes the entries of an index file in the order they were written, together with the
+/// amount of trailing bytes that do not form a complete entry
+pub(in crate::dur) fn decode_entries(mut bytes: &[u8]) -> (Vec<(u64, u64)>, usize) {
+    let mut entries = Vec::with_capacity(bytes.len() / ENTRY_SIZE as usize);
+
+    while bytes.remaining() >= ENTRY_SIZE as usize {
+        let offset = bytes.get_u64();
+        let position = bytes.get_u64();
+
+        entries.push((offset, position));
+    }
+
+    (entries, bytes.remaining())
+}
+
 impl Index {
     pub async fn load_from_disk(path: &str) -> Result<Self> {
-        let index_file = match OpenOptions::new().read(true).open(path).await {
-            Ok(file) => file,
+        let bytes = match fs::read(path).await {
+            Ok(bytes) => bytes,
             Err(err) if err.kind() == io::ErrorKind::NotFound => {
                 return Self::new(path, BTreeMap::default()).await;
             }
             Err(err) => return Err(err.into()),
         };
 
-        let mut index = BTreeMap::new();
-        let mut reader = BufReader::new(index_file);
-
-        loop {
-            let offset = match reader.read_u64().await {
-                Ok(offset) => offset,
-                Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
-                Err(e) => return Err(e.into()),
-            };
-
-            let position = reader.read_u64().await?;
+        // An incomplete entry at the end is left over from an interrupted append, it is cut off
+        // so new entries are not written after it
+        let (entries, trailing) = decode_entries(&bytes);
+        let size = entries.len() as u64 * ENTRY_SIZE;
+        let index = Self::new(path, entries.into_iter().collect()).await?;
 
-            index.insert(offset, position);
+        if trailing > 0 {
+            warn!("Truncating {trailing} bytes of an incomplete entry from index {path}");
+            index.file.set_len(size).await?;
         }
 
-        Self::new(path, index).await
+        Ok(index)
     }
 
     async fn new(path: &str, index: BTreeMap<u64, u64>) -> Result<Self> {
@@ -81,6 +97,17 @@ impl Index {
         Ok(())
     }
 
+    /// Removes the entries written after the index had `size` bytes
+    pub async fn truncate(&mut self, size: u64) -> Result<()> {
+        self.file.set_len(size).await?;
+        let keep = (size / ENTRY_SIZE) as usize;
+        while self.index.len() > keep {
+            self.index.pop_last();
+        }
+
+        Ok(())
+    }
+
     pub fn range<R>(&self, range: R) -> Range<'_, u64, u64>
     where
         R: RangeBounds<u64>,
@@ -96,6 +123,21 @@ impl Index {
         self.index.first_key_value().map(|e| *e.0)
     }
 
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Size of the index file on disk
+    pub fn size(&self) -> u64 {
+        self.index.len() as u64 * ENTRY_SIZE
+    }
+
+    pub async fn sync(&self) -> Result<()> {
+        self.file.sync_all().await?;
+
+        Ok(())
+    }
+
     pub async fn delete(self) -> Result<()> {
         let Self { file, path, .. } = self;
 
@@ -106,3 +148,45 @@ impl Index {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+    use tokio::fs;
+
+    use super::Index;
+
+    #[tokio::test]
+    async fn incomplete_entry_is_truncated() {
+        let dir = tempdir().expect("Failed to create tempdir");
+        let path = dir.path().join("0.index");
+        let path = path.to_str().unwrap();
+
+        let mut index = Index::load_from_disk(path)
+            .await
+            .expect("Failed to load index");
+        index.append(0, 0).await.expect("Failed to append");
+        drop(index);
+
+        // An append interrupted halfway through its entry
+        let mut bytes = fs::read(path).await.unwrap();
+        bytes.extend_from_slice(&[0, 0, 0, 1]);
+        fs::write(path, bytes).await.unwrap();
+
+        let mut index = Index::load_from_disk(path)
+            .await
+            .expect("Failed to load index");
+        assert_eq!(index.size(), 16);
+        index.append(1, 30).await.expect("Failed to append");
+        assert_eq!(fs::metadata(path).await.unwrap().len(), index.size());
+        drop(index);
+
+        let index = Index::load_from_disk(path)
+            .await
+            .expect("Failed to load index");
+        assert_eq!(index.range(..).collect::<Vec<_>>(), vec![
+            (&0, &0),
+            (&1, &30)
+        ]);
+    }
+}
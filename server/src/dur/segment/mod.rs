@@ -1,4 +1,4 @@
-mod index;
+pub(super) mod index;
 
 use std::fmt::Display;
 use std::os::unix::fs::{FileExt, MetadataExt};
@@ -7,27 +7,87 @@ use std::sync::Arc;
 use bytes::{Buf, Bytes};
 use index::Index;
 use shared::data::timestamp::Timestamp;
+use shared::state::segment_state::SegmentState;
 use std::fs::File as StdFile;
-use tokio::fs::remove_file;
+use tokio::fs::{self, hard_link, remove_file};
 use tokio::task::spawn_blocking;
 use tokio::{
     fs::{File, OpenOptions},
-    io::{AsyncSeekExt, AsyncWriteExt, BufWriter},
+    io::{self, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufWriter},
 };
+use tracing::warn;
 
-use crate::config::Config;
+use crate::config::{Config, LogDir};
 use crate::dur::error::Error;
 use crate::dur::record::{Record, RecordHeader};
+use crate::dur::storage::{OffloadCandidate, Storage, TieredLog};
 
 use super::error::Result;
 
+async fn copy_prefix(from: &str, to: &str, length: u64) -> Result<()> {
+    let mut reader = File::open(from).await?.take(length);
+    let mut writer = File::create(to).await?;
+
+    io::copy(&mut reader, &mut writer).await?;
+    writer.sync_all().await?;
+
+    Ok(())
+}
+
+/// The files of a closed segment, they do not change until the segment is offloaded or deleted
+#[derive(Debug, Clone)]
+pub struct SegmentFiles {
+    topic_id: u64,
+    partition_id: u64,
+    pub start_offset: u64,
+    log_path: String,
+    index_path: String,
+}
+
+impl SegmentFiles {
+    /// Copies the files into `target`, returns false when the segment was deleted in the meantime
+    pub async fn copy_to(&self, target: &LogDir) -> Result<bool> {
+        for (from, to) in [
+            (
+                &self.log_path,
+                target.log_path(self.topic_id, self.partition_id, self.start_offset),
+            ),
+            (
+                &self.index_path,
+                target.index_path(self.topic_id, self.partition_id, self.start_offset),
+            ),
+        ] {
+            match fs::copy(from, &to).await {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Where the log of a segment is stored
+enum Log {
+    Local {
+        read: Arc<StdFile>,
+        write: File,
+    },
+    /// Offloaded to tiered storage, only closed segments are offloaded
+    Tiered {
+        storage: Arc<dyn Storage>,
+        key: String,
+    },
+}
+
 pub struct Segment {
     topic_id: u64,
     partition_id: u64,
     start_offset: u64,
     log_file_path: String,
-    log_file_r: Arc<StdFile>,
-    log_file_w: File,
+    tiered_path: String,
+    log: Log,
     log_size: u64,
     index: Index,
     max_log_size: u64,
@@ -36,12 +96,44 @@ pub struct Segment {
 impl Segment {
     pub async fn load_from_disk(
         config: &Config,
+        log_dir: &LogDir,
         topic_id: u64,
         partition_id: u64,
         start_offset: u64,
     ) -> Result<Self> {
-        let log_file_path = config.log_path(topic_id, partition_id, start_offset);
-        let index_file_path = config.index_path(topic_id, partition_id, start_offset);
+        let log_file_path = log_dir.log_path(topic_id, partition_id, start_offset);
+        let index_file_path = log_dir.index_path(topic_id, partition_id, start_offset);
+        let tiered_path = log_dir.tiered_path(topic_id, partition_id, start_offset);
+
+        if let Some(tiered) = TieredLog::read(&tiered_path).await? {
+            let storage = config.tiering.backend.clone().ok_or_else(|| {
+                Error::TieredStorage(format!(
+                    "{tiered_path} is offloaded, but no tiered storage is configured"
+                ))
+            })?;
+
+            // The marker is written after the upload, a local log is left over from an offload
+            // that was interrupted before removing it
+            if fs::try_exists(&log_file_path).await? {
+                remove_file(&log_file_path).await?;
+            }
+
+            return Ok(Self {
+                start_offset,
+                topic_id,
+                partition_id,
+                log_file_path,
+                tiered_path,
+
+                index: Index::load_from_disk(&index_file_path).await?,
+                log: Log::Tiered {
+                    storage,
+                    key: tiered.key,
+                },
+                log_size: tiered.log_size,
+                max_log_size: config.segment.size,
+            });
+        }
 
         // TODO: should we always open the write file? what if a segment is closed
         let log_file_write = OpenOptions::new()
@@ -65,10 +157,13 @@ impl Segment {
             topic_id,
             partition_id,
             log_file_path,
+            tiered_path,
 
             index: Index::load_from_disk(&index_file_path).await?,
-            log_file_w: log_file_write,
-            log_file_r: Arc::new(log_file_read),
+            log: Log::Local {
+                read: Arc::new(log_file_read),
+                write: log_file_write,
+            },
             log_size,
             max_log_size: config.segment.size,
         })
@@ -79,7 +174,24 @@ impl Segment {
             return Err(Error::SegmentFull);
         }
 
-        let mut writer = BufWriter::new(&mut self.log_file_w);
+        let index_size = self.index.size();
+        if let Err(e) = self.write_record(record).await {
+            // A partial record would be read as corrupt, and the next record would be indexed at
+            // the wrong position
+            if let Err(rollback) = self.rollback(index_size).await {
+                warn!("Failed to roll back a partial append to {self}: {rollback}");
+            }
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    async fn write_record(&mut self, record: &Record) -> Result<()> {
+        let Log::Local { write, .. } = &mut self.log else {
+            unreachable!("Tiered segments are always full");
+        };
+        let mut writer = BufWriter::new(&mut *write);
 
         writer.write_u64(record.offset).await?;
         writer.write_u64(record.timestamp.as_micros()).await?;
@@ -105,13 +217,53 @@ impl Segment {
         // Save the size of the start of the mesasge, or, the log size before writing the message
         self.index.append(record.offset, self.log_size).await?;
 
-        self.log_size = self.log_file_w.stream_position().await?;
+        self.log_size = write.stream_position().await?;
 
         Ok(())
     }
 
+    /// Cuts the log and index back to their size before a failed append
+    async fn rollback(&mut self, index_size: u64) -> Result<()> {
+        if let Log::Local { write, .. } = &mut self.log {
+            write.set_len(self.log_size).await?;
+        }
+        self.index.truncate(index_size).await
+    }
+
     pub fn is_full(&self) -> bool {
-        self.log_size >= self.max_log_size
+        self.is_tiered() || self.log_size >= self.max_log_size
+    }
+
+    pub fn is_tiered(&self) -> bool {
+        matches!(self.log, Log::Tiered { .. })
+    }
+
+    pub fn set_max_log_size(&mut self, max_log_size: u64) {
+        self.max_log_size = max_log_size;
+    }
+
+    pub fn log_size(&self) -> u64 {
+        self.log_size
+    }
+
+    /// Bytes used on the local disk by both the log and the index
+    pub fn disk_usage(&self) -> u64 {
+        match self.log {
+            Log::Local { .. } => self.log_size + self.index.size(),
+            Log::Tiered { .. } => self.index.size(),
+        }
+    }
+
+    /// Timestamp of the newest record in this segment
+    pub async fn max_timestamp(&self) -> Result<Option<Timestamp>> {
+        let Some(max_offset) = self.max_offset() else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .read_exact(max_offset)
+            .await?
+            .map(|record| record.timestamp))
     }
 
     fn record_location(&self, offset: u64) -> Option<u64> {
@@ -133,44 +285,16 @@ impl Segment {
 
         let read_len = end_location - start_location;
 
-        let bytes = self.read_at(start_location, read_len as usize).await?;
+        let mut bytes = self.read_at(start_location, read_len as usize).await?;
         assert_eq!(bytes.len(), read_len as usize);
 
-        let mut bytes = Bytes::from(bytes);
         let mut records = Vec::new();
 
         while bytes.has_remaining() {
-            let offset = bytes.get_u64();
-            let timestamp = Timestamp::from(bytes.get_u64());
-
-            let key_len = bytes.get_u32();
-            let key = bytes.copy_to_bytes(key_len as usize);
-
-            let value_len = bytes.get_u32();
-            let value = bytes.copy_to_bytes(value_len as usize);
-
-            let header_len = bytes.get_u16();
-
-            let headers = (0..header_len)
-                .map(|_| {
-                    let key_len = bytes.get_u32();
-                    let key = bytes.copy_to_bytes(key_len as usize);
-                    let key = String::from_utf8(key.to_vec()).expect("failed to parse from_utf8");
+            let position = end_location - bytes.remaining() as u64;
+            let record = decode_record(&mut bytes).ok_or(Error::CorruptRecord(position))?;
 
-                    let value_len = bytes.get_u32();
-                    let value = bytes.copy_to_bytes(value_len as usize);
-
-                    RecordHeader { key, value }
-                })
-                .collect::<Vec<_>>();
-
-            records.push(Record {
-                offset,
-                timestamp,
-                key,
-                value,
-                headers,
-            });
+            records.push(record);
         }
 
         Ok(records)
@@ -198,89 +322,281 @@ impl Segment {
             self.log_size - record_file_offset
         } as usize;
 
-        let bytes = self.read_at(record_file_offset, record_len).await?;
+        let mut bytes = self.read_at(record_file_offset, record_len).await?;
         assert_eq!(bytes.len(), record_len);
 
-        let mut bytes = Bytes::from(bytes);
+        let record = decode_record(&mut bytes).ok_or(Error::CorruptRecord(record_file_offset))?;
 
-        let offset = bytes.get_u64();
-        let timestamp = Timestamp::from(bytes.get_u64());
+        Ok(Some(record))
+    }
 
-        let key_len = bytes.get_u32();
-        let key = bytes.copy_to_bytes(key_len as usize);
+    pub fn max_offset(&self) -> Option<u64> {
+        self.index.max_offset()
+    }
 
-        let value_len = bytes.get_u32();
-        let value = bytes.copy_to_bytes(value_len as usize);
+    pub fn min_offset(&self) -> Option<u64> {
+        self.index.min_offset()
+    }
 
-        let header_len = bytes.get_u16();
+    /// Writes are flushed on every append, this makes sure they reached the disk
+    pub async fn sync(&self) -> Result<()> {
+        if let Log::Local { write, .. } = &self.log {
+            write.sync_all().await?;
+        }
+        self.index.sync().await
+    }
 
-        let headers = (0..header_len)
-            .map(|_| {
-                let key_len = bytes.get_u32();
-                let key = bytes.copy_to_bytes(key_len as usize);
-                let key = String::from_utf8(key.to_vec()).expect("failed to parse from_utf8");
+    pub fn state(&self) -> SegmentState {
+        SegmentState {
+            start_offset: self.start_offset,
+            min_offset: self.min_offset(),
+            max_offset: self.max_offset(),
+            log_size: self.log_size,
+            log_path: self.log_file_path.clone(),
+            index_path: self.index.path().to_string(),
+            is_full: self.is_full(),
+            tiered_key: match &self.log {
+                Log::Local { .. } => None,
+                Log::Tiered { key, .. } => Some(key.clone()),
+            },
+        }
+    }
 
-                let value_len = bytes.get_u32();
-                let value = bytes.copy_to_bytes(value_len as usize);
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
 
-                RecordHeader { key, value }
-            })
-            .collect::<Vec<_>>();
+    /// Hard links the log and index into `target`, only for segments that are no longer appended
+    /// to
+    pub async fn link_to(&self, target: &LogDir) -> Result<()> {
+        match &self.log {
+            Log::Local { .. } => {
+                hard_link(
+                    &self.log_file_path,
+                    target.log_path(self.topic_id, self.partition_id, self.start_offset),
+                )
+                .await?
+            }
+            // Copied, so removing the segment later does not remove the log from the backup
+            Log::Tiered { storage, key } => {
+                let target_key = self.new_tiered_key();
+                storage.copy(key, &target_key).await?;
+
+                TieredLog {
+                    key: target_key,
+                    log_size: self.log_size,
+                }
+                .write(&target.tiered_path(self.topic_id, self.partition_id, self.start_offset))
+                .await?;
+            }
+        }
+        hard_link(
+            self.index.path(),
+            target.index_path(self.topic_id, self.partition_id, self.start_offset),
+        )
+        .await?;
 
-        Ok(Some(Record {
-            offset,
-            timestamp,
-            key,
-            value,
-            headers,
-        }))
+        Ok(())
     }
 
-    pub fn max_offset(&self) -> Option<u64> {
-        self.index.max_offset()
+    /// Copies the log and index as far as they are written into `target`
+    pub async fn copy_to(&self, target: &LogDir) -> Result<()> {
+        if self.is_tiered() {
+            return self.link_to(target).await;
+        }
+
+        copy_prefix(
+            &self.log_file_path,
+            &target.log_path(self.topic_id, self.partition_id, self.start_offset),
+            self.log_size,
+        )
+        .await?;
+        copy_prefix(
+            self.index.path(),
+            &target.index_path(self.topic_id, self.partition_id, self.start_offset),
+            self.index.size(),
+        )
+        .await
     }
 
-    pub fn min_offset(&self) -> Option<u64> {
-        self.index.min_offset()
+    /// Copies the segment into `target` for a partition that moves there, unlike [`Self::copy_to`]
+    /// an offloaded log keeps its key as the segment is not deleted afterwards
+    pub async fn relocate_to(&self, target: &LogDir) -> Result<()> {
+        let Log::Tiered { key, .. } = &self.log else {
+            return self.copy_to(target).await;
+        };
+
+        // A copy of the local log made before the segment was offloaded
+        let log_path = target.log_path(self.topic_id, self.partition_id, self.start_offset);
+        if fs::try_exists(&log_path).await? {
+            remove_file(&log_path).await?;
+        }
+
+        copy_prefix(
+            self.index.path(),
+            &target.index_path(self.topic_id, self.partition_id, self.start_offset),
+            self.index.size(),
+        )
+        .await?;
+        TieredLog {
+            key: key.clone(),
+            log_size: self.log_size,
+        }
+        .write(&target.tiered_path(self.topic_id, self.partition_id, self.start_offset))
+        .await
     }
 
-    pub fn index(&self) -> &Index {
-        &self.index
+    /// The files of the segment when its log is on the local disk
+    pub fn files(&self) -> Option<SegmentFiles> {
+        if self.is_tiered() {
+            return None;
+        }
+
+        Some(SegmentFiles {
+            topic_id: self.topic_id,
+            partition_id: self.partition_id,
+            start_offset: self.start_offset,
+            log_path: self.log_file_path.clone(),
+            index_path: self.index.path().to_string(),
+        })
+    }
+
+    /// Returns the segment when its log is on the local disk and its newest record is before
+    /// `before`, the caller makes sure it is closed
+    pub async fn offload_candidate(&self, before: Timestamp) -> Result<Option<OffloadCandidate>> {
+        if self.is_tiered() {
+            return Ok(None);
+        }
+
+        let Some(max_timestamp) = self.max_timestamp().await? else {
+            return Ok(None);
+        };
+        if max_timestamp.as_micros() >= before.as_micros() {
+            return Ok(None);
+        }
+
+        Ok(Some(OffloadCandidate {
+            topic_id: self.topic_id,
+            partition_id: self.partition_id,
+            start_offset: self.start_offset,
+            log_path: self.log_file_path.clone(),
+            key: self.new_tiered_key(),
+        }))
+    }
+
+    /// Reads the log uploaded as `key` from now on, the marker is written before the local log is
+    /// removed
+    pub async fn complete_offload(&mut self, storage: Arc<dyn Storage>, key: String) -> Result<()> {
+        TieredLog {
+            key: key.clone(),
+            log_size: self.log_size,
+        }
+        .write(&self.tiered_path)
+        .await?;
+
+        self.log = Log::Tiered { storage, key };
+        remove_file(&self.log_file_path).await?;
+
+        Ok(())
+    }
+
+    /// Keys are unique, a topic id can be reused after the topic is deleted
+    fn new_tiered_key(&self) -> String {
+        format!(
+            "{}/{}/{:0>20}-{}.log",
+            self.topic_id,
+            self.partition_id,
+            self.start_offset,
+            Timestamp::now().as_micros()
+        )
     }
 
     pub async fn delete(self) -> Result<()> {
         let Self {
             log_file_path,
-            log_file_r,
-            log_file_w,
+            tiered_path,
+            log,
             index,
             ..
         } = self;
 
-        drop(log_file_w);
-        drop(log_file_r);
-
         index.delete().await?;
-        remove_file(log_file_path).await?;
+
+        match log {
+            Log::Local { read, write } => {
+                drop(write);
+                drop(read);
+
+                remove_file(log_file_path).await?;
+            }
+            // The marker goes first, it should never point to a deleted object
+            Log::Tiered { storage, key } => {
+                remove_file(tiered_path).await?;
+                storage.delete(&key).await?;
+            }
+        }
 
         Ok(())
     }
 
     #[allow(clippy::uninit_vec)]
-    async fn read_at(&self, file_offset: u64, length: usize) -> Result<Vec<u8>> {
-        let file = self.log_file_r.clone();
-        spawn_blocking(move || {
-            let mut buf = Vec::with_capacity(length);
-            unsafe {
-                buf.set_len(length);
+    async fn read_at(&self, file_offset: u64, length: usize) -> Result<Bytes> {
+        match &self.log {
+            Log::Local { read, .. } => {
+                let file = read.clone();
+                spawn_blocking(move || {
+                    let mut buf = Vec::with_capacity(length);
+                    unsafe {
+                        buf.set_len(length);
+                    }
+                    file.read_exact_at(&mut buf, file_offset)?;
+
+                    Ok(buf.into())
+                })
+                .await
+                .expect("failed to join spawn_blocking handle")
+            }
+            Log::Tiered { storage, key } => {
+                storage
+                    .read_range(key, file_offset..file_offset + length as u64)
+                    .await
             }
-            file.read_exact_at(&mut buf, file_offset)?;
+        }
+    }
+}
 
-            Ok(buf)
+/// Decodes the record at the start of `bytes` in the format written by [`Segment::append`],
+/// returns None when the bytes end in the middle of the record or it is not valid
+pub(super) fn decode_record(bytes: &mut Bytes) -> Option<Record> {
+    let offset = bytes.try_get_u64().ok()?;
+    let timestamp = Timestamp::from(bytes.try_get_u64().ok()?);
+    let key = decode_bytes(bytes)?;
+    let value = decode_bytes(bytes)?;
+
+    let header_len = bytes.try_get_u16().ok()?;
+    let headers = (0..header_len)
+        .map(|_| {
+            let key = String::from_utf8(decode_bytes(bytes)?.to_vec()).ok()?;
+            let value = decode_bytes(bytes)?;
+
+            Some(RecordHeader { key, value })
         })
-        .await
-        .expect("failed to join spawn_blocking handle")
-    }
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(Record {
+        offset,
+        timestamp,
+        key,
+        value,
+        headers,
+    })
+}
+
+/// Reads a u32 length followed by that many bytes
+fn decode_bytes(bytes: &mut Bytes) -> Option<Bytes> {
+    let len = bytes.try_get_u32().ok()? as usize;
+
+    (bytes.remaining() >= len).then(|| bytes.split_to(len))
 }
 
 impl Display for Segment {
@@ -306,9 +622,9 @@ mod test {
     #[tokio::test]
     async fn segment_basic_read_write() {
         let config = Config::default();
-        create_dir_all(config.partition_path(0, 0)).unwrap();
+        create_dir_all(config.base_log_dir().partition_path(0, 0)).unwrap();
 
-        let mut segment = Segment::load_from_disk(&config, 0, 0, 0)
+        let mut segment = Segment::load_from_disk(&config, &config.base_log_dir(), 0, 0, 0)
             .await
             .expect("Failed to load segment");
 
@@ -332,9 +648,9 @@ mod test {
     #[tokio::test]
     async fn segment_continue_on_existing_segment() {
         let config = Config::default();
-        create_dir_all(config.partition_path(0, 0)).unwrap();
+        create_dir_all(config.base_log_dir().partition_path(0, 0)).unwrap();
 
-        let mut segment = Segment::load_from_disk(&config, 0, 0, 0)
+        let mut segment = Segment::load_from_disk(&config, &config.base_log_dir(), 0, 0, 0)
             .await
             .expect("Failed to load segment");
 
@@ -347,7 +663,7 @@ mod test {
         println!("{}", segment);
         drop(segment);
 
-        let segment = Segment::load_from_disk(&config, 0, 0, 0)
+        let segment = Segment::load_from_disk(&config, &config.base_log_dir(), 0, 0, 0)
             .await
             .expect("Failed to load segment");
 
@@ -362,11 +678,11 @@ mod test {
     #[tokio::test]
     async fn segment_is_full() {
         let mut config = Config::default();
-        create_dir_all(config.partition_path(0, 0)).unwrap();
+        create_dir_all(config.base_log_dir().partition_path(0, 0)).unwrap();
 
         config.segment.size = 1;
 
-        let mut segment = Segment::load_from_disk(&config, 0, 0, 0)
+        let mut segment = Segment::load_from_disk(&config, &config.base_log_dir(), 0, 0, 0)
             .await
             .expect("Failed to load segment");
 
@@ -0,0 +1,115 @@
+use std::{
+    io::{ErrorKind, SeekFrom},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use tokio::{
+    fs::{self, File, create_dir_all, hard_link},
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+
+use super::Storage;
+use crate::dur::error::Result;
+
+/// Stores objects as files below a directory, e.g. on a slower mount
+#[derive(Debug)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Creates the directories of the file of `key`
+    async fn create_path(&self, key: &str) -> Result<PathBuf> {
+        let path = self.root.join(key);
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent).await?;
+        }
+
+        Ok(path)
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, path: &Path) -> Result<()> {
+        let target = self.create_path(key).await?;
+        let tmp_target = target.with_extension("tmp");
+
+        // Copied to a temporary file first, so a partial copy never shows up as the object
+        fs::copy(path, &tmp_target).await?;
+        File::open(&tmp_target).await?.sync_all().await?;
+        fs::rename(&tmp_target, &target).await?;
+
+        Ok(())
+    }
+
+    async fn read_range(&self, key: &str, range: Range<u64>) -> Result<Bytes> {
+        let mut file = File::open(self.root.join(key)).await?;
+        file.seek(SeekFrom::Start(range.start)).await?;
+
+        let mut buffer = vec![0; (range.end - range.start) as usize];
+        file.read_exact(&mut buffer).await?;
+
+        Ok(buffer.into())
+    }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<()> {
+        let target = self.create_path(to).await?;
+        hard_link(self.root.join(from), target).await?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match fs::remove_file(self.root.join(key)).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use tempfile::{NamedTempFile, tempdir};
+
+    use super::{LocalStorage, Storage};
+
+    #[tokio::test]
+    async fn local_storage_put_read_copy_delete() {
+        let root = tempdir().expect("Failed to create tempdir");
+        let storage = LocalStorage::new(root.path());
+
+        let mut file = NamedTempFile::new().expect("Failed to create file");
+        file.write_all(b"Hello World").unwrap();
+
+        storage
+            .put("0/0/a.log", file.path())
+            .await
+            .expect("Failed to put");
+        storage
+            .copy("0/0/a.log", "1/0/b.log")
+            .await
+            .expect("Failed to copy");
+        storage.delete("0/0/a.log").await.expect("Failed to delete");
+        storage
+            .delete("0/0/a.log")
+            .await
+            .expect("Failed to delete a missing key");
+
+        let bytes = storage
+            .read_range("1/0/b.log", 6..11)
+            .await
+            .expect("Failed to read range");
+        assert_eq!(&bytes[..], b"World");
+        assert!(storage.read_range("0/0/a.log", 0..1).await.is_err());
+    }
+}
@@ -0,0 +1,120 @@
+//! Secondary storage that closed segments are offloaded to, see `tiering` in the config. Only the
+//! log of a segment is offloaded, its index stays on the local disk together with a marker that
+//! names the key of the log
+
+mod local;
+mod s3;
+
+use std::{fmt::Debug, io::ErrorKind, ops::Range, path::Path, sync::Arc};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tracing::warn;
+
+pub use local::LocalStorage;
+pub use s3::S3Storage;
+
+use super::error::{Error, Result};
+use crate::config::{Config, StorageConfig};
+
+/// Objects are written once and never changed, only read, copied and deleted
+#[async_trait]
+pub trait Storage: Debug + Send + Sync {
+    /// Uploads the file at `path` as `key`
+    async fn put(&self, key: &str, path: &Path) -> Result<()>;
+
+    async fn read_range(&self, key: &str, range: Range<u64>) -> Result<Bytes>;
+
+    async fn copy(&self, from: &str, to: &str) -> Result<()>;
+
+    /// Deleting a key that does not exist succeeds
+    async fn delete(&self, key: &str) -> Result<()>;
+}
+
+pub fn open(config: &StorageConfig) -> Result<Arc<dyn Storage>> {
+    Ok(match config {
+        StorageConfig::Local { path } => Arc::new(LocalStorage::new(path)),
+        StorageConfig::S3(config) => Arc::new(S3Storage::new(config)?),
+    })
+}
+
+/// Contents of the marker that replaces the log of an offloaded segment on the local disk
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TieredLog {
+    pub key: String,
+    pub log_size: u64,
+}
+
+/// A closed segment of which the log can be offloaded
+#[derive(Debug)]
+pub struct OffloadCandidate {
+    pub topic_id: u64,
+    pub partition_id: u64,
+    pub start_offset: u64,
+    pub log_path: String,
+    /// Key to upload the log as, unique so an offload never overwrites an existing object
+    pub key: String,
+}
+
+impl TieredLog {
+    pub async fn read(path: &str) -> Result<Option<Self>> {
+        let bytes = match fs::read(path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| Error::TieredStorage(format!("unreadable marker {path}: {e}")))
+    }
+
+    /// Written to a temporary file first, a marker is either complete or missing
+    pub async fn write(&self, path: &str) -> Result<()> {
+        let tmp_path = format!("{path}.tmp");
+
+        fs::write(
+            &tmp_path,
+            serde_json::to_vec(self).expect("serde_json to_vec failed"),
+        )
+        .await?;
+        fs::rename(&tmp_path, path).await?;
+
+        Ok(())
+    }
+}
+
+/// Deletes the offloaded logs of a topic directory that is removed without being loaded, such as
+/// a purged trash entry
+pub async fn delete_tiered_logs(config: &Config, topic_path: &str) -> Result<()> {
+    let mut partitions = match fs::read_dir(format!("{topic_path}/partitions")).await {
+        Ok(partitions) => partitions,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    while let Some(partition) = partitions.next_entry().await? {
+        let mut files = fs::read_dir(partition.path()).await?;
+        while let Some(file) = files.next_entry().await? {
+            let path = file.path();
+            if path.extension().is_none_or(|s| s != "tiered") {
+                continue;
+            }
+
+            let Some(tiered) = TieredLog::read(&path.to_string_lossy()).await? else {
+                continue;
+            };
+            match &config.tiering.backend {
+                Some(storage) => storage.delete(&tiered.key).await?,
+                None => warn!(
+                    "Leaving {} in tiered storage, no tiered storage is configured",
+                    tiered.key
+                ),
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use std::{ops::Range, path::Path, sync::Arc};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use object_store::{
+    ObjectStore, aws::AmazonS3Builder, buffered::BufWriter, path::Path as ObjectPath,
+};
+use tokio::{fs::File, io::AsyncWriteExt};
+
+use super::Storage;
+use crate::{
+    config::S3Config,
+    dur::error::{Error, Result},
+};
+
+/// Stores objects in an S3 compatible bucket, settings that are not configured are read from the
+/// `AWS_*` environment variables
+#[derive(Debug)]
+pub struct S3Storage {
+    store: Arc<dyn ObjectStore>,
+    prefix: Option<String>,
+}
+
+impl S3Storage {
+    pub fn new(config: &S3Config) -> Result<Self> {
+        let S3Config {
+            bucket,
+            prefix,
+            endpoint,
+            region,
+            access_key_id,
+            secret_access_key,
+            allow_http,
+        } = config;
+
+        let mut builder = AmazonS3Builder::from_env()
+            .with_bucket_name(bucket)
+            .with_allow_http(*allow_http);
+        if let Some(endpoint) = endpoint {
+            builder = builder.with_endpoint(endpoint);
+        }
+        if let Some(region) = region {
+            builder = builder.with_region(region);
+        }
+        if let Some(access_key_id) = access_key_id {
+            builder = builder.with_access_key_id(access_key_id);
+        }
+        if let Some(secret_access_key) = secret_access_key {
+            builder = builder.with_secret_access_key(secret_access_key);
+        }
+
+        Ok(Self {
+            store: Arc::new(builder.build().map_err(storage_error)?),
+            prefix: prefix.clone(),
+        })
+    }
+
+    fn location(&self, key: &str) -> ObjectPath {
+        match &self.prefix {
+            Some(prefix) => ObjectPath::from(format!("{prefix}/{key}")),
+            None => ObjectPath::from(key),
+        }
+    }
+}
+
+fn storage_error(error: object_store::Error) -> Error {
+    Error::TieredStorage(error.to_string())
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(&self, key: &str, path: &Path) -> Result<()> {
+        // Large logs are uploaded in parts instead of being read into memory at once
+        let mut writer = BufWriter::new(self.store.clone(), self.location(key));
+        let mut file = File::open(path).await?;
+
+        tokio::io::copy(&mut file, &mut writer).await?;
+        writer.shutdown().await?;
+
+        Ok(())
+    }
+
+    async fn read_range(&self, key: &str, range: Range<u64>) -> Result<Bytes> {
+        self.store
+            .get_range(&self.location(key), range)
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<()> {
+        self.store
+            .copy(&self.location(from), &self.location(to))
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match self.store.delete(&self.location(key)).await {
+            Err(object_store::Error::NotFound { .. }) => Ok(()),
+            result => result.map_err(storage_error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, io::Write};
+
+    use tempfile::NamedTempFile;
+
+    use super::{S3Storage, Storage};
+    use crate::config::S3Config;
+
+    /// Runs against a local stand-in, e.g.
+    /// `docker run -p 9000:9000 minio/minio server /data` with a bucket named `pigeon`, then
+    /// `PIGEON_TEST_S3_ENDPOINT=http://127.0.0.1:9000 cargo test -- --ignored s3_storage`
+    #[tokio::test]
+    #[ignore]
+    async fn s3_storage_put_read_copy_delete() {
+        let var = |key: &str, default: &str| env::var(key).unwrap_or(default.to_string());
+        let storage = S3Storage::new(&S3Config {
+            bucket: var("PIGEON_TEST_S3_BUCKET", "pigeon"),
+            prefix: Some("test".to_string()),
+            endpoint: Some(var("PIGEON_TEST_S3_ENDPOINT", "http://127.0.0.1:9000")),
+            region: Some("us-east-1".to_string()),
+            access_key_id: Some(var("PIGEON_TEST_S3_ACCESS_KEY_ID", "minioadmin")),
+            secret_access_key: Some(var("PIGEON_TEST_S3_SECRET_ACCESS_KEY", "minioadmin")),
+            allow_http: true,
+        })
+        .expect("Failed to create storage");
+
+        let mut file = NamedTempFile::new().expect("Failed to create file");
+        file.write_all(b"Hello World").unwrap();
+
+        storage
+            .put("0/0/a.log", file.path())
+            .await
+            .expect("Failed to put");
+        storage
+            .copy("0/0/a.log", "0/0/b.log")
+            .await
+            .expect("Failed to copy");
+        storage.delete("0/0/a.log").await.expect("Failed to delete");
+        storage
+            .delete("0/0/a.log")
+            .await
+            .expect("Failed to delete a missing key");
+
+        let bytes = storage
+            .read_range("0/0/b.log", 6..11)
+            .await
+            .expect("Failed to read range");
+        assert_eq!(&bytes[..], b"World");
+        storage.delete("0/0/b.log").await.expect("Failed to delete");
+    }
+}
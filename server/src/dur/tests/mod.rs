@@ -21,9 +21,10 @@ async fn single_topic_random_test() {
 
     let mut random = SmallRng::seed_from_u64(54323409);
 
-    let mut topic = Topic::load_from_disk(config.clone(), 0, "foo", 10)
-        .await
-        .expect("Failed to create topic");
+    let mut topic =
+        Topic::load_from_disk(config.clone(), 0, "foo", vec![config.base_log_dir(); 10])
+            .await
+            .expect("Failed to create topic");
 
     for i in 0..count {
         for p in 0..config.topic.num_partitions {
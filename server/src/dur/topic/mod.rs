@@ -1,36 +1,47 @@
+use std::collections::{BTreeSet, HashSet};
 use std::sync::Arc;
 
 use bytes::Bytes;
 use shared::data::offset_selection::OffsetSelection;
+use shared::data::timestamp::Timestamp;
+use shared::data::topic_config::{CleanupPolicy, TopicConfig};
+use shared::state::segment_state::SegmentState;
 use shared::state::topic_state::TopicState;
 use tokio::fs::remove_dir;
 
-use crate::config::Config;
+use crate::config::{Config, LogDir};
 use crate::dur::error::{Error, Result};
+use crate::dur::storage::{OffloadCandidate, Storage};
+use crate::metrics::METRICS;
 use crate::record_batch::RecordBatch;
 
 use super::partition::Partition;
 use super::record::{Record, RecordHeader};
+use super::segment::SegmentFiles;
+use super::trash;
 
 pub struct Topic {
     topic_id: u64,
     name: String,
     config: Arc<Config>,
+    topic_config: TopicConfig,
 
     pub(super) partitions: Vec<Partition>,
 }
 
 impl Topic {
+    /// Loads a partition from each of `log_dirs`, the index is the partition id
     pub async fn load_from_disk(
         config: Arc<Config>,
         topic_id: u64,
         name: &str,
-        partition_count: u64,
+        log_dirs: Vec<LogDir>,
     ) -> Result<Self> {
-        let mut partitions = Vec::with_capacity(partition_count as usize);
-        for partition_id in 0..partition_count {
+        let mut partitions = Vec::with_capacity(log_dirs.len());
+        for (partition_id, log_dir) in log_dirs.into_iter().enumerate() {
             let partition =
-                Partition::load_from_disk(config.clone(), topic_id, partition_id).await?;
+                Partition::load_from_disk(config.clone(), log_dir, topic_id, partition_id as u64)
+                    .await?;
             partitions.push(partition);
         }
 
@@ -38,39 +49,211 @@ impl Topic {
             topic_id,
             name: name.to_string(),
             config,
+            topic_config: TopicConfig::default(),
             partitions,
         })
     }
 
+    /// Adds a partition in each of `log_dirs`, existing partitions are left untouched
+    pub async fn add_partitions(&mut self, log_dirs: Vec<LogDir>) -> Result<()> {
+        let segment_size = self
+            .topic_config
+            .segment_size
+            .unwrap_or(self.config.segment.size);
+
+        for log_dir in log_dirs {
+            let mut partition = Partition::load_from_disk(
+                self.config.clone(),
+                log_dir,
+                self.topic_id,
+                self.partition_count(),
+            )
+            .await?;
+            partition.set_segment_size(segment_size);
+
+            self.partitions.push(partition);
+        }
+
+        Ok(())
+    }
+
+    pub fn partition_count(&self) -> u64 {
+        self.partitions.len() as u64
+    }
+
+    /// Applies the topic level overrides of the broker configuration
+    pub fn set_config(&mut self, topic_config: TopicConfig) {
+        let segment_size = topic_config
+            .segment_size
+            .unwrap_or(self.config.segment.size);
+
+        for partition in self.partitions.iter_mut() {
+            partition.set_segment_size(segment_size);
+        }
+
+        self.topic_config = topic_config;
+    }
+
+    /// Replaces the broker configuration after a reload, settings read during loading keep their
+    /// old value
+    pub fn set_broker_config(&mut self, config: Arc<Config>) {
+        for partition in self.partitions.iter_mut() {
+            partition.set_broker_config(config.clone());
+        }
+
+        self.config = config;
+    }
+
+    pub fn topic_config(&self) -> &TopicConfig {
+        &self.topic_config
+    }
+
+    /// The topic limit can only lower `limits.max_record_size`
+    pub fn max_record_size(&self) -> u64 {
+        let max_record_size = self.config.limits.max_record_size;
+
+        self.topic_config
+            .max_record_size
+            .or(self.config.topic.max_record_size)
+            .map_or(max_record_size, |size| size.min(max_record_size))
+    }
+
+    pub async fn enforce_retention(&mut self) -> Result<usize> {
+        if self.topic_config.cleanup_policy.unwrap_or_default() == CleanupPolicy::Retain {
+            return Ok(0);
+        }
+
+        let retention_ms = self
+            .topic_config
+            .retention_ms
+            .or(self.config.topic.retention_ms);
+        let retention_bytes = self
+            .topic_config
+            .retention_bytes
+            .or(self.config.topic.retention_bytes);
+
+        if retention_ms.is_none() && retention_bytes.is_none() {
+            return Ok(0);
+        }
+
+        let mut removed = 0;
+        for partition in self.partitions.iter_mut() {
+            removed += partition
+                .enforce_retention(retention_ms, retention_bytes)
+                .await?;
+        }
+
+        Ok(removed)
+    }
+
     pub async fn read_all_from_partition(&mut self, partition_id: u64) -> Result<Vec<Record>> {
-        // TODO: we should batch this as a single read for multiple messages
+        self.read_from_partition(partition_id, 0).await
+    }
+
+    pub async fn read_from_partition(&self, partition_id: u64, offset: u64) -> Result<Vec<Record>> {
         let partition = self
             .partitions
             .get(partition_id as usize)
             .ok_or(Error::PartitionNotFound)?;
 
-        let min_offset = partition.min_offset().unwrap_or(0);
-        let max_offset = partition.max_offset().unwrap_or(0);
+        partition.read_from(offset).await
+    }
 
-        let mut v = vec![];
-        for offset in min_offset..=max_offset {
-            match partition.read_exact(offset).await {
-                Ok(Some(record)) => v.push(record),
-                Ok(None) => continue,
-                Err(e) => return Err(e),
-            }
-        }
+    pub async fn read_segment_from(&self, partition_id: u64, offset: u64) -> Result<Vec<Record>> {
+        let partition = self
+            .partitions
+            .get(partition_id as usize)
+            .ok_or(Error::PartitionNotFound)?;
+
+        partition.read_segment_from(offset).await
+    }
+
+    pub fn min_offset(&self, partition_id: u64) -> Result<Option<u64>> {
+        let partition = self
+            .partitions
+            .get(partition_id as usize)
+            .ok_or(Error::PartitionNotFound)?;
+
+        Ok(partition.min_offset())
+    }
+
+    pub fn max_offset(&self, partition_id: u64) -> Result<Option<u64>> {
+        let partition = self
+            .partitions
+            .get(partition_id as usize)
+            .ok_or(Error::PartitionNotFound)?;
+
+        Ok(partition.max_offset())
+    }
+
+    pub async fn delete_segments_before(
+        &mut self,
+        partition_id: u64,
+        offset: u64,
+    ) -> Result<usize> {
+        let partition = self
+            .partitions
+            .get_mut(partition_id as usize)
+            .ok_or(Error::PartitionNotFound)?;
+
+        partition.delete_segments_before(offset).await
+    }
+
+    pub fn segment_states(&self, partition_id: u64) -> Result<Vec<SegmentState>> {
+        let partition = self
+            .partitions
+            .get(partition_id as usize)
+            .ok_or(Error::PartitionNotFound)?;
+
+        Ok(partition.segment_states())
+    }
 
-        Ok(v)
+    pub async fn roll_segment(&mut self, partition_id: u64) -> Result<bool> {
+        let partition = self
+            .partitions
+            .get_mut(partition_id as usize)
+            .ok_or(Error::PartitionNotFound)?;
+
+        partition.roll_segment().await
     }
 
+    /// Removes the topic from disk, or moves it to the trash when soft deletes are enabled
     pub async fn delete(self) -> Result<()> {
+        if self.config.trash.soft_delete {
+            let partition_count = self.partition_count();
+            let Self {
+                topic_id,
+                name,
+                config,
+                topic_config,
+                partitions,
+            } = self;
+            drop(partitions);
+
+            trash::move_topic_to_trash(
+                &config,
+                topic_id,
+                Some(&name),
+                topic_config,
+                partition_count,
+            )
+            .await?;
+            return Ok(());
+        }
+
+        let log_dirs = self
+            .partitions
+            .iter()
+            .map(|partition| partition.log_dir().clone())
+            .collect::<BTreeSet<_>>();
         for partition in self.partitions.into_iter() {
             partition.delete().await?;
         }
 
-        remove_dir(self.config.partitions_path(self.topic_id)).await?;
-        remove_dir(self.config.topic_path(self.topic_id)).await?;
+        for log_dir in log_dirs {
+            remove_dir(log_dir.partitions_path(self.topic_id)).await?;
+            remove_dir(log_dir.topic_path(self.topic_id)).await?;
+        }
 
         Ok(())
     }
@@ -87,7 +270,22 @@ impl Topic {
             .get_mut(partition_id as usize)
             .ok_or(Error::PartitionNotFound)?;
 
-        partition.append(key, value, headers).await
+        let record = partition.append(key, value, headers).await?;
+        METRICS.bytes_in.inc_by(&self.name, record.size() as u64);
+
+        Ok(record)
+    }
+
+    pub async fn append_record(&mut self, partition_id: u64, record: Record) -> Result<Record> {
+        let partition = self
+            .partitions
+            .get_mut(partition_id as usize)
+            .ok_or(Error::PartitionNotFound)?;
+
+        let record = partition.append_record(record).await?;
+        METRICS.bytes_in.inc_by(&self.name, record.size() as u64);
+
+        Ok(record)
     }
 
     pub async fn read_batch(
@@ -122,13 +320,104 @@ impl Topic {
                 .iter()
                 .map(|partition| partition.state())
                 .collect(),
+            config: self.topic_config.clone(),
         }
     }
 
+    pub async fn sync(&self) -> Result<()> {
+        for partition in &self.partitions {
+            partition.sync().await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn offload_candidates(&self, before: Timestamp) -> Result<Vec<OffloadCandidate>> {
+        let mut candidates = Vec::new();
+        for partition in &self.partitions {
+            candidates.append(&mut partition.offload_candidates(before).await?);
+        }
+
+        Ok(candidates)
+    }
+
+    pub async fn complete_offload(
+        &mut self,
+        partition_id: u64,
+        start_offset: u64,
+        storage: Arc<dyn Storage>,
+        key: String,
+    ) -> Result<bool> {
+        self.partitions
+            .get_mut(partition_id as usize)
+            .ok_or(Error::PartitionNotFound)?
+            .complete_offload(start_offset, storage, key)
+            .await
+    }
+
+    /// Log-end offsets of every partition in the backup, by partition id
+    pub async fn backup(&self, backup_id: &str) -> Result<Vec<u64>> {
+        let mut end_offsets = Vec::with_capacity(self.partitions.len());
+        for partition in &self.partitions {
+            end_offsets.push(partition.backup(backup_id).await?);
+        }
+
+        Ok(end_offsets)
+    }
+
+    pub fn disk_usage(&self) -> u64 {
+        self.partitions.iter().map(Partition::disk_usage).sum()
+    }
+
+    /// The log directory and disk usage of each partition
+    pub fn placements(&self) -> Vec<(LogDir, u64)> {
+        self.partitions
+            .iter()
+            .map(|partition| (partition.log_dir().clone(), partition.disk_usage()))
+            .collect()
+    }
+
+    pub fn log_dir(&self, partition_id: u64) -> Result<&LogDir> {
+        let partition = self
+            .partitions
+            .get(partition_id as usize)
+            .ok_or(Error::PartitionNotFound)?;
+
+        Ok(partition.log_dir())
+    }
+
+    pub fn closed_segment_files(&self, partition_id: u64) -> Result<Vec<SegmentFiles>> {
+        let partition = self
+            .partitions
+            .get(partition_id as usize)
+            .ok_or(Error::PartitionNotFound)?;
+
+        Ok(partition.closed_segment_files())
+    }
+
+    pub async fn relocate_partition(
+        &mut self,
+        partition_id: u64,
+        target: LogDir,
+        staging: &LogDir,
+        copied: &HashSet<u64>,
+    ) -> Result<()> {
+        self.partitions
+            .get_mut(partition_id as usize)
+            .ok_or(Error::PartitionNotFound)?
+            .relocate(target, staging, copied)
+            .await
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    /// Data is stored by topic id, so renaming only changes the in memory name
+    pub fn rename(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
     pub fn is_internal(&self) -> bool {
         self.name.starts_with("__")
     }
@@ -149,9 +438,10 @@ mod test {
     async fn topic_basic_read_write() {
         let config = Arc::new(Config::default());
 
-        let mut topic = Topic::load_from_disk(config, 0, "foo", 10)
-            .await
-            .expect("Failed to create topic");
+        let mut topic =
+            Topic::load_from_disk(config.clone(), 0, "foo", vec![config.base_log_dir(); 10])
+                .await
+                .expect("Failed to create topic");
 
         let record = topic
             .append(0, "foo".into(), "bar".into(), vec![])
@@ -173,9 +463,10 @@ mod test {
     async fn topic_continue_on_existing() {
         let config = Arc::new(Config::default());
 
-        let mut topic = Topic::load_from_disk(config.clone(), 0, "foo", 10)
-            .await
-            .expect("Failed to create topic");
+        let mut topic =
+            Topic::load_from_disk(config.clone(), 0, "foo", vec![config.base_log_dir(); 10])
+                .await
+                .expect("Failed to create topic");
 
         let record = topic
             .append(0, "foo".into(), "bar".into(), vec![])
@@ -184,9 +475,10 @@ mod test {
         assert_eq!(record.offset, 0);
         drop(topic);
 
-        let topic = Topic::load_from_disk(config.clone(), 0, "foo", 10)
-            .await
-            .expect("Failed to create topic");
+        let topic =
+            Topic::load_from_disk(config.clone(), 0, "foo", vec![config.base_log_dir(); 10])
+                .await
+                .expect("Failed to create topic");
 
         let read_record = topic
             .read_exact(0, 0)
@@ -202,9 +494,10 @@ mod test {
     async fn topic_multiple_partitions() {
         let config = Arc::new(Config::default());
 
-        let mut topic = Topic::load_from_disk(config.clone(), 0, "foo", 10)
-            .await
-            .expect("Failed to create topic");
+        let mut topic =
+            Topic::load_from_disk(config.clone(), 0, "foo", vec![config.base_log_dir(); 10])
+                .await
+                .expect("Failed to create topic");
 
         let record = topic
             .append(0, "foo".into(), "bar".into(), vec![])
@@ -213,9 +506,10 @@ mod test {
         assert_eq!(record.offset, 0);
         drop(topic);
 
-        let topic = Topic::load_from_disk(config.clone(), 0, "foo", 10)
-            .await
-            .expect("Failed to create topic");
+        let topic =
+            Topic::load_from_disk(config.clone(), 0, "foo", vec![config.base_log_dir(); 10])
+                .await
+                .expect("Failed to create topic");
 
         let read_record = topic
             .read_exact(0, 0)
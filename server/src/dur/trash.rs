@@ -0,0 +1,155 @@
+use std::io::ErrorKind;
+
+use shared::{
+    data::{timestamp::Timestamp, topic_config::TopicConfig},
+    state::trash_state::TrashState,
+};
+use tokio::fs::{self, create_dir_all};
+use tracing::{info, warn};
+
+use crate::config::Config;
+
+use super::{error::Result, storage::delete_tiered_logs};
+
+/// Moves the directories of a topic into a new trash entry, the topic should no longer be
+/// loaded. The state is kept below `path`, the partitions stay in their log directory
+pub async fn move_topic_to_trash(
+    config: &Config,
+    topic_id: u64,
+    name: Option<&str>,
+    topic_config: TopicConfig,
+    partitions: u64,
+) -> Result<TrashState> {
+    let deleted_at = Timestamp::now();
+    let state = TrashState {
+        trash_id: format!("{}-{}", deleted_at.as_micros(), topic_id),
+        topic_id,
+        name: name.map(str::to_string),
+        partitions,
+        config: topic_config,
+        deleted_at,
+    };
+
+    create_dir_all(config.trash_entry_path(&state.trash_id)).await?;
+    fs::write(
+        config.trash_state_path(&state.trash_id),
+        serde_json::to_vec(&state).expect("serde_json to_vec failed"),
+    )
+    .await?;
+    for log_dir in config.all_log_dirs() {
+        if !fs::try_exists(log_dir.topic_path(topic_id)).await? {
+            continue;
+        }
+
+        create_dir_all(log_dir.trash_entry_path(&state.trash_id)).await?;
+        fs::rename(
+            log_dir.topic_path(topic_id),
+            log_dir.trash_topic_path(&state.trash_id),
+        )
+        .await?;
+    }
+
+    info!("Moved topic {topic_id} to trash as {}", state.trash_id);
+    Ok(state)
+}
+
+/// Trash ids are used in paths, anything but the generated format is rejected
+fn is_valid_trash_id(trash_id: &str) -> bool {
+    !trash_id.is_empty() && trash_id.chars().all(|c| c.is_ascii_digit() || c == '-')
+}
+
+pub async fn read(config: &Config, trash_id: &str) -> Result<Option<TrashState>> {
+    if !is_valid_trash_id(trash_id) {
+        return Ok(None);
+    }
+
+    let bytes = match fs::read(config.trash_state_path(trash_id)).await {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    match serde_json::from_slice(&bytes) {
+        Ok(state) => Ok(Some(state)),
+        Err(e) => {
+            warn!("Ignoring unreadable trash entry {trash_id}: {e}");
+            Ok(None)
+        }
+    }
+}
+
+/// All trash entries, ordered from oldest to newest
+pub async fn list(config: &Config) -> Result<Vec<TrashState>> {
+    let mut states = Vec::new();
+
+    let mut stream = match fs::read_dir(config.trash_path()).await {
+        Ok(stream) => stream,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(states),
+        Err(e) => return Err(e.into()),
+    };
+
+    while let Some(entry) = stream.next_entry().await? {
+        let Some(trash_id) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+
+        if let Some(state) = read(config, &trash_id).await? {
+            states.push(state);
+        }
+    }
+
+    states.sort_by_key(|state| state.deleted_at.as_micros());
+    Ok(states)
+}
+
+/// Moves the topic directories of a trash entry back as the directories of `topic_id`
+pub async fn restore(config: &Config, trash_id: &str, topic_id: u64) -> Result<()> {
+    for log_dir in config.all_log_dirs() {
+        if fs::try_exists(log_dir.trash_topic_path(trash_id)).await? {
+            create_dir_all(log_dir.topics_path()).await?;
+            fs::rename(
+                log_dir.trash_topic_path(trash_id),
+                log_dir.topic_path(topic_id),
+            )
+            .await?;
+        }
+    }
+    remove_entry(config, trash_id).await?;
+
+    info!("Restored trash entry {trash_id} as topic {topic_id}");
+    Ok(())
+}
+
+/// Permanently removes trash entries deleted before `before`, returns the amount of removed
+/// entries
+pub async fn purge(config: &Config, before: Timestamp) -> Result<usize> {
+    let mut removed = 0;
+
+    for state in list(config).await? {
+        if state.deleted_at.as_micros() >= before.as_micros() {
+            break;
+        }
+
+        info!("Purging trash entry {}", state.trash_id);
+        for log_dir in config.all_log_dirs() {
+            delete_tiered_logs(config, &log_dir.trash_topic_path(&state.trash_id)).await?;
+        }
+        remove_entry(config, &state.trash_id).await?;
+        removed += 1;
+    }
+
+    Ok(removed)
+}
+
+/// Removes the entry from every log directory, the state in `path` goes last
+async fn remove_entry(config: &Config, trash_id: &str) -> Result<()> {
+    for log_dir in config.all_log_dirs().iter().rev() {
+        match fs::remove_dir_all(log_dir.trash_entry_path(trash_id)).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(())
+}
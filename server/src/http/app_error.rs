@@ -1,4 +1,8 @@
-use axum::{Json, http::StatusCode, response::IntoResponse};
+use axum::{
+    Json,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
 use shared::{data::encoding, response::error_response::ErrorResponse};
 use tokio::sync::broadcast::error::RecvError;
 
@@ -27,6 +31,11 @@ impl From<RecvError> for AppError {
 
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
+        let code = self.0.code();
+        let retry_after_ms = match self.0 {
+            app::error::Error::Throttled(retry_after_ms) => Some(retry_after_ms),
+            _ => None,
+        };
         let (status, message) = match self.0 {
             app::error::Error::Durrability(error) => match error {
                 crate::dur::error::Error::UnderlyingIO(error) => {
@@ -44,6 +53,18 @@ impl IntoResponse for AppError {
                 crate::dur::error::Error::OffsetOutOfRange => {
                     (StatusCode::INTERNAL_SERVER_ERROR, error.to_string())
                 }
+                crate::dur::error::Error::CorruptRecord(_) => {
+                    (StatusCode::INTERNAL_SERVER_ERROR, error.to_string())
+                }
+                crate::dur::error::Error::TieredStorage(_) => {
+                    (StatusCode::INTERNAL_SERVER_ERROR, error.to_string())
+                }
+                crate::dur::error::Error::DiskFull(_) => {
+                    (StatusCode::INSUFFICIENT_STORAGE, error.to_string())
+                }
+                crate::dur::error::Error::MissingLogEntries(..) => {
+                    (StatusCode::INTERNAL_SERVER_ERROR, error.to_string())
+                }
             },
             app::error::Error::TopicIdNotFound(_) => (StatusCode::BAD_REQUEST, self.0.to_string()),
             app::error::Error::MaxTopicIdReached => (StatusCode::BAD_REQUEST, self.0.to_string()),
@@ -62,17 +83,84 @@ impl IntoResponse for AppError {
                 (StatusCode::INTERNAL_SERVER_ERROR, recv_error.to_string())
             }
             app::error::Error::InvalidName(_) => (StatusCode::BAD_REQUEST, self.0.to_string()),
+            app::error::Error::Unauthorized => (StatusCode::UNAUTHORIZED, self.0.to_string()),
+            app::error::Error::AdminRequired => (StatusCode::FORBIDDEN, self.0.to_string()),
+            app::error::Error::InvalidTokenName(_) => (StatusCode::BAD_REQUEST, self.0.to_string()),
+            app::error::Error::TokenNameInUse(_) => (StatusCode::BAD_REQUEST, self.0.to_string()),
+            app::error::Error::TokenNameNotFound(_) => {
+                (StatusCode::BAD_REQUEST, self.0.to_string())
+            }
+            app::error::Error::AclDenied(..) => (StatusCode::FORBIDDEN, self.0.to_string()),
+            app::error::Error::AclNotFound(_) => (StatusCode::BAD_REQUEST, self.0.to_string()),
+            app::error::Error::InvalidPrincipal(_) => (StatusCode::BAD_REQUEST, self.0.to_string()),
+            app::error::Error::RecordTooLarge(..) => {
+                (StatusCode::PAYLOAD_TOO_LARGE, self.0.to_string())
+            }
+            app::error::Error::TooManyHeaders(..) => (StatusCode::BAD_REQUEST, self.0.to_string()),
+            app::error::Error::RequestTooLarge(_) => {
+                (StatusCode::PAYLOAD_TOO_LARGE, self.0.to_string())
+            }
+            app::error::Error::InvalidTopicConfig(_) => {
+                (StatusCode::BAD_REQUEST, self.0.to_string())
+            }
+            app::error::Error::PartitionCountDecrease(..) => {
+                (StatusCode::BAD_REQUEST, self.0.to_string())
+            }
+            app::error::Error::TrashNotFound(_) => (StatusCode::BAD_REQUEST, self.0.to_string()),
+            app::error::Error::InvalidArchive(_) => (StatusCode::BAD_REQUEST, self.0.to_string()),
+            app::error::Error::UnknownLogDir(_) => (StatusCode::BAD_REQUEST, self.0.to_string()),
+            app::error::Error::Throttled(_) => (StatusCode::TOO_MANY_REQUESTS, self.0.to_string()),
+            app::error::Error::InvalidQuota(_) => (StatusCode::BAD_REQUEST, self.0.to_string()),
+            app::error::Error::QuotaNotFound(_) => (StatusCode::BAD_REQUEST, self.0.to_string()),
+            app::error::Error::LeaseNotFound(..) => (StatusCode::BAD_REQUEST, self.0.to_string()),
+            app::error::Error::QueueDelayTooLong(..) => {
+                (StatusCode::BAD_REQUEST, self.0.to_string())
+            }
+            app::error::Error::TooManyMessages(..) => (StatusCode::BAD_REQUEST, self.0.to_string()),
+            app::error::Error::OffsetNotOnSegmentBoundary(_) => {
+                (StatusCode::BAD_REQUEST, self.0.to_string())
+            }
+            app::error::Error::InvalidReservedHeader(..) => {
+                (StatusCode::BAD_REQUEST, self.0.to_string())
+            }
+            app::error::Error::RecordNotFound(..) => (StatusCode::BAD_REQUEST, self.0.to_string()),
         };
 
-        (
+        let mut response = (
             status,
             Json(ErrorResponse {
                 error: message,
                 status: status.as_u16(),
+                code,
+                retry_after_ms,
             }),
         )
-            .into_response()
+            .into_response();
+
+        // Retry-After only has second precision, the body carries the exact delay
+        if let Some(retry_after_ms) = retry_after_ms {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, retry_after_ms.div_ceil(1000).into());
+        }
+
+        response
     }
 }
 
 pub type AppResult<T> = Result<Json<T>, AppError>;
+
+/// Replaces the plain text rejection of a body over `http.max_request_size` with an error
+/// response, record limits are already reported as JSON
+pub async fn request_too_large(response: Response, max_request_size: usize) -> Response {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value == "application/json");
+
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE && !is_json {
+        return AppError(app::error::Error::RequestTooLarge(max_request_size)).into_response();
+    }
+
+    response
+}
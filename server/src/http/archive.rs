@@ -0,0 +1,140 @@
+use std::pin::Pin;
+
+use axum::{
+    Extension, Json,
+    body::Body,
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use shared::{
+    commands::import_topic_command::ImportTopicCommand,
+    data::{identifier::Identifier, topic_archive::TopicArchiveHeader},
+    response::{import_response::ImportResponse, record_response::RecordResponse},
+};
+use tokio_stream::{Stream, StreamExt};
+
+use super::app_error::{AppError, AppResult};
+use crate::{
+    app::{App, error::Error},
+    auth::Principal,
+};
+
+/// Streams the topic as newline-delimited JSON, a header line followed by every record of every
+/// partition. The lock is only held while reading a single segment
+pub async fn export_topic(
+    State(app): State<App>,
+    Path(name): Path<String>,
+) -> Result<Response, AppError> {
+    let export = app.read().await.export_topic(&Identifier::Name(name))?;
+
+    let stream: Pin<Box<dyn Stream<Item = Result<String, Error>> + Send>> = Box::pin(
+        async_stream::try_stream! {
+            let header = serde_json::to_string(&export.header).expect("Failed to serialize header");
+            yield header + "\n";
+
+            for (partition_id, end_offset) in export.end_offsets.into_iter().enumerate() {
+                let partition_id = partition_id as u64;
+                let mut offset = 0;
+
+                while offset < end_offset {
+                    let records = app
+                        .read()
+                        .await
+                        .export_records(export.topic_id, partition_id, offset, end_offset)
+                        .await?;
+                    let Some(last) = records.last() else {
+                        break;
+                    };
+                    offset = last.offset + 1;
+
+                    let mut chunk = String::new();
+                    for record in records {
+                        chunk += &serde_json::to_string(&record).expect("Failed to serialize record");
+                        chunk += "\n";
+                    }
+                    yield chunk;
+                }
+            }
+        },
+    );
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+/// Creates a topic from an archive streamed in the request body. The records that were imported
+/// before an invalid line are kept
+pub async fn import_topic(
+    State(app): State<App>,
+    Extension(principal): Extension<Principal>,
+    Query(import): Query<ImportTopicCommand>,
+    body: Body,
+) -> AppResult<ImportResponse> {
+    let mut stream = body.into_data_stream();
+    let mut buffer = Vec::new();
+    let mut line_number = 0;
+    let mut topic_id = None;
+    let mut records = 0;
+
+    loop {
+        let chunk = stream
+            .next()
+            .await
+            .transpose()
+            .map_err(|e| Error::InvalidArchive(e.to_string()))?;
+        let done = chunk.is_none();
+        match chunk {
+            Some(chunk) => buffer.extend_from_slice(&chunk),
+            // The last line may not end in a newline
+            None if !buffer.is_empty() => buffer.push(b'\n'),
+            None => {}
+        }
+
+        while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+            let line = buffer.drain(..=end).collect::<Vec<_>>();
+            line_number += 1;
+
+            let line = &line[..end];
+            if line.is_empty() {
+                continue;
+            }
+
+            match topic_id {
+                None => {
+                    let header: TopicArchiveHeader = serde_json::from_slice(line)
+                        .map_err(|e| Error::InvalidArchive(format!("line {line_number}: {e}")))?;
+
+                    topic_id = Some(
+                        app.write()
+                            .await
+                            .import_topic(&principal, &header, import.name.as_deref())
+                            .await?,
+                    );
+                }
+                Some(topic_id) => {
+                    let record: RecordResponse = serde_json::from_slice(line)
+                        .map_err(|e| Error::InvalidArchive(format!("line {line_number}: {e}")))?;
+
+                    app.write()
+                        .await
+                        .import_record(topic_id, record, import.keep_offsets)
+                        .await?;
+                    records += 1;
+                }
+            }
+        }
+
+        if done {
+            break;
+        }
+    }
+
+    let topic_id = topic_id.ok_or(Error::InvalidArchive("missing header".to_string()))?;
+    let topic = app.read().await.get_topic_by_id(topic_id)?.state();
+
+    Ok(Json(ImportResponse { topic, records }))
+}
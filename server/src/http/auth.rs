@@ -0,0 +1,68 @@
+use axum::{
+    extract::{Request, State},
+    http::header::AUTHORIZATION,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{
+    app::{App, error::Error},
+    auth::Principal,
+};
+
+use super::app_error::AppError;
+
+/// Resolves the [`Principal`] of every request and stores it in the request extensions for
+/// later handlers and middleware. With authentication enabled this is the owner of the bearer
+/// token, otherwise the configured principal header is used, falling back to anonymous
+pub async fn authenticate(
+    State(app): State<App>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let principal = {
+        let lock = app.read().await;
+
+        if lock.auth_enabled() {
+            let token = request
+                .headers()
+                .get(AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .ok_or(Error::Unauthorized)?;
+
+            lock.authenticate(token)?
+        } else {
+            lock.principal_header()
+                .and_then(|header| request.headers().get(header))
+                .and_then(|value| value.to_str().ok())
+                .filter(|name| !name.is_empty())
+                .map(|name| Principal {
+                    name: name.to_string(),
+                    admin: false,
+                })
+                .unwrap_or_else(Principal::anonymous)
+        }
+    };
+
+    request.extensions_mut().insert(principal);
+
+    Ok(next.run(request).await)
+}
+
+pub async fn require_admin(
+    State(app): State<App>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if app.read().await.auth_enabled()
+        && !request
+            .extensions()
+            .get::<Principal>()
+            .is_some_and(|principal| principal.admin)
+    {
+        return Err(Error::AdminRequired.into());
+    }
+
+    Ok(next.run(request).await)
+}
@@ -0,0 +1,24 @@
+use axum::{Router, extract::State, http::StatusCode, routing::get};
+
+use crate::app::App;
+
+/// The process is up and answering requests
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// Metadata has loaded and the broker is not shutting down
+pub async fn readyz(State(app): State<App>) -> (StatusCode, &'static str) {
+    if app.is_shutting_down() {
+        (StatusCode::SERVICE_UNAVAILABLE, "shutting down")
+    } else {
+        (StatusCode::OK, "ok")
+    }
+}
+
+/// Served while the app loads from disk, only the liveness probe succeeds
+pub fn loading_router() -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .fallback(|| async { (StatusCode::SERVICE_UNAVAILABLE, "loading") })
+}
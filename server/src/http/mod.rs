@@ -1,48 +1,89 @@
 mod app_error;
+mod archive;
+mod auth;
+mod health;
 
 use std::collections::HashMap;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
 use std::time::Duration;
 
-use app_error::{AppError, AppResult};
-use axum::extract::{Path, State};
-use axum::routing::{delete, get, post};
+use app_error::{AppError, AppResult, request_too_large};
+use axum::Extension;
+use axum::extract::{DefaultBodyLimit, Path, State};
+use axum::http::header;
+use axum::middleware::{from_fn_with_state, map_response};
+use axum::response::IntoResponse;
+use axum::routing::{delete, get, post, put};
 use axum::{Json, Router};
+use shared::commands::ack_command::{AckCommand, NackCommand};
+use shared::commands::alter_partitions_command::AlterPartitionsCommand;
+use shared::commands::alter_topic_config_command::AlterTopicConfigCommand;
+use shared::commands::create_acl_command::CreateAclCommand;
+use shared::commands::create_token_command::CreateTokenCommand;
 use shared::commands::create_topic_command::CreateTopicCommand;
+use shared::commands::dead_letter_command::DeadLetterCommand;
 use shared::commands::fetch_command::FetchCommand;
+use shared::commands::move_partition_command::MovePartitionCommand;
 use shared::commands::produce_command::ProduceCommand;
+use shared::commands::receive_command::ReceiveCommand;
+use shared::commands::rename_topic_command::RenameTopicCommand;
+use shared::commands::restore_trash_command::RestoreTrashCommand;
+use shared::commands::truncate_partition_command::TruncatePartitionCommand;
+use shared::consts::{DELIVER_AT_HEADER, EXPIRES_AT_HEADER};
 use shared::data::encoding;
 use shared::data::identifier::Identifier;
+use shared::data::quota::{Quota, QuotaEntity};
+use shared::data::timestamp::Timestamp;
+use shared::response::dead_letter_response::DeadLetterResponse;
 use shared::response::produce_response::ProduceResponse;
+use shared::response::receive_response::{QueueMessageResponse, ReceiveResponse};
 use shared::response::record_response::FetchResponse;
+use shared::response::snapshot_response::SnapshotResponse;
+use shared::response::token_response::CreateTokenResponse;
+use shared::state::acl_state::AclState;
+use shared::state::backup_state::BackupState;
+use shared::state::log_dir_state::LogDirState;
+use shared::state::partition_state::PartitionState;
+use shared::state::queue_state::QueueState;
+use shared::state::quota_state::QuotaState;
+use shared::state::segment_state::SegmentState;
+use shared::state::token_state::TokenState;
 use shared::state::topic_state::TopicState;
+use shared::state::trash_state::TrashState;
+use tokio::io;
 use tokio::net::TcpListener;
 use tokio::select;
+use tokio::sync::oneshot;
 use tokio::time::{self, Instant};
 use tokio_stream::{Stream, StreamExt, StreamMap};
 use tracing::info;
 
-use crate::app::App;
-use crate::dur::record::{Record, RecordHeader};
+use crate::app::{App, AppLock};
+use crate::auth::Principal;
+use crate::dur::record::{Delivery, Record, RecordHeader};
+use crate::metrics::METRICS;
 use crate::record_batch::RecordBatch;
 
 pub struct HttpServer {
-    router: Router,
-    address: String,
+    listener: std::net::TcpListener,
 }
 
 async fn create_topic(
     State(app): State<App>,
+    Extension(principal): Extension<Principal>,
     Json(create_topic): Json<CreateTopicCommand>,
 ) -> AppResult<TopicState> {
     let mut lock = app.write().await;
 
     let topic_id = lock
         .create_topic(
+            &principal,
             create_topic.topic_id,
             &create_topic.name,
             create_topic.partitions,
+            create_topic.config,
         )
         .await?;
 
@@ -53,13 +94,29 @@ async fn create_topic(
 
 async fn produce(
     State(app): State<App>,
+    Extension(principal): Extension<Principal>,
     Json(produce): Json<ProduceCommand>,
 ) -> AppResult<ProduceResponse> {
+    let start = Instant::now();
     let mut lock = app.write().await;
+    let topic = lock.topic_label(&produce.topic);
 
+    let result = produce_record(&mut lock, &principal, produce).await;
+
+    METRICS.produce_requests.inc(&topic);
+    METRICS.produce_latency.observe(&topic, start.elapsed());
+
+    Ok(Json(ProduceResponse { offset: result? }))
+}
+
+async fn produce_record(
+    lock: &mut AppLock,
+    principal: &Principal,
+    produce: ProduceCommand,
+) -> Result<u64, AppError> {
     let key = produce.encoding.decode(&produce.key)?;
     let value = produce.encoding.decode(&produce.value)?;
-    let headers = produce
+    let mut headers = produce
         .headers
         .unwrap_or(vec![])
         .iter()
@@ -69,44 +126,276 @@ async fn produce(
                 value: produce.encoding.decode(&header.value)?,
             })
         })
-        .collect::<Result<_, encoding::Error>>()?;
+        .collect::<Result<Vec<_>, encoding::Error>>()?;
+    if let Some(expires_at) = produce.expires_at {
+        headers.push(RecordHeader::timestamp(EXPIRES_AT_HEADER, expires_at));
+    }
+    if let Some(deliver_at) = produce.deliver_at {
+        headers.push(RecordHeader::timestamp(DELIVER_AT_HEADER, deliver_at));
+    }
 
     let offset = lock
-        .produce(produce.topic, produce.partition_id, key, value, headers)
+        .produce(
+            principal,
+            produce.topic,
+            produce.partition_id,
+            key,
+            value,
+            headers,
+        )
+        .await?;
+
+    Ok(offset)
+}
+
+async fn dead_letter(
+    State(app): State<App>,
+    Extension(principal): Extension<Principal>,
+    Json(dead_letter): Json<DeadLetterCommand>,
+) -> AppResult<DeadLetterResponse> {
+    let mut lock = app.write().await;
+
+    let response = lock
+        .dead_letter_record(
+            &principal,
+            &dead_letter.topic,
+            dead_letter.partition_id,
+            dead_letter.offset,
+            &dead_letter.error,
+            dead_letter.attempts,
+        )
         .await?;
 
-    Ok(Json(ProduceResponse { offset }))
+    Ok(Json(response))
 }
 
 async fn get_topic_state(
     State(app): State<App>,
+    Extension(principal): Extension<Principal>,
+    Path(name): Path<String>,
+) -> AppResult<TopicState> {
+    let lock = app.read().await;
+
+    let state = lock.topic_state(&principal, &Identifier::Name(name))?;
+
+    Ok(Json(state))
+}
+
+async fn alter_topic_config(
+    State(app): State<App>,
+    Extension(principal): Extension<Principal>,
+    Path(name): Path<String>,
+    Json(alter): Json<AlterTopicConfigCommand>,
+) -> AppResult<TopicState> {
+    let mut lock = app.write().await;
+
+    let state = lock
+        .alter_topic_config(
+            &principal,
+            &Identifier::Name(name),
+            &alter.config,
+            &alter.reset,
+        )
+        .await?;
+
+    Ok(Json(state))
+}
+
+async fn alter_partitions(
+    State(app): State<App>,
+    Extension(principal): Extension<Principal>,
+    Path(name): Path<String>,
+    Json(alter): Json<AlterPartitionsCommand>,
+) -> AppResult<TopicState> {
+    let mut lock = app.write().await;
+
+    let state = lock
+        .alter_partitions(&principal, &Identifier::Name(name), alter.partitions)
+        .await?;
+
+    Ok(Json(state))
+}
+
+async fn rename_topic(
+    State(app): State<App>,
+    Extension(principal): Extension<Principal>,
     Path(name): Path<String>,
+    Json(rename): Json<RenameTopicCommand>,
 ) -> AppResult<TopicState> {
+    let mut lock = app.write().await;
+
+    let state = lock
+        .rename_topic(&principal, &Identifier::Name(name), &rename.name)
+        .await?;
+
+    Ok(Json(state))
+}
+
+async fn receive(
+    State(app): State<App>,
+    Extension(principal): Extension<Principal>,
+    Path(name): Path<String>,
+    Json(receive): Json<ReceiveCommand>,
+) -> AppResult<ReceiveResponse> {
+    let mut lock = app.write().await;
+    let identifier = Identifier::Name(name);
+
+    let messages = lock
+        .receive(
+            &principal,
+            &identifier,
+            receive.max_messages,
+            receive.visibility_timeout_ms,
+        )
+        .await?;
+
+    let topic_id = lock.get_topic(&identifier)?.id();
+    let messages = messages
+        .into_iter()
+        .map(|message| {
+            Ok(QueueMessageResponse {
+                record: message.record.to_response(
+                    &receive.encoding,
+                    topic_id,
+                    message.lease.partition_id,
+                )?,
+                lease: message.lease,
+                deliveries: message.deliveries,
+                visible_at: message.visible_at,
+            })
+        })
+        .collect::<Result<Vec<_>, encoding::Error>>()?;
+
+    Ok(Json(ReceiveResponse {
+        encoding: receive.encoding,
+        messages,
+    }))
+}
+
+async fn ack(
+    State(app): State<App>,
+    Extension(principal): Extension<Principal>,
+    Path(name): Path<String>,
+    Json(ack): Json<AckCommand>,
+) -> Result<(), AppError> {
+    let mut lock = app.write().await;
+
+    lock.ack(&principal, &Identifier::Name(name), &ack.leases)
+        .await?;
+
+    Ok(())
+}
+
+async fn nack(
+    State(app): State<App>,
+    Extension(principal): Extension<Principal>,
+    Path(name): Path<String>,
+    Json(nack): Json<NackCommand>,
+) -> Result<(), AppError> {
+    let mut lock = app.write().await;
+
+    lock.nack(
+        &principal,
+        &Identifier::Name(name),
+        &nack.leases,
+        nack.delay_ms,
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn get_queue_state(
+    State(app): State<App>,
+    Extension(principal): Extension<Principal>,
+    Path(name): Path<String>,
+) -> AppResult<QueueState> {
     let lock = app.read().await;
 
-    let state = lock.get_topic_by_name(&name)?.state();
+    let state = lock.queue_state(&principal, &Identifier::Name(name))?;
 
     Ok(Json(state))
 }
 
-async fn get_all_topics_state(State(app): State<App>) -> AppResult<HashMap<u64, TopicState>> {
+async fn get_all_topics_state(
+    State(app): State<App>,
+    Extension(principal): Extension<Principal>,
+) -> AppResult<HashMap<u64, TopicState>> {
     let lock = app.read().await;
 
-    Ok(Json(lock.topic_states()))
+    Ok(Json(lock.topic_states(&principal)))
 }
 
-async fn delete_topic(State(app): State<App>, Path(name): Path<String>) -> Result<(), AppError> {
+async fn delete_topic(
+    State(app): State<App>,
+    Extension(principal): Extension<Principal>,
+    Path(name): Path<String>,
+) -> Result<(), AppError> {
     let mut lock = app.write().await;
 
-    lock.delete_topic(&Identifier::Name(name)).await?;
+    lock.delete_topic(&principal, &Identifier::Name(name))
+        .await?;
 
     Ok(())
 }
 
 async fn fetch(
     State(app): State<App>,
+    Extension(principal): Extension<Principal>,
     Json(fetch): Json<FetchCommand>,
 ) -> AppResult<FetchResponse> {
+    let start = Instant::now();
+    let result = fetch_batch(&app, &principal, &fetch).await;
+    let elapsed = start.elapsed();
+
+    let lock = app.read().await;
+    for topic in &fetch.topics {
+        let topic = lock.topic_label(&topic.identifier);
+        METRICS.fetch_requests.inc(&topic);
+        METRICS.fetch_latency.observe(&topic, elapsed);
+    }
+
+    let batch = result?;
+    let mut bytes_out = HashMap::new();
+    for (topic_id, bytes) in batch.topic_bytes() {
+        let topic = lock.topic_label(&Identifier::Id(topic_id));
+        METRICS.bytes_out.inc_by(&topic, bytes as u64);
+        bytes_out.insert(topic, bytes as u64);
+    }
+
+    let topic_bytes = fetch
+        .topics
+        .iter()
+        .map(|topic| {
+            let topic = lock.topic_label(&topic.identifier);
+            let bytes = bytes_out.get(&topic).copied().unwrap_or(0);
+            (topic, bytes)
+        })
+        .collect::<Vec<_>>();
+    let delay = lock.charge_fetch_quota(&principal, &topic_bytes);
+    drop(lock);
+
+    // Over quota fetches are answered late instead of rejected, so consumers slow down on their own
+    if !delay.is_zero() {
+        for (topic, _) in &topic_bytes {
+            METRICS.throttled_requests.inc(topic);
+        }
+
+        let mut shutdown = app.shutdown_receiver();
+        select! {
+            _ = shutdown.wait_for(|shutdown| *shutdown) => {}
+            _ = time::sleep(delay) => {}
+        }
+    }
+
+    Ok(Json(batch.to_response(fetch.encoding)?))
+}
+
+async fn fetch_batch(
+    app: &App,
+    principal: &Principal,
+    fetch: &FetchCommand,
+) -> Result<RecordBatch, AppError> {
     let until = Instant::now() + Duration::from_millis(fetch.timeout_ms);
     let mut batch = RecordBatch::new(fetch.min_bytes, fetch.max_bytes);
 
@@ -115,6 +404,7 @@ async fn fetch(
     for topic in &fetch.topics {
         for partition in &topic.partitions {
             lock.read_batch(
+                principal,
                 &mut batch,
                 &partition.offset,
                 partition.id,
@@ -123,7 +413,7 @@ async fn fetch(
             .await?;
 
             if batch.is_full() {
-                return Ok(Json(batch.to_response(fetch.encoding)?));
+                return Ok(batch);
             }
         }
     }
@@ -131,7 +421,7 @@ async fn fetch(
     drop(lock);
 
     if batch.is_ready() {
-        return Ok(Json(batch.to_response(fetch.encoding)?));
+        return Ok(batch);
     }
 
     let mut lock = app.write().await;
@@ -139,7 +429,7 @@ async fn fetch(
 
     for topic in &fetch.topics {
         let topic_id = lock.get_topic(&topic.identifier)?.id();
-        let mut rx = lock.subscribe(&topic.identifier)?;
+        let mut rx = lock.subscribe(principal, &topic.identifier)?;
 
         let rx = Box::pin(async_stream::stream! {
             while let Ok((partition_id, record)) = rx.recv().await {
@@ -158,15 +448,40 @@ async fn fetch(
 
     drop(lock);
 
+    let mut shutdown = app.shutdown_receiver();
+
     loop {
+        // Returns once a held back record is deliverable, the consumer reads it with its next fetch
+        let next_delivery = batch.next_delivery().map(|deliver_at| {
+            let now = Timestamp::now().as_micros();
+            Duration::from_micros(deliver_at.as_micros().saturating_sub(now))
+        });
+
         select! {
-             _ = time::sleep_until(until) => return Ok(Json(batch.to_response(fetch.encoding)?)),
+            _ = shutdown.wait_for(|shutdown| *shutdown) => return Ok(batch),
+            _ = time::sleep_until(until) => {
+                METRICS.fetch_timeouts.fetch_add(1, Ordering::Relaxed);
+                return Ok(batch);
+            }
+            _ = time::sleep(next_delivery.unwrap_or_default()), if next_delivery.is_some() => {
+                return Ok(batch);
+            }
             record = map.next() => {
-                if let Some((topic_id, (partition_id, record))) = record {
-                    batch.push(topic_id, partition_id, record.as_ref().clone());
+                if let Some((topic_id, (partition_id, record))) = record
+                    && !batch.is_held_back(topic_id, partition_id)
+                {
+                    match record.delivery(Timestamp::now()) {
+                        Delivery::Deliverable => {
+                            batch.push(topic_id, partition_id, record.as_ref().clone());
+                        }
+                        Delivery::Expired => {}
+                        Delivery::NotBefore(deliver_at) => {
+                            batch.hold_back(topic_id, partition_id, deliver_at);
+                        }
+                    }
 
                     if batch.is_ready() {
-                        return Ok(Json(batch.to_response(fetch.encoding)?))
+                        return Ok(batch)
                     }
                 }
             }
@@ -174,31 +489,336 @@ async fn fetch(
     }
 }
 
-impl HttpServer {
-    pub fn new(host: &str, port: u16, app: App) -> Self {
-        let router = Router::new()
-            .route("/topics", post(create_topic))
-            .route("/topics", get(get_all_topics_state))
-            .route("/topics/{name}/state", get(get_topic_state))
-            .route("/topics/{name}", delete(delete_topic))
-            .route("/topics/records", post(produce))
-            .route("/topics/records", get(fetch))
-            .with_state(app);
+async fn get_segments(
+    State(app): State<App>,
+    Path((name, partition_id)): Path<(String, u64)>,
+) -> AppResult<Vec<SegmentState>> {
+    let lock = app.read().await;
+
+    let segments = lock.segment_states(&Identifier::Name(name), partition_id)?;
+
+    Ok(Json(segments))
+}
+
+async fn roll_segment(
+    State(app): State<App>,
+    Path((name, partition_id)): Path<(String, u64)>,
+) -> AppResult<Vec<SegmentState>> {
+    let mut lock = app.write().await;
+
+    let segments = lock
+        .roll_segment(&Identifier::Name(name), partition_id)
+        .await?;
+
+    Ok(Json(segments))
+}
+
+async fn truncate_partition(
+    State(app): State<App>,
+    Path((name, partition_id)): Path<(String, u64)>,
+    Json(truncate): Json<TruncatePartitionCommand>,
+) -> AppResult<Vec<SegmentState>> {
+    let mut lock = app.write().await;
+
+    let segments = lock
+        .truncate_partition(&Identifier::Name(name), partition_id, truncate.offset)
+        .await?;
 
+    Ok(Json(segments))
+}
+
+async fn move_partition(
+    State(app): State<App>,
+    Path((name, partition_id)): Path<(String, u64)>,
+    Json(move_partition): Json<MovePartitionCommand>,
+) -> AppResult<PartitionState> {
+    let state = app
+        .move_partition(
+            &Identifier::Name(name),
+            partition_id,
+            &move_partition.log_dir,
+        )
+        .await?;
+
+    Ok(Json(state))
+}
+
+async fn get_all_log_dirs(State(app): State<App>) -> AppResult<Vec<LogDirState>> {
+    let lock = app.read().await;
+
+    Ok(Json(lock.log_dir_states()))
+}
+
+async fn metrics(State(app): State<App>) -> impl IntoResponse {
+    let lock = app.read().await;
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        lock.encode_metrics(),
+    )
+}
+
+async fn create_token(
+    State(app): State<App>,
+    Json(create_token): Json<CreateTokenCommand>,
+) -> AppResult<CreateTokenResponse> {
+    let mut lock = app.write().await;
+
+    let response = lock
+        .create_token(&create_token.name, create_token.admin)
+        .await?;
+
+    Ok(Json(response))
+}
+
+async fn get_all_tokens(State(app): State<App>) -> AppResult<Vec<TokenState>> {
+    let lock = app.read().await;
+
+    Ok(Json(lock.token_states()))
+}
+
+async fn revoke_token(State(app): State<App>, Path(name): Path<String>) -> Result<(), AppError> {
+    let mut lock = app.write().await;
+
+    lock.revoke_token(&name).await?;
+
+    Ok(())
+}
+
+async fn create_acl(
+    State(app): State<App>,
+    Json(create_acl): Json<CreateAclCommand>,
+) -> AppResult<AclState> {
+    let mut lock = app.write().await;
+
+    let acl = lock
+        .create_acl(
+            &create_acl.principal,
+            create_acl.pattern,
+            create_acl.operations,
+        )
+        .await?;
+
+    Ok(Json(acl))
+}
+
+async fn get_all_acls(State(app): State<App>) -> AppResult<Vec<AclState>> {
+    let lock = app.read().await;
+
+    Ok(Json(lock.acl_states()))
+}
+
+async fn delete_acl(State(app): State<App>, Path(acl_id): Path<u64>) -> Result<(), AppError> {
+    let mut lock = app.write().await;
+
+    lock.delete_acl(acl_id).await?;
+
+    Ok(())
+}
+
+async fn set_client_quota(
+    State(app): State<App>,
+    Path(name): Path<String>,
+    Json(quota): Json<Quota>,
+) -> AppResult<QuotaState> {
+    let mut lock = app.write().await;
+
+    let state = lock.set_quota(QuotaEntity::Client(name), quota).await?;
+
+    Ok(Json(state))
+}
+
+async fn set_topic_quota(
+    State(app): State<App>,
+    Path(name): Path<String>,
+    Json(quota): Json<Quota>,
+) -> AppResult<QuotaState> {
+    let mut lock = app.write().await;
+
+    let state = lock.set_quota(QuotaEntity::Topic(name), quota).await?;
+
+    Ok(Json(state))
+}
+
+async fn get_all_quotas(State(app): State<App>) -> AppResult<Vec<QuotaState>> {
+    let lock = app.read().await;
+
+    Ok(Json(lock.quota_states()))
+}
+
+async fn delete_client_quota(
+    State(app): State<App>,
+    Path(name): Path<String>,
+) -> Result<(), AppError> {
+    let mut lock = app.write().await;
+
+    lock.delete_quota(QuotaEntity::Client(name)).await?;
+
+    Ok(())
+}
+
+async fn delete_topic_quota(
+    State(app): State<App>,
+    Path(name): Path<String>,
+) -> Result<(), AppError> {
+    let mut lock = app.write().await;
+
+    lock.delete_quota(QuotaEntity::Topic(name)).await?;
+
+    Ok(())
+}
+
+async fn snapshot_metadata(State(app): State<App>) -> AppResult<SnapshotResponse> {
+    let mut lock = app.write().await;
+
+    let offset = lock.snapshot_metadata().await?;
+
+    Ok(Json(SnapshotResponse { offset }))
+}
+
+async fn get_all_trash(State(app): State<App>) -> AppResult<Vec<TrashState>> {
+    let lock = app.read().await;
+
+    Ok(Json(lock.trash_states().await?))
+}
+
+async fn restore_trash(
+    State(app): State<App>,
+    Path(trash_id): Path<String>,
+    Json(restore): Json<RestoreTrashCommand>,
+) -> AppResult<TopicState> {
+    let mut lock = app.write().await;
+
+    let state = lock
+        .restore_trash(&trash_id, restore.name.as_deref())
+        .await?;
+
+    Ok(Json(state))
+}
+
+async fn get_all_backups(State(app): State<App>) -> AppResult<Vec<BackupState>> {
+    let lock = app.read().await;
+
+    Ok(Json(lock.backup_states().await?))
+}
+
+async fn create_backup(State(app): State<App>) -> AppResult<BackupState> {
+    let mut lock = app.write().await;
+
+    Ok(Json(lock.create_backup().await?))
+}
+
+impl HttpServer {
+    /// Binds the listener up front so probes are answered while the app loads from disk
+    pub async fn bind(host: &str, port: u16) -> io::Result<Self> {
         let address = format!("{}:{}", host, port);
+        let listener = TcpListener::bind(&address).await?.into_std()?;
+
+        info!("Starting listener on {}", &address);
 
-        HttpServer { router, address }
+        Ok(HttpServer { listener })
     }
 
-    pub async fn serve(self) -> tokio::io::Result<()> {
-        let listener = TcpListener::bind(&self.address).await?;
+    /// Serves the loading router until `loading` completes, then hands the listener back
+    pub async fn serve_while_loading<F: Future>(&self, loading: F) -> io::Result<F::Output> {
+        let listener = TcpListener::from_std(self.listener.try_clone()?)?;
+        let (loaded_tx, loaded_rx) = oneshot::channel::<()>();
 
-        info!("Starting listener on {}", &self.address);
+        let server = tokio::spawn(async move {
+            axum::serve(listener, health::loading_router())
+                .with_graceful_shutdown(async {
+                    let _ = loaded_rx.await;
+                })
+                .await
+        });
 
-        axum::serve(listener, self.router)
-            .await
-            .expect("axum::serve failed");
+        let output = loading.await;
+
+        let _ = loaded_tx.send(());
+        server.await.map_err(io::Error::other)??;
 
-        Ok(())
+        Ok(output)
     }
+
+    /// Serves until `shutdown` completes, after which no new connections are accepted and the
+    /// requests in flight are completed
+    pub async fn serve(
+        self,
+        app: App,
+        shutdown: impl Future<Output = ()> + Send + 'static,
+    ) -> io::Result<()> {
+        let listener = TcpListener::from_std(self.listener)?;
+        let max_request_size = app.read().await.max_request_size();
+
+        axum::serve(listener, router(app, max_request_size))
+            .with_graceful_shutdown(shutdown)
+            .await
+    }
+}
+
+fn router(app: App, max_request_size: usize) -> Router {
+    let admin = Router::new()
+        .route("/admin/tokens", post(create_token))
+        .route("/admin/tokens", get(get_all_tokens))
+        .route("/admin/tokens/{name}", delete(revoke_token))
+        .route("/admin/acls", post(create_acl))
+        .route("/admin/acls", get(get_all_acls))
+        .route("/admin/acls/{acl_id}", delete(delete_acl))
+        .route("/admin/quotas", get(get_all_quotas))
+        .route("/admin/quotas/clients/{name}", put(set_client_quota))
+        .route("/admin/quotas/clients/{name}", delete(delete_client_quota))
+        .route("/admin/quotas/topics/{name}", put(set_topic_quota))
+        .route("/admin/quotas/topics/{name}", delete(delete_topic_quota))
+        .route("/admin/metadata/snapshot", post(snapshot_metadata))
+        .route("/admin/trash", get(get_all_trash))
+        .route("/admin/trash/{trash_id}/restore", post(restore_trash))
+        .route("/admin/backups", post(create_backup))
+        .route("/admin/backups", get(get_all_backups))
+        .route("/admin/topics/{name}/export", get(archive::export_topic))
+        .route("/admin/topics/import", post(archive::import_topic))
+        .route(
+            "/admin/topics/{name}/partitions/{partition_id}/segments",
+            get(get_segments),
+        )
+        .route(
+            "/admin/topics/{name}/partitions/{partition_id}/roll",
+            post(roll_segment),
+        )
+        .route(
+            "/admin/topics/{name}/partitions/{partition_id}/truncate",
+            post(truncate_partition),
+        )
+        .route(
+            "/admin/topics/{name}/partitions/{partition_id}/move",
+            post(move_partition),
+        )
+        .route("/admin/log-dirs", get(get_all_log_dirs))
+        .route("/metrics", get(metrics))
+        .route_layer(from_fn_with_state(app.clone(), auth::require_admin));
+
+    // Probes are added after the auth layer, orchestrators do not carry a token
+    Router::new()
+        .route("/topics", post(create_topic))
+        .route("/topics", get(get_all_topics_state))
+        .route("/topics/{name}/state", get(get_topic_state))
+        .route("/topics/{name}/config", put(alter_topic_config))
+        .route("/topics/{name}/partitions", put(alter_partitions))
+        .route("/topics/{name}/rename", post(rename_topic))
+        .route("/topics/{name}", delete(delete_topic))
+        .route("/topics/{name}/queue", get(get_queue_state))
+        .route("/topics/{name}/queue/receive", post(receive))
+        .route("/topics/{name}/queue/ack", post(ack))
+        .route("/topics/{name}/queue/nack", post(nack))
+        .route("/topics/records", post(produce))
+        .route("/topics/records", get(fetch))
+        .route("/topics/records/dead-letter", post(dead_letter))
+        .merge(admin)
+        .layer(map_response(move |response| {
+            request_too_large(response, max_request_size)
+        }))
+        .layer(DefaultBodyLimit::max(max_request_size))
+        .layer(from_fn_with_state(app.clone(), auth::authenticate))
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        .with_state(app)
 }
@@ -0,0 +1,12 @@
+#![feature(btree_cursors)]
+
+pub mod app;
+mod auth;
+pub mod config;
+pub mod dur;
+pub mod http;
+
+pub mod meta;
+mod metrics;
+mod queue;
+mod record_batch;
@@ -1,25 +1,52 @@
-#![feature(btree_cursors)]
-
-pub mod app;
-pub mod config;
-mod dur;
-pub mod http;
-
-mod meta;
-mod record_batch;
-use anyhow::Result;
-use app::App;
+use anyhow::{Context, Result};
 use clap::Parser;
-use config::Config;
-use http::HttpServer;
-use shared::{consts::DEFAULT_PORT, logging::set_up_logging};
+use server::app::App;
+use server::config::Config;
+use server::http::HttpServer;
+use shared::logging::{LogHandle, set_up_reloadable_logging};
+use tokio::select;
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
 
 #[derive(Parser, Debug)]
 #[command(name = "pigeon", version, author, about = "Run pegon server")]
 struct Cli {
+    /// TOML config file, values can be overridden by PIGEON_* environment variables
+    #[arg(long, env = "PIGEON_CONFIG")]
+    config: Option<String>,
+
+    /// Print the resolved config and exit
+    #[arg(long)]
+    print_config: bool,
+
+    #[arg(long)]
+    host: Option<String>,
+
     #[arg(long)]
     port: Option<u16>,
 
+    /// Require a bearer token on every request
+    #[arg(long)]
+    auth: bool,
+
+    /// Token that is always accepted with admin privileges, overrides `auth.admin_token` which
+    /// is also read from PIGEON_AUTH_ADMIN_TOKEN
+    #[arg(long)]
+    admin_token: Option<String>,
+
+    /// Enforce topic ACLs for principals without admin privileges
+    #[arg(long)]
+    acl: bool,
+
+    /// Header that identifies the principal of a request when authentication is disabled
+    #[arg(long)]
+    principal_header: Option<String>,
+
+    /// Rebuild the topics in the metadata from the topic directories on disk
+    #[arg(long)]
+    rebuild_metadata: bool,
+
     #[arg(long, short, action = clap::ArgAction::Count)]
     verbose: u8,
 
@@ -27,21 +54,109 @@ struct Cli {
     quiet: u8,
 }
 
+/// Loads the config file and environment, with the command line flags on top
+fn load_config(cli: &Cli) -> Result<Config> {
+    let mut config = Config::load(cli.config.as_deref())?;
+    if let Some(host) = &cli.host {
+        config.http.host = host.clone();
+    }
+    if let Some(port) = cli.port {
+        config.http.port = port;
+    }
+    if cli.auth {
+        config.auth.enabled = true;
+    }
+    if let Some(admin_token) = &cli.admin_token {
+        config.auth.admin_token = Some(admin_token.clone());
+    }
+    if cli.acl {
+        config.acl.enabled = true;
+    }
+    if let Some(principal_header) = &cli.principal_header {
+        config.acl.principal_header = Some(principal_header.clone());
+    }
+    config.metadata.rebuild = cli.rebuild_metadata;
+    config.validate()?;
+
+    Ok(config)
+}
+
+/// Reloads the config on SIGHUP, a config that fails to load is reported and ignored
+fn spawn_reload_task(cli: Cli, app: App, log: LogHandle) -> Result<JoinHandle<()>> {
+    let mut hangup = signal(SignalKind::hangup())?;
+
+    Ok(tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            info!("Received SIGHUP, reloading config");
+
+            let config = match load_config(&cli) {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("Failed to reload config, keeping the current config: {e:#}");
+                    continue;
+                }
+            };
+
+            if let Err(e) = log.set_filter(config.log.filter.as_deref()) {
+                warn!("Failed to apply log filter, keeping the current config: {e}");
+                continue;
+            }
+
+            let changes = app.write().await.reload_config(config);
+            if changes.is_empty() {
+                info!("Config reloaded without changes");
+            }
+        }
+    }))
+}
+
+/// Completes on the first SIGTERM or SIGINT, after marking the app as shutting down
+fn shutdown_signal(app: App) -> Result<impl Future<Output = ()>> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+
+    Ok(async move {
+        select! {
+            _ = terminate.recv() => info!("Received SIGTERM"),
+            _ = interrupt.recv() => info!("Received SIGINT"),
+        }
+
+        app.shutdown();
+    })
+}
+
 #[tokio::main]
 pub async fn main() -> Result<()> {
     let cli = Cli::parse();
-    set_up_logging(cli.verbose, cli.quiet)?;
+    let log = set_up_reloadable_logging(cli.verbose, cli.quiet)?;
+
+    let config = load_config(&cli)?;
+    if cli.print_config {
+        print!("{}", config.to_toml());
+        return Ok(());
+    }
+
+    if let Some(filter) = &config.log.filter {
+        log.set_filter(Some(filter)).map_err(anyhow::Error::msg)?;
+    }
 
-    let config = Config::default();
-    let app = App::load_from_disk(config)
-        .await
-        .expect("Failed to load app state");
+    let http = HttpServer::bind(&config.http.host, config.http.port).await?;
+    let app = http
+        .serve_while_loading(App::load_from_disk(config))
+        .await?
+        .context("Failed to load app state")?;
 
-    let port = cli.port.unwrap_or(DEFAULT_PORT);
+    app.spawn_retention_task().await;
+    app.spawn_metadata_snapshot_task().await;
+    app.spawn_disk_space_task().await;
+    spawn_reload_task(cli, app.clone(), log)?;
 
-    let http = HttpServer::new("127.0.0.1", port, app);
+    let shutdown = shutdown_signal(app.clone())?;
+    http.serve(app.clone(), shutdown).await?;
 
-    http.serve().await.expect("http server failed");
+    info!("Stopped accepting requests, syncing segments to disk");
+    app.write().await.sync().await?;
+    info!("Shutdown complete");
 
     Ok(())
 }
@@ -0,0 +1,13 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AddPartitionsEntry {
+    pub topic_id: u64,
+    /// The total amount of partitions of the topic after the change
+    pub partitions: u64,
+    /// The log directory of each added partition by partition id
+    #[serde(default)]
+    pub log_dirs: BTreeMap<u64, String>,
+}
@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+use shared::data::topic_config::TopicConfig;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AlterTopicConfigEntry {
+    pub topic_id: u64,
+    /// The complete config of the topic after the change
+    pub config: TopicConfig,
+}
@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+use shared::data::acl::{AclOperation, AclPattern};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CreateAclEntry {
+    pub acl_id: u64,
+    pub principal: String,
+    pub pattern: AclPattern,
+    pub operations: Vec<AclOperation>,
+}
@@ -1,8 +1,16 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
+use shared::data::topic_config::TopicConfig;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CreateTopicEntry {
     pub topic_id: u64,
     pub name: String,
     pub partitions: u64,
+    #[serde(default)]
+    pub config: TopicConfig,
+    /// The log directory of each partition by partition id
+    #[serde(default)]
+    pub log_dirs: BTreeMap<u64, String>,
 }
@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+use shared::data::quota::QuotaEntity;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeleteQuotaEntry {
+    pub entity: QuotaEntity,
+}
@@ -1,57 +1,170 @@
+pub mod add_partitions_entry;
+pub mod alter_topic_config_entry;
+pub mod create_acl_entry;
 pub mod create_topic_entry;
+pub mod delete_acl_entry;
+pub mod delete_quota_entry;
 pub mod delete_topic_entry;
+pub mod place_partitions_entry;
+pub mod recovery;
+pub mod rename_topic_entry;
+pub mod set_quota_entry;
+pub mod snapshot;
 use core::str;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
+use bytes::Bytes;
+
+use add_partitions_entry::AddPartitionsEntry;
+use alter_topic_config_entry::AlterTopicConfigEntry;
+use create_acl_entry::CreateAclEntry;
 use create_topic_entry::CreateTopicEntry;
+use delete_acl_entry::DeleteAclEntry;
+use delete_quota_entry::DeleteQuotaEntry;
 use delete_topic_entry::DeleteTopicEntry;
+use place_partitions_entry::PlacePartitionsEntry;
+use rename_topic_entry::RenameTopicEntry;
 use serde::{Deserialize, Serialize};
+use set_quota_entry::SetQuotaEntry;
+use shared::{
+    data::topic_config::TopicConfig,
+    state::{acl_state::AclState, quota_state::QuotaState},
+};
+use tracing::warn;
 
 use crate::dur::record::Record;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum MetadataEntry {
     CreateTopic(CreateTopicEntry),
     DeleteTopic(DeleteTopicEntry),
+    CreateAcl(CreateAclEntry),
+    DeleteAcl(DeleteAclEntry),
+    AlterTopicConfig(AlterTopicConfigEntry),
+    AddPartitions(AddPartitionsEntry),
+    RenameTopic(RenameTopicEntry),
+    PlacePartitions(PlacePartitionsEntry),
+    SetQuota(SetQuotaEntry),
+    DeleteQuota(DeleteQuotaEntry),
 }
 
-#[derive(Default, Debug)]
+#[derive(Serialize, Deserialize, Default, Debug)]
 pub struct Metadata {
     pub topics: HashMap<u64, TopicMetadata>,
+    pub acls: BTreeMap<u64, AclState>,
+    #[serde(default)]
+    pub quotas: Vec<QuotaState>,
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct TopicMetadata {
     pub topic_id: u64,
     pub name: String,
     pub partitions: u64,
+    pub config: TopicConfig,
+    /// The log directory of each partition, partitions without one are looked up on disk
+    #[serde(default)]
+    pub log_dirs: BTreeMap<u64, String>,
+}
+
+/// A record on the metadata topic that could not be decoded into a `MetadataEntry`
+#[derive(Debug)]
+pub struct InvalidEntry {
+    pub offset: u64,
+    pub reason: String,
+    pub value: Bytes,
 }
 
 impl Metadata {
-    pub fn from_records(records: Vec<Record>) -> Self {
-        let mut metadata = Metadata::default();
+    /// Replays the metadata entries in `records` on top of the current state, records that can
+    /// not be decoded are skipped and returned
+    pub fn apply_records(&mut self, records: Vec<Record>) -> Vec<InvalidEntry> {
+        let mut invalid = Vec::new();
 
         for record in records {
-            let value = str::from_utf8(&record.value).expect("Invalid UTF8 on metadata topic");
-
-            let entry = serde_json::from_str::<MetadataEntry>(value)
-                .expect("Invalid JSON on metadata topic");
+            let entry = str::from_utf8(&record.value)
+                .map_err(|e| format!("Invalid UTF8: {e}"))
+                .and_then(|value| {
+                    serde_json::from_str::<MetadataEntry>(value)
+                        .map_err(|e| format!("Invalid JSON: {e}"))
+                });
 
             match entry {
-                MetadataEntry::CreateTopic(entry) => {
-                    metadata.topics.insert(entry.topic_id, TopicMetadata {
-                        topic_id: entry.topic_id,
-                        name: entry.name,
-                        partitions: entry.partitions,
+                Ok(entry) => self.apply(entry),
+                Err(reason) => {
+                    warn!(
+                        "Skipping metadata entry at offset {}: {reason}",
+                        record.offset
+                    );
+                    invalid.push(InvalidEntry {
+                        offset: record.offset,
+                        reason,
+                        value: record.value,
                     });
                 }
-                MetadataEntry::DeleteTopic(entry) => {
-                    metadata.topics.remove(&entry.topic_id);
-                }
             }
         }
 
-        metadata
+        invalid
+    }
+
+    pub fn apply(&mut self, entry: MetadataEntry) {
+        match entry {
+            MetadataEntry::CreateTopic(entry) => {
+                self.topics.insert(entry.topic_id, TopicMetadata {
+                    topic_id: entry.topic_id,
+                    name: entry.name,
+                    partitions: entry.partitions,
+                    config: entry.config,
+                    log_dirs: entry.log_dirs,
+                });
+            }
+            MetadataEntry::DeleteTopic(entry) => {
+                self.topics.remove(&entry.topic_id);
+            }
+            MetadataEntry::CreateAcl(entry) => {
+                self.acls.insert(entry.acl_id, AclState {
+                    acl_id: entry.acl_id,
+                    principal: entry.principal,
+                    pattern: entry.pattern,
+                    operations: entry.operations,
+                });
+            }
+            MetadataEntry::DeleteAcl(entry) => {
+                self.acls.remove(&entry.acl_id);
+            }
+            MetadataEntry::AlterTopicConfig(entry) => {
+                if let Some(topic) = self.topics.get_mut(&entry.topic_id) {
+                    topic.config = entry.config;
+                }
+            }
+            MetadataEntry::AddPartitions(entry) => {
+                if let Some(topic) = self.topics.get_mut(&entry.topic_id) {
+                    topic.partitions = entry.partitions;
+                    topic.log_dirs.extend(entry.log_dirs);
+                }
+            }
+            MetadataEntry::RenameTopic(entry) => {
+                if let Some(topic) = self.topics.get_mut(&entry.topic_id) {
+                    topic.name = entry.name;
+                }
+            }
+            MetadataEntry::PlacePartitions(entry) => {
+                if let Some(topic) = self.topics.get_mut(&entry.topic_id) {
+                    topic.log_dirs.extend(entry.log_dirs);
+                }
+            }
+            MetadataEntry::SetQuota(entry) => {
+                self.quotas.retain(|quota| quota.entity != entry.entity);
+                self.quotas.push(QuotaState {
+                    entity: entry.entity,
+                    quota: entry.quota,
+                });
+            }
+            MetadataEntry::DeleteQuota(entry) => {
+                self.quotas.retain(|quota| quota.entity != entry.entity);
+            }
+        }
     }
 }
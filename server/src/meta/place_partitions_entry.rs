@@ -0,0 +1,10 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlacePartitionsEntry {
+    pub topic_id: u64,
+    /// The log directory of each placed or moved partition by partition id
+    pub log_dirs: BTreeMap<u64, String>,
+}
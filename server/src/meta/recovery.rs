@@ -0,0 +1,222 @@
+use std::{collections::BTreeMap, fmt::Display, io, path::PathBuf};
+
+use shared::data::topic_config::TopicConfig;
+use tokio::fs::{self, create_dir_all};
+use tracing::warn;
+
+use crate::config::Config;
+
+use super::{
+    InvalidEntry, Metadata, MetadataEntry, TopicMetadata, add_partitions_entry::AddPartitionsEntry,
+    create_topic_entry::CreateTopicEntry, delete_topic_entry::DeleteTopicEntry,
+};
+
+/// Everything that did not match up while loading the metadata during startup
+#[derive(Default, Debug)]
+pub struct RecoveryReport {
+    /// Metadata entries that could not be decoded, a copy is kept in the quarantine directory
+    pub invalid_entries: Vec<InvalidEntry>,
+    /// Paths in the topics directory that are not a topic id
+    pub unexpected_paths: Vec<PathBuf>,
+    /// Trash ids of topic directories without a topic in the metadata
+    pub trashed_topic_dirs: Vec<String>,
+    /// Topic directories without a topic that were left in place because entries were skipped
+    pub kept_topic_dirs: Vec<u64>,
+    /// Entries appended to the metadata to match the topic directories on disk
+    pub rebuilt_entries: Vec<MetadataEntry>,
+}
+
+impl RecoveryReport {
+    pub fn is_empty(&self) -> bool {
+        self.invalid_entries.is_empty()
+            && self.unexpected_paths.is_empty()
+            && self.trashed_topic_dirs.is_empty()
+            && self.kept_topic_dirs.is_empty()
+            && self.rebuilt_entries.is_empty()
+    }
+}
+
+impl Display for RecoveryReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Metadata recovery report:")?;
+
+        for entry in &self.invalid_entries {
+            writeln!(
+                f,
+                "  skipped entry at offset {}: {}",
+                entry.offset, entry.reason
+            )?;
+        }
+        for path in &self.unexpected_paths {
+            writeln!(f, "  ignored unexpected path {}", path.display())?;
+        }
+        for trash_id in &self.trashed_topic_dirs {
+            writeln!(
+                f,
+                "  moved directory of unknown topic to trash as {trash_id}"
+            )?;
+        }
+        for topic_id in &self.kept_topic_dirs {
+            writeln!(
+                f,
+                "  kept directory of unknown topic {topic_id}, start with --rebuild-metadata to recover it"
+            )?;
+        }
+        for entry in &self.rebuilt_entries {
+            match entry {
+                MetadataEntry::CreateTopic(entry) => writeln!(
+                    f,
+                    "  recovered topic {} as {} with {} partitions",
+                    entry.topic_id, entry.name, entry.partitions
+                )?,
+                MetadataEntry::AddPartitions(entry) => writeln!(
+                    f,
+                    "  recovered partitions of topic {}, now {} partitions",
+                    entry.topic_id, entry.partitions
+                )?,
+                MetadataEntry::DeleteTopic(entry) => {
+                    writeln!(f, "  removed topic {} without a directory", entry.topic_id)?
+                }
+                entry => writeln!(f, "  appended {entry:?}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Keeps a copy of entries that could not be decoded, so they can be inspected by hand
+pub async fn quarantine(config: &Config, entries: &[InvalidEntry]) -> io::Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    create_dir_all(config.quarantine_path()).await?;
+    for entry in entries {
+        fs::write(config.quarantine_entry_path(entry.offset), &entry.value).await?;
+    }
+
+    Ok(())
+}
+
+/// Finds the topic directories in every log directory and their partition count, paths that are
+/// not a topic id are added to `unexpected_paths`
+pub async fn scan_topic_dirs(
+    config: &Config,
+    unexpected_paths: &mut Vec<PathBuf>,
+) -> io::Result<BTreeMap<u64, u64>> {
+    let mut topics = BTreeMap::new();
+
+    for log_dir in config.all_log_dirs() {
+        let mut stream = match fs::read_dir(log_dir.topics_path()).await {
+            Ok(stream) => stream,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+
+        while let Some(entry) = stream.next_entry().await? {
+            let path = entry.path();
+
+            let Some(topic_id) = parse_id(&entry) else {
+                warn!("Ignoring unexpected path {}", path.display());
+                unexpected_paths.push(path);
+                continue;
+            };
+
+            let partitions = topics.entry(topic_id).or_insert(0);
+            match fs::read_dir(log_dir.partitions_path(topic_id)).await {
+                Ok(mut stream) => {
+                    while let Some(entry) = stream.next_entry().await? {
+                        match parse_id(&entry) {
+                            Some(partition_id) => *partitions = (*partitions).max(partition_id + 1),
+                            None => {
+                                warn!("Ignoring unexpected path {}", entry.path().display());
+                                unexpected_paths.push(entry.path());
+                            }
+                        }
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    Ok(topics)
+}
+
+fn parse_id(entry: &fs::DirEntry) -> Option<u64> {
+    entry.file_name().to_str()?.parse().ok()
+}
+
+impl Metadata {
+    /// Makes the topics match the topic directories found on disk, topics without a name in the
+    /// metadata are recovered as `recovered-<topic_id>`, with a suffix when a topic already has
+    /// that name. Returns the entries that persist the changes
+    pub fn rebuild(&mut self, topic_dirs: &BTreeMap<u64, u64>) -> Vec<MetadataEntry> {
+        let mut entries = Vec::new();
+
+        for (&topic_id, &partitions) in topic_dirs {
+            match self.topics.get_mut(&topic_id) {
+                Some(topic) if topic.partitions < partitions => {
+                    topic.partitions = partitions;
+                    entries.push(MetadataEntry::AddPartitions(AddPartitionsEntry {
+                        topic_id,
+                        partitions,
+                        log_dirs: BTreeMap::new(),
+                    }));
+                }
+                Some(_) => {}
+                None => {
+                    let name = match topic_id {
+                        0 => "__metadata".to_string(),
+                        topic_id => self.recovered_name(topic_id),
+                    };
+                    let partitions = partitions.max(1);
+
+                    self.topics.insert(topic_id, TopicMetadata {
+                        topic_id,
+                        name: name.clone(),
+                        partitions,
+                        config: TopicConfig::default(),
+                        log_dirs: BTreeMap::new(),
+                    });
+                    entries.push(MetadataEntry::CreateTopic(CreateTopicEntry {
+                        topic_id,
+                        name,
+                        partitions,
+                        config: TopicConfig::default(),
+                        log_dirs: BTreeMap::new(),
+                    }));
+                }
+            }
+        }
+
+        let missing = self
+            .topics
+            .keys()
+            .filter(|topic_id| !topic_dirs.contains_key(topic_id))
+            .copied()
+            .collect::<Vec<_>>();
+        for topic_id in missing {
+            self.topics.remove(&topic_id);
+            entries.push(MetadataEntry::DeleteTopic(DeleteTopicEntry { topic_id }));
+        }
+
+        entries
+    }
+
+    fn recovered_name(&self, topic_id: u64) -> String {
+        let base = format!("recovered-{topic_id}");
+        let in_use = |name: &str| self.topics.values().any(|topic| topic.name == name);
+
+        let mut name = base.clone();
+        let mut suffix = 1;
+        while in_use(&name) {
+            name = format!("{base}-{suffix}");
+            suffix += 1;
+        }
+
+        name
+    }
+}
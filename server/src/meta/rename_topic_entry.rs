@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RenameTopicEntry {
+    pub topic_id: u64,
+    pub name: String,
+}
@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+use shared::data::quota::{Quota, QuotaEntity};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SetQuotaEntry {
+    pub entity: QuotaEntity,
+    pub quota: Quota,
+}
@@ -0,0 +1,94 @@
+use std::io::{self, ErrorKind};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs::{self, create_dir_all};
+use tracing::{debug, warn};
+
+use crate::config::Config;
+
+use super::Metadata;
+
+/// The metadata state after applying all entries up to and including `offset`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MetadataSnapshot {
+    pub offset: u64,
+    pub metadata: Metadata,
+}
+
+impl MetadataSnapshot {
+    /// Writes the snapshot to a temporary file first, so a partially written snapshot is never
+    /// picked up during startup
+    pub async fn write(&self, config: &Config) -> io::Result<()> {
+        create_dir_all(config.snapshots_path()).await?;
+
+        let path = config.snapshot_path(self.offset);
+        let tmp_path = format!("{path}.tmp");
+
+        fs::write(
+            &tmp_path,
+            serde_json::to_vec(self).expect("serde_json to_vec failed"),
+        )
+        .await?;
+        fs::rename(&tmp_path, &path).await?;
+
+        debug!("Wrote metadata snapshot {path}");
+        Ok(())
+    }
+
+    /// Offsets of the snapshots on disk, ordered from oldest to newest
+    pub async fn offsets(config: &Config) -> io::Result<Vec<u64>> {
+        let mut offsets = Vec::new();
+
+        let mut stream = match fs::read_dir(config.snapshots_path()).await {
+            Ok(stream) => stream,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(offsets),
+            Err(e) => return Err(e),
+        };
+
+        while let Some(entry) = stream.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_none_or(|s| s != "json") {
+                continue;
+            }
+
+            match path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse::<u64>().ok())
+            {
+                Some(offset) => offsets.push(offset),
+                None => warn!("Ignoring unexpected snapshot file {}", path.display()),
+            }
+        }
+
+        offsets.sort();
+        Ok(offsets)
+    }
+
+    /// Loads the newest snapshot that can be read, unreadable snapshots are skipped
+    pub async fn load_latest(config: &Config) -> io::Result<Option<Self>> {
+        for offset in Self::offsets(config).await?.into_iter().rev() {
+            let bytes = fs::read(config.snapshot_path(offset)).await?;
+
+            match serde_json::from_slice::<Self>(&bytes) {
+                Ok(snapshot) => return Ok(Some(snapshot)),
+                Err(e) => warn!("Skipping unreadable metadata snapshot at offset {offset}: {e}"),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Removes all but the newest `keep` snapshots, returns the offsets of the remaining snapshots
+    pub async fn remove_old(config: &Config, keep: usize) -> io::Result<Vec<u64>> {
+        let mut offsets = Self::offsets(config).await?;
+        let remove = offsets.len().saturating_sub(keep);
+
+        for offset in offsets.drain(..remove) {
+            debug!("Removing metadata snapshot at offset {offset}");
+            fs::remove_file(config.snapshot_path(offset)).await?;
+        }
+
+        Ok(offsets)
+    }
+}
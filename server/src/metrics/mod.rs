@@ -0,0 +1,244 @@
+//! Process wide counters and histograms, exposed in the Prometheus text format on `GET /metrics`.
+//! Gauges that can be read from the current state are computed when scraped instead, see
+//! [`crate::app::AppLock::encode_metrics`]
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+/// Upper bounds in seconds of the latency histogram buckets
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0, 10.0, 30.0,
+];
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+#[derive(Default)]
+pub struct Metrics {
+    pub produce_requests: CounterVec,
+    pub produce_latency: HistogramVec,
+    pub fetch_requests: CounterVec,
+    pub fetch_latency: HistogramVec,
+    /// Long-polling fetches that returned because their timeout expired
+    pub fetch_timeouts: AtomicU64,
+    pub bytes_in: CounterVec,
+    pub bytes_out: CounterVec,
+    /// Produces rejected and fetches delayed by a quota
+    pub throttled_requests: CounterVec,
+    /// Segments whose offload to tiered storage failed, they are retried on the next run
+    pub offload_failures: AtomicU64,
+}
+
+impl Metrics {
+    pub fn encode(&self, writer: &mut MetricsWriter) {
+        self.produce_requests.encode(
+            writer,
+            "pigeon_produce_requests_total",
+            "Produce requests per topic",
+        );
+        self.produce_latency.encode(
+            writer,
+            "pigeon_produce_duration_seconds",
+            "Produce request latency per topic",
+        );
+        self.fetch_requests.encode(
+            writer,
+            "pigeon_fetch_requests_total",
+            "Fetch requests per topic",
+        );
+        self.fetch_latency.encode(
+            writer,
+            "pigeon_fetch_duration_seconds",
+            "Fetch request latency per topic, including time spent long-polling",
+        );
+
+        writer.header(
+            "pigeon_fetch_timeouts_total",
+            "Fetch requests that waited until their timeout",
+            "counter",
+        );
+        writer.sample(
+            "pigeon_fetch_timeouts_total",
+            &[],
+            self.fetch_timeouts.load(Ordering::Relaxed),
+        );
+
+        self.bytes_in.encode(
+            writer,
+            "pigeon_bytes_in_total",
+            "Bytes of records appended per topic",
+        );
+        self.bytes_out.encode(
+            writer,
+            "pigeon_bytes_out_total",
+            "Bytes of records returned by fetches per topic",
+        );
+        self.throttled_requests.encode(
+            writer,
+            "pigeon_throttled_requests_total",
+            "Produces rejected and fetches delayed by a quota per topic",
+        );
+
+        writer.header(
+            "pigeon_offload_failures_total",
+            "Segments that failed to offload to tiered storage",
+            "counter",
+        );
+        writer.sample(
+            "pigeon_offload_failures_total",
+            &[],
+            self.offload_failures.load(Ordering::Relaxed),
+        );
+    }
+}
+
+/// Counter with a `topic` label
+#[derive(Default)]
+pub struct CounterVec(Mutex<BTreeMap<String, u64>>);
+
+impl CounterVec {
+    pub fn inc(&self, topic: &str) {
+        self.inc_by(topic, 1);
+    }
+
+    pub fn inc_by(&self, topic: &str, value: u64) {
+        let mut counters = self.0.lock().unwrap();
+
+        match counters.get_mut(topic) {
+            Some(counter) => *counter += value,
+            None => {
+                counters.insert(topic.to_string(), value);
+            }
+        }
+    }
+
+    fn encode(&self, writer: &mut MetricsWriter, name: &str, help: &str) {
+        writer.header(name, help, "counter");
+
+        for (topic, value) in self.0.lock().unwrap().iter() {
+            writer.sample(name, &[("topic", topic)], value);
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+/// Latency histogram with a `topic` label
+#[derive(Default)]
+pub struct HistogramVec(Mutex<BTreeMap<String, Histogram>>);
+
+impl HistogramVec {
+    pub fn observe(&self, topic: &str, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let mut histograms = self.0.lock().unwrap();
+
+        if !histograms.contains_key(topic) {
+            histograms.insert(topic.to_string(), Histogram::default());
+        }
+        let histogram = histograms.get_mut(topic).unwrap();
+
+        for (bucket, bound) in histogram.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        histogram.sum += seconds;
+        histogram.count += 1;
+    }
+
+    fn encode(&self, writer: &mut MetricsWriter, name: &str, help: &str) {
+        writer.header(name, help, "histogram");
+
+        let bucket_name = format!("{name}_bucket");
+        let sum_name = format!("{name}_sum");
+        let count_name = format!("{name}_count");
+
+        for (topic, histogram) in self.0.lock().unwrap().iter() {
+            for (count, bound) in histogram.buckets.iter().zip(LATENCY_BUCKETS) {
+                let bound = bound.to_string();
+                writer.sample(&bucket_name, &[("topic", topic), ("le", &bound)], count);
+            }
+            writer.sample(
+                &bucket_name,
+                &[("topic", topic), ("le", "+Inf")],
+                histogram.count,
+            );
+            writer.sample(&sum_name, &[("topic", topic)], histogram.sum);
+            writer.sample(&count_name, &[("topic", topic)], histogram.count);
+        }
+    }
+}
+
+/// Builds a response in the Prometheus text exposition format
+#[derive(Default)]
+pub struct MetricsWriter(String);
+
+impl MetricsWriter {
+    pub fn header(&mut self, name: &str, help: &str, kind: &str) {
+        let _ = writeln!(self.0, "# HELP {name} {help}");
+        let _ = writeln!(self.0, "# TYPE {name} {kind}");
+    }
+
+    pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl std::fmt::Display) {
+        self.0.push_str(name);
+
+        if !labels.is_empty() {
+            self.0.push('{');
+            for (i, (label, value)) in labels.iter().enumerate() {
+                if i > 0 {
+                    self.0.push(',');
+                }
+                let value = value
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n");
+                let _ = write!(self.0, "{label}=\"{value}\"");
+            }
+            self.0.push('}');
+        }
+
+        let _ = writeln!(self.0, " {value}");
+    }
+
+    pub fn finish(self) -> String {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{CounterVec, HistogramVec, MetricsWriter};
+
+    #[test]
+    fn metrics_text_format() {
+        let counter = CounterVec::default();
+        counter.inc("foo");
+        counter.inc_by("foo", 2);
+        counter.inc("b\"ar");
+
+        let histogram = HistogramVec::default();
+        histogram.observe("foo", Duration::from_millis(20));
+
+        let mut writer = MetricsWriter::default();
+        counter.encode(&mut writer, "requests_total", "Requests");
+        histogram.encode(&mut writer, "latency_seconds", "Latency");
+        let text = writer.finish();
+
+        assert!(text.contains("# TYPE requests_total counter\n"));
+        assert!(text.contains("requests_total{topic=\"foo\"} 3\n"));
+        assert!(text.contains("requests_total{topic=\"b\\\"ar\"} 1\n"));
+        assert!(text.contains("latency_seconds_bucket{topic=\"foo\",le=\"0.01\"} 0\n"));
+        assert!(text.contains("latency_seconds_bucket{topic=\"foo\",le=\"0.025\"} 1\n"));
+        assert!(text.contains("latency_seconds_bucket{topic=\"foo\",le=\"+Inf\"} 1\n"));
+        assert!(text.contains("latency_seconds_count{topic=\"foo\"} 1\n"));
+    }
+}
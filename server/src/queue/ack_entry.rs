@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AckEntry {
+    pub topic_id: u64,
+    pub partition_id: u64,
+    pub offset: u64,
+}
@@ -0,0 +1,222 @@
+pub mod ack_entry;
+pub mod delete_queue_entry;
+pub mod nack_entry;
+pub mod receive_entry;
+pub mod snapshot_entry;
+
+use core::str;
+
+use std::collections::{BTreeMap, HashMap};
+
+use ack_entry::AckEntry;
+use delete_queue_entry::DeleteQueueEntry;
+use nack_entry::NackEntry;
+use receive_entry::ReceiveEntry;
+use serde::{Deserialize, Serialize};
+use shared::data::{lease::Lease, timestamp::Timestamp};
+use snapshot_entry::{CursorEntry, SnapshotEntry};
+use tracing::warn;
+
+use crate::dur::record::Record;
+
+pub const QUEUES_TOPIC: &str = "__queues";
+
+/// Upper bound of visibility timeouts and nack delays, one day
+pub const MAX_QUEUE_DELAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum QueueEntry {
+    Receive(ReceiveEntry),
+    Ack(AckEntry),
+    Nack(NackEntry),
+    DeleteQueue(DeleteQueueEntry),
+    Snapshot(SnapshotEntry),
+}
+
+/// The queues of the topics that were received from as a queue, keyed by topic id
+#[derive(Default, Debug)]
+pub struct Queues {
+    pub queues: HashMap<u64, Queue>,
+    next_lease_id: u64,
+    /// Entries applied since the last snapshot, or since the start of `__queues`
+    entries_since_snapshot: u64,
+}
+
+#[derive(Default, Debug)]
+pub struct Queue {
+    /// Per partition, the offset after the last message received from it
+    pub cursors: HashMap<u64, u64>,
+    /// Received messages that were not acked yet, by partition and offset
+    pub leases: BTreeMap<(u64, u64), MessageLease>,
+}
+
+#[derive(Debug)]
+pub struct MessageLease {
+    pub lease_id: u64,
+    pub deliveries: u64,
+    pub visible_at: Timestamp,
+}
+
+/// A message handed to a worker by a receive
+#[derive(Debug)]
+pub struct QueueMessage {
+    pub lease: Lease,
+    pub deliveries: u64,
+    pub visible_at: Timestamp,
+    pub record: Record,
+}
+
+impl Queues {
+    pub fn from_records(records: Vec<Record>) -> Self {
+        let mut queues = Queues::default();
+
+        for record in records {
+            let entry = str::from_utf8(&record.value)
+                .map_err(|e| format!("Invalid UTF8: {e}"))
+                .and_then(|value| {
+                    serde_json::from_str::<QueueEntry>(value)
+                        .map_err(|e| format!("Invalid JSON: {e}"))
+                });
+
+            match entry {
+                Ok(entry) => queues.apply(entry),
+                Err(reason) => warn!("Skipping queue entry at offset {}: {reason}", record.offset),
+            }
+        }
+
+        queues
+    }
+
+    pub fn apply(&mut self, entry: QueueEntry) {
+        self.entries_since_snapshot += 1;
+
+        match entry {
+            QueueEntry::Receive(entry) => {
+                let queue = self.queues.entry(entry.topic_id).or_default();
+                let cursor = queue.cursors.entry(entry.partition_id).or_default();
+                *cursor = (*cursor).max(entry.offset + 1);
+
+                queue
+                    .leases
+                    .insert((entry.partition_id, entry.offset), MessageLease {
+                        lease_id: entry.lease_id,
+                        deliveries: entry.deliveries,
+                        visible_at: entry.visible_at,
+                    });
+                self.next_lease_id = self.next_lease_id.max(entry.lease_id + 1);
+            }
+            QueueEntry::Ack(entry) => {
+                if let Some(queue) = self.queues.get_mut(&entry.topic_id) {
+                    queue.leases.remove(&(entry.partition_id, entry.offset));
+                }
+            }
+            QueueEntry::Nack(entry) => {
+                if let Some(lease) = self
+                    .queues
+                    .get_mut(&entry.topic_id)
+                    .and_then(|queue| queue.leases.get_mut(&(entry.partition_id, entry.offset)))
+                {
+                    lease.visible_at = entry.visible_at;
+                }
+            }
+            QueueEntry::DeleteQueue(entry) => {
+                self.queues.remove(&entry.topic_id);
+            }
+            QueueEntry::Snapshot(entry) => {
+                *self = Queues::default();
+
+                for cursor in entry.cursors {
+                    self.queues
+                        .entry(cursor.topic_id)
+                        .or_default()
+                        .cursors
+                        .insert(cursor.partition_id, cursor.offset);
+                }
+                for lease in entry.leases {
+                    self.apply(QueueEntry::Receive(lease));
+                }
+
+                self.next_lease_id = self.next_lease_id.max(entry.next_lease_id);
+                self.entries_since_snapshot = 0;
+            }
+        }
+    }
+
+    pub fn entries_since_snapshot(&self) -> u64 {
+        self.entries_since_snapshot
+    }
+
+    /// An entry that restores the current state of every queue
+    pub fn snapshot(&self) -> SnapshotEntry {
+        let mut cursors = Vec::new();
+        let mut leases = Vec::new();
+
+        for (&topic_id, queue) in &self.queues {
+            cursors.extend(
+                queue
+                    .cursors
+                    .iter()
+                    .map(|(&partition_id, &offset)| CursorEntry {
+                        topic_id,
+                        partition_id,
+                        offset,
+                    }),
+            );
+            leases.extend(queue.leases.iter().map(|(&(partition_id, offset), lease)| {
+                ReceiveEntry {
+                    topic_id,
+                    partition_id,
+                    offset,
+                    lease_id: lease.lease_id,
+                    deliveries: lease.deliveries,
+                    visible_at: lease.visible_at,
+                }
+            }));
+        }
+
+        SnapshotEntry {
+            next_lease_id: self.next_lease_id,
+            cursors,
+            leases,
+        }
+    }
+
+    pub fn next_lease_id(&self) -> u64 {
+        self.next_lease_id
+    }
+
+    /// The offset of the next message of the partition that was never received
+    pub fn cursor(&self, topic_id: u64, partition_id: u64) -> u64 {
+        self.queues
+            .get(&topic_id)
+            .and_then(|queue| queue.cursors.get(&partition_id))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Whether the lease is the latest delivery of a message that was not acked yet
+    pub fn is_leased(&self, topic_id: u64, lease: &Lease) -> bool {
+        self.queues
+            .get(&topic_id)
+            .and_then(|queue| queue.leases.get(&(lease.partition_id, lease.offset)))
+            .is_some_and(|message_lease| message_lease.lease_id == lease.lease_id)
+    }
+
+    /// Received messages whose visibility timeout ran out, with their delivery count, oldest
+    /// offsets first
+    pub fn visible(&self, topic_id: u64, now: Timestamp) -> Vec<(u64, u64, u64)> {
+        self.queues
+            .get(&topic_id)
+            .map(|queue| {
+                queue
+                    .leases
+                    .iter()
+                    .filter(|(_, lease)| lease.visible_at.as_micros() <= now.as_micros())
+                    .map(|((partition_id, offset), lease)| {
+                        (*partition_id, *offset, lease.deliveries)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+use shared::data::timestamp::Timestamp;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NackEntry {
+    pub topic_id: u64,
+    pub partition_id: u64,
+    pub offset: u64,
+    pub visible_at: Timestamp,
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+use shared::data::timestamp::Timestamp;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReceiveEntry {
+    pub topic_id: u64,
+    pub partition_id: u64,
+    pub offset: u64,
+    pub lease_id: u64,
+    pub deliveries: u64,
+    pub visible_at: Timestamp,
+}
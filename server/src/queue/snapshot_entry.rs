@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+use super::receive_entry::ReceiveEntry;
+
+/// The state of every queue, replaces the state built from the entries before it
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SnapshotEntry {
+    pub next_lease_id: u64,
+    pub cursors: Vec<CursorEntry>,
+    /// The messages that were not acked yet, as their latest receive
+    pub leases: Vec<ReceiveEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CursorEntry {
+    pub topic_id: u64,
+    pub partition_id: u64,
+    pub offset: u64,
+}
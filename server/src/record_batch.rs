@@ -1,7 +1,10 @@
 use std::collections::HashMap;
 
 use shared::{
-    data::encoding::{self, Encoding},
+    data::{
+        encoding::{self, Encoding},
+        timestamp::Timestamp,
+    },
     response::record_response::{FetchResponse, RecordResponse},
 };
 
@@ -16,6 +19,9 @@ pub struct RecordBatch {
     total_bytes: usize,
 
     records: HashMap<u64, HashMap<u64, Vec<Record>>>,
+    /// Partitions whose next record is not deliverable yet, with the time it becomes deliverable.
+    /// Later records of these partitions are left out so partitions are consumed in order
+    held_back: HashMap<(u64, u64), Timestamp>,
 }
 
 impl RecordBatch {
@@ -25,6 +31,7 @@ impl RecordBatch {
             total_bytes: 0,
             min_bytes,
             records: HashMap::new(),
+            held_back: HashMap::new(),
         }
     }
 
@@ -52,6 +59,48 @@ impl RecordBatch {
         partition_records.append(&mut records);
     }
 
+    /// Removes the records of a partition from the batch
+    pub fn take_records(&mut self, topic_id: u64, partition_id: u64) -> Vec<Record> {
+        let records = self
+            .records
+            .get_mut(&topic_id)
+            .and_then(|partitions| partitions.remove(&partition_id))
+            .unwrap_or_default();
+
+        self.total_bytes -= records.iter().map(Record::size).sum::<usize>();
+        records
+    }
+
+    pub fn hold_back(&mut self, topic_id: u64, partition_id: u64, deliver_at: Timestamp) {
+        self.held_back
+            .entry((topic_id, partition_id))
+            .or_insert(deliver_at);
+    }
+
+    pub fn is_held_back(&self, topic_id: u64, partition_id: u64) -> bool {
+        self.held_back.contains_key(&(topic_id, partition_id))
+    }
+
+    /// The earliest time a held back record becomes deliverable
+    pub fn next_delivery(&self) -> Option<Timestamp> {
+        self.held_back
+            .values()
+            .min_by_key(|deliver_at| deliver_at.as_micros())
+            .copied()
+    }
+
+    /// Bytes in the batch per topic id
+    pub fn topic_bytes(&self) -> impl Iterator<Item = (u64, usize)> {
+        self.records.iter().map(|(topic_id, partitions)| {
+            let bytes = partitions
+                .values()
+                .flatten()
+                .map(Record::size)
+                .sum::<usize>();
+            (*topic_id, bytes)
+        })
+    }
+
     pub fn to_response(&self, encoding: Encoding) -> Result<FetchResponse, encoding::Error> {
         let mut records = Vec::new();
         for (topic_id, partition) in &self.records {
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::lease::Lease;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AckCommand {
+    pub leases: Vec<Lease>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NackCommand {
+    pub leases: Vec<Lease>,
+    /// How long the messages stay invisible before they are redelivered, immediately when unset.
+    /// At most one day
+    #[serde(default)]
+    pub delay_ms: Option<u64>,
+}
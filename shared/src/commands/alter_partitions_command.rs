@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct AlterPartitionsCommand {
+    /// The new total amount of partitions, this can not be lower than the current amount
+    pub partitions: u64,
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::topic_config::{TopicConfig, TopicConfigKey};
+
+#[derive(Serialize, Deserialize)]
+pub struct AlterTopicConfigCommand {
+    /// Values to change, unset values keep their current setting
+    pub config: TopicConfig,
+    /// Values to unset so the broker default applies again, applied before `config`
+    #[serde(default)]
+    pub reset: Vec<TopicConfigKey>,
+}
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::acl::{AclOperation, AclPattern};
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateAclCommand {
+    /// Name of the principal, or `*` to match any principal
+    pub principal: String,
+    pub pattern: AclPattern,
+    pub operations: Vec<AclOperation>,
+}
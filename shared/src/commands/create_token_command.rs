@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateTokenCommand {
+    pub name: String,
+    pub admin: bool,
+}
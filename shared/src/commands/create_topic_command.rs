@@ -1,8 +1,12 @@
 use serde::{Deserialize, Serialize};
 
+use crate::data::topic_config::TopicConfig;
+
 #[derive(Serialize, Deserialize)]
 pub struct CreateTopicCommand {
     pub topic_id: Option<u64>,
     pub name: String,
     pub partitions: Option<u64>,
+    #[serde(default)]
+    pub config: TopicConfig,
 }
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::identifier::Identifier;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeadLetterCommand {
+    pub topic: Identifier,
+    pub partition_id: u64,
+    pub offset: u64,
+    /// Why the record could not be processed
+    pub error: String,
+    /// How often processing the record was attempted
+    pub attempts: u64,
+}
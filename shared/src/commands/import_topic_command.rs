@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+/// Query parameters of an import, the archive itself is sent as the request body
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ImportTopicCommand {
+    /// Name of the created topic, defaults to the name in the archive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Keep the original offsets and timestamps instead of appending as new records
+    #[serde(default)]
+    pub keep_offsets: bool,
+}
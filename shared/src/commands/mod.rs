@@ -1,3 +1,15 @@
+pub mod ack_command;
+pub mod alter_partitions_command;
+pub mod alter_topic_config_command;
+pub mod create_acl_command;
+pub mod create_token_command;
 pub mod create_topic_command;
+pub mod dead_letter_command;
 pub mod fetch_command;
+pub mod import_topic_command;
+pub mod move_partition_command;
 pub mod produce_command;
+pub mod receive_command;
+pub mod rename_topic_command;
+pub mod restore_trash_command;
+pub mod truncate_partition_command;
@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MovePartitionCommand {
+    /// One of the configured log directories
+    pub log_dir: String,
+}
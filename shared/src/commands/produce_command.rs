@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::data::{encoding::Encoding, identifier::Identifier};
+use crate::data::{encoding::Encoding, identifier::Identifier, timestamp::Timestamp};
 
 #[derive(Serialize, Deserialize)]
 pub struct ProduceCommand {
@@ -9,7 +9,15 @@ pub struct ProduceCommand {
     pub key: String,
     pub value: String,
     pub encoding: Encoding,
+    /// Names starting with `__` are reserved, only the delivery headers can be set
     pub headers: Option<Vec<ProduceHeaderCommand>>,
+    /// The record is no longer returned by fetches from this time on
+    #[serde(default)]
+    pub expires_at: Option<Timestamp>,
+    /// The record, and the records after it in the partition, are held back until this time. At
+    /// most `limits.max_deliver_delay_ms` of the broker in the future
+    #[serde(default)]
+    pub deliver_at: Option<Timestamp>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::encoding::Encoding;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceiveCommand {
+    pub encoding: Encoding,
+    pub max_messages: usize,
+    /// Uses the `queue.visibility_timeout_ms` of the broker when unset, at most one day
+    #[serde(default)]
+    pub visibility_timeout_ms: Option<u64>,
+}
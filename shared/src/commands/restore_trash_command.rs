@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RestoreTrashCommand {
+    /// Name of the recreated topic, defaults to the name the topic had before it was deleted
+    #[serde(default)]
+    pub name: Option<String>,
+}
@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TruncatePartitionCommand {
+    /// Segments that only contain records before this offset are removed
+    pub offset: u64,
+}
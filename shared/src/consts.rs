@@ -1 +1,25 @@
 pub const DEFAULT_PORT: u16 = 6394;
+
+/// Headers starting with this are reserved for the broker, producers can only set the delivery
+/// headers below
+pub const RESERVED_HEADER_PREFIX: &str = "__";
+
+/// Reserved record header holding the time in microseconds after which a record is no longer
+/// returned by fetches
+pub const EXPIRES_AT_HEADER: &str = "__expires_at";
+
+/// Reserved record header holding the time in microseconds before which a record is held back
+/// from fetches
+pub const DELIVER_AT_HEADER: &str = "__deliver_at";
+
+/// Appended to the name of a topic to get the name of its dead-letter topic
+pub const DEAD_LETTER_TOPIC_SUFFIX: &str = ".dlq";
+
+/// Reserved record headers added to records moved to a dead-letter topic, holding the name of the
+/// topic, partition and offset the record was moved from, why it could not be processed and how
+/// often that was attempted
+pub const DEAD_LETTER_TOPIC_HEADER: &str = "__dlq_topic";
+pub const DEAD_LETTER_PARTITION_HEADER: &str = "__dlq_partition";
+pub const DEAD_LETTER_OFFSET_HEADER: &str = "__dlq_offset";
+pub const DEAD_LETTER_ERROR_HEADER: &str = "__dlq_error";
+pub const DEAD_LETTER_ATTEMPTS_HEADER: &str = "__dlq_attempts";
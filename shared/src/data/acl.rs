@@ -0,0 +1,118 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AclOperation {
+    Produce,
+    Fetch,
+    Create,
+    Delete,
+    Describe,
+    Alter,
+}
+
+/// Selects the topics an ACL applies to
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", content = "value")]
+pub enum AclPattern {
+    /// Matches a single topic by its exact name
+    Literal(String),
+    /// Matches every topic whose name starts with the prefix
+    Prefixed(String),
+    /// Matches every topic
+    Any,
+}
+
+/// Principal name that matches every principal
+pub const ANY_PRINCIPAL: &str = "*";
+
+impl AclPattern {
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            AclPattern::Literal(literal) => literal == name,
+            AclPattern::Prefixed(prefix) => name.starts_with(prefix),
+            AclPattern::Any => true,
+        }
+    }
+}
+
+impl Display for AclPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AclPattern::Literal(literal) => write!(f, "{literal}"),
+            AclPattern::Prefixed(prefix) => write!(f, "{prefix}*"),
+            AclPattern::Any => write!(f, "*"),
+        }
+    }
+}
+
+impl FromStr for AclPattern {
+    type Err = String;
+
+    /// Parses `*` as any topic, `foo*` as a prefix and everything else as a literal name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("pattern can not be empty".to_string());
+        }
+
+        Ok(match s.strip_suffix('*') {
+            Some("") => AclPattern::Any,
+            Some(prefix) => AclPattern::Prefixed(prefix.to_string()),
+            None => AclPattern::Literal(s.to_string()),
+        })
+    }
+}
+
+impl Display for AclOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operation = match self {
+            AclOperation::Produce => "produce",
+            AclOperation::Fetch => "fetch",
+            AclOperation::Create => "create",
+            AclOperation::Delete => "delete",
+            AclOperation::Describe => "describe",
+            AclOperation::Alter => "alter",
+        };
+
+        write!(f, "{operation}")
+    }
+}
+
+impl FromStr for AclOperation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "produce" => AclOperation::Produce,
+            "fetch" => AclOperation::Fetch,
+            "create" => AclOperation::Create,
+            "delete" => AclOperation::Delete,
+            "describe" => AclOperation::Describe,
+            "alter" => AclOperation::Alter,
+            _ => return Err(format!("unknown operation {s}")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AclPattern;
+
+    #[test]
+    fn parse_patterns() {
+        assert_eq!("*".parse(), Ok(AclPattern::Any));
+        assert_eq!("foo*".parse(), Ok(AclPattern::Prefixed("foo".to_string())));
+        assert_eq!("foo".parse(), Ok(AclPattern::Literal("foo".to_string())));
+        assert!("".parse::<AclPattern>().is_err());
+    }
+
+    #[test]
+    fn pattern_matches() {
+        assert!(AclPattern::Any.matches("foo"));
+        assert!(AclPattern::Prefixed("foo.".to_string()).matches("foo.bar"));
+        assert!(!AclPattern::Prefixed("foo.".to_string()).matches("bar.foo"));
+        assert!(AclPattern::Literal("foo".to_string()).matches("foo"));
+        assert!(!AclPattern::Literal("foo".to_string()).matches("foobar"));
+    }
+}
@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+/// Identifies one delivery of a queue message, a redelivery gets a new lease id so a worker whose
+/// visibility timeout ran out can no longer ack or nack the message
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct Lease {
+    pub partition_id: u64,
+    pub offset: u64,
+    pub lease_id: u64,
+}
@@ -1,5 +1,10 @@
+pub mod acl;
 pub mod encoding;
 pub mod identifier;
+pub mod lease;
 pub mod offset_selection;
 pub mod partitioner;
+pub mod quota;
 pub mod timestamp;
+pub mod topic_archive;
+pub mod topic_config;
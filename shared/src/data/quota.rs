@@ -0,0 +1,51 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// Client or topic name of the quota that applies to every client or topic without its own quota
+pub const DEFAULT_QUOTA_NAME: &str = "*";
+
+/// Who a quota applies to, clients are identified by their principal name
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(tag = "type", content = "name")]
+pub enum QuotaEntity {
+    Client(String),
+    Topic(String),
+}
+
+impl QuotaEntity {
+    pub fn name(&self) -> &str {
+        match self {
+            QuotaEntity::Client(name) | QuotaEntity::Topic(name) => name,
+        }
+    }
+
+    /// The entity whose quota applies when this one has none
+    pub fn default_entity(&self) -> QuotaEntity {
+        match self {
+            QuotaEntity::Client(_) => QuotaEntity::Client(DEFAULT_QUOTA_NAME.to_string()),
+            QuotaEntity::Topic(_) => QuotaEntity::Topic(DEFAULT_QUOTA_NAME.to_string()),
+        }
+    }
+}
+
+impl Display for QuotaEntity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuotaEntity::Client(name) => write!(f, "client {name}"),
+            QuotaEntity::Topic(name) => write!(f, "topic {name}"),
+        }
+    }
+}
+
+/// Rates a client or topic may not exceed, unset rates are unlimited
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Quota {
+    /// Bytes of records produced per second
+    pub produce_bytes_per_sec: Option<u64>,
+    /// Bytes of records fetched per second
+    pub fetch_bytes_per_sec: Option<u64>,
+    /// Produce and fetch requests per second
+    pub requests_per_sec: Option<u64>,
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::{encoding::Encoding, topic_config::TopicConfig};
+
+/// Keys, values and header values in an archive are always base64 encoded
+pub const ARCHIVE_ENCODING: Encoding = Encoding::B64;
+
+/// First line of a topic archive, every following line is a
+/// [`crate::response::record_response::RecordResponse`] encoded with [`ARCHIVE_ENCODING`]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TopicArchiveHeader {
+    pub name: String,
+    pub partitions: u64,
+    pub config: TopicConfig,
+}
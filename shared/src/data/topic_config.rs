@@ -0,0 +1,137 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Per topic overrides of the broker configuration, unset values fall back to the broker default
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct TopicConfig {
+    /// Size in bytes after which a new segment is rolled
+    pub segment_size: Option<u64>,
+    /// Closed segments whose newest record is older than this are removed
+    pub retention_ms: Option<u64>,
+    /// Oldest closed segments are removed while a partition is larger than this many bytes
+    pub retention_bytes: Option<u64>,
+    /// Maximum size in bytes of a single record, including key and headers
+    pub max_record_size: Option<u64>,
+    pub cleanup_policy: Option<CleanupPolicy>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CleanupPolicy {
+    /// Segments are removed once they fall outside of the retention limits
+    #[default]
+    Delete,
+    /// Segments are never removed by retention, records are kept forever
+    #[serde(alias = "Compact")]
+    Retain,
+}
+
+/// Names a value of a [`TopicConfig`], used to return it to the broker default
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TopicConfigKey {
+    SegmentSize,
+    RetentionMs,
+    RetentionBytes,
+    MaxRecordSize,
+    CleanupPolicy,
+}
+
+impl FromStr for TopicConfigKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "segment_size" => Ok(TopicConfigKey::SegmentSize),
+            "retention_ms" => Ok(TopicConfigKey::RetentionMs),
+            "retention_bytes" => Ok(TopicConfigKey::RetentionBytes),
+            "max_record_size" => Ok(TopicConfigKey::MaxRecordSize),
+            "cleanup_policy" => Ok(TopicConfigKey::CleanupPolicy),
+            _ => Err(format!("Unknown topic config key {s}")),
+        }
+    }
+}
+
+impl TopicConfig {
+    /// Returns a copy of self with every value that is set in `other` overridden
+    pub fn merge(&self, other: &TopicConfig) -> TopicConfig {
+        TopicConfig {
+            segment_size: other.segment_size.or(self.segment_size),
+            retention_ms: other.retention_ms.or(self.retention_ms),
+            retention_bytes: other.retention_bytes.or(self.retention_bytes),
+            max_record_size: other.max_record_size.or(self.max_record_size),
+            cleanup_policy: other.cleanup_policy.or(self.cleanup_policy),
+        }
+    }
+
+    /// Returns a copy of self with the values of `keys` unset, so the broker default applies
+    pub fn reset(&self, keys: &[TopicConfigKey]) -> TopicConfig {
+        let mut config = self.clone();
+
+        for key in keys {
+            match key {
+                TopicConfigKey::SegmentSize => config.segment_size = None,
+                TopicConfigKey::RetentionMs => config.retention_ms = None,
+                TopicConfigKey::RetentionBytes => config.retention_bytes = None,
+                TopicConfigKey::MaxRecordSize => config.max_record_size = None,
+                TopicConfigKey::CleanupPolicy => config.cleanup_policy = None,
+            }
+        }
+
+        config
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CleanupPolicy, TopicConfig, TopicConfigKey};
+
+    #[test]
+    fn merge_overrides_set_values() {
+        let base = TopicConfig {
+            segment_size: Some(10),
+            retention_ms: Some(20),
+            ..Default::default()
+        };
+
+        let merged = base.merge(&TopicConfig {
+            retention_ms: Some(30),
+            cleanup_policy: Some(CleanupPolicy::Retain),
+            ..Default::default()
+        });
+
+        assert_eq!(merged, TopicConfig {
+            segment_size: Some(10),
+            retention_ms: Some(30),
+            cleanup_policy: Some(CleanupPolicy::Retain),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn reset_unsets_values() {
+        let base = TopicConfig {
+            segment_size: Some(10),
+            retention_ms: Some(20),
+            cleanup_policy: Some(CleanupPolicy::Retain),
+            ..Default::default()
+        };
+
+        let reset = base.reset(&[TopicConfigKey::RetentionMs, TopicConfigKey::CleanupPolicy]);
+
+        assert_eq!(reset, TopicConfig {
+            segment_size: Some(10),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn deserialize_legacy_compact_policy() {
+        let config = serde_json::from_str::<TopicConfig>(
+            r#"{"cleanup_policy":"Compact","compression":null}"#,
+        )
+        .expect("Failed to deserialize");
+
+        assert_eq!(config.cleanup_policy, Some(CleanupPolicy::Retain));
+    }
+}
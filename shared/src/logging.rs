@@ -1,7 +1,8 @@
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::{
-    EnvFilter, fmt,
+    EnvFilter, Registry, fmt,
     layer::SubscriberExt,
+    reload,
     util::{SubscriberInitExt, TryInitError},
 };
 
@@ -16,10 +17,14 @@ fn get_default_loglevel(verbose: u8, quiet: u8) -> LevelFilter {
     }
 }
 
+fn default_filter(default_level: LevelFilter) -> EnvFilter {
+    EnvFilter::builder()
+        .with_default_directive(default_level.into())
+        .from_env_lossy()
+}
+
 pub fn set_up_logging(verbose: u8, quiet: u8) -> Result<(), TryInitError> {
-    let filter = EnvFilter::builder()
-        .with_default_directive(get_default_loglevel(verbose, quiet).into())
-        .from_env_lossy();
+    let filter = default_filter(get_default_loglevel(verbose, quiet));
 
     // Use the tracing subscriber `Registry`, or any other subscriber
     // that impls `LookupSpan`
@@ -28,3 +33,38 @@ pub fn set_up_logging(verbose: u8, quiet: u8) -> Result<(), TryInitError> {
         .with(fmt::Layer::default())
         .try_init()
 }
+
+/// Changes the log filter of a running process, see `set_up_reloadable_logging`
+pub struct LogHandle {
+    default_level: LevelFilter,
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+impl LogHandle {
+    /// Replaces the filter with `directives`, or with the filter from the verbosity flags and
+    /// `RUST_LOG` when unset
+    pub fn set_filter(&self, directives: Option<&str>) -> Result<(), String> {
+        let filter = match directives {
+            Some(directives) => EnvFilter::try_new(directives).map_err(|e| e.to_string())?,
+            None => default_filter(self.default_level),
+        };
+
+        self.handle.reload(filter).map_err(|e| e.to_string())
+    }
+}
+
+/// Same as `set_up_logging`, but the filter can be changed afterwards through the returned handle
+pub fn set_up_reloadable_logging(verbose: u8, quiet: u8) -> Result<LogHandle, TryInitError> {
+    let default_level = get_default_loglevel(verbose, quiet);
+    let (filter, handle) = reload::Layer::new(default_filter(default_level));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::Layer::default())
+        .try_init()?;
+
+    Ok(LogHandle {
+        default_level,
+        handle,
+    })
+}
@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+/// Where a record was copied to in the dead-letter topic
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeadLetterResponse {
+    pub topic: String,
+    pub partition_id: u64,
+    pub offset: u64,
+}
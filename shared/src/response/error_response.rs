@@ -4,4 +4,85 @@ use serde::{Deserialize, Serialize};
 pub struct ErrorResponse {
     pub error: String,
     pub status: u16,
+    #[serde(default)]
+    pub code: ErrorCode,
+    /// How long to wait before retrying a throttled request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
+}
+
+/// Stable machine-readable identifier of an error, clients should match on this instead of the
+/// error message
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorCode {
+    Io,
+    SegmentFull,
+    PartitionNotFound,
+    InvalidLogFilename,
+    OffsetOutOfRange,
+    TopicNotFound,
+    MaxTopicIdReached,
+    TopicIdInUse,
+    TopicNameInUse,
+    InternalTopicName,
+    ReservedTopicName,
+    Encoding,
+    FetchTimeout,
+    Internal,
+    InvalidTopicName,
+    Unauthorized,
+    AdminRequired,
+    InvalidTokenName,
+    TokenNameInUse,
+    TokenNotFound,
+    AclDenied,
+    AclNotFound,
+    InvalidPrincipal,
+    RecordTooLarge,
+    InvalidTopicConfig,
+    PartitionCountDecrease,
+    TrashNotFound,
+    CorruptRecord,
+    InvalidArchive,
+    TieredStorage,
+    UnknownLogDir,
+    DiskFull,
+    MissingLogEntries,
+    TooManyHeaders,
+    RequestTooLarge,
+    Throttled,
+    InvalidQuota,
+    QuotaNotFound,
+    LeaseNotFound,
+    QueueDelayTooLong,
+    TooManyMessages,
+    InvalidReservedHeader,
+    OffsetNotOnSegmentBoundary,
+    RecordNotFound,
+    /// Returned for codes unknown to this version, or responses without a code
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ErrorCode, ErrorResponse};
+
+    #[test]
+    fn deserialize_known_code() {
+        let response: ErrorResponse =
+            serde_json::from_str(r#"{"error":"","status":400,"code":"TopicNameInUse"}"#).unwrap();
+        assert_eq!(response.code, ErrorCode::TopicNameInUse);
+    }
+
+    #[test]
+    fn deserialize_unknown_or_missing_code() {
+        let response: ErrorResponse =
+            serde_json::from_str(r#"{"error":"","status":400,"code":"SomethingNew"}"#).unwrap();
+        assert_eq!(response.code, ErrorCode::Unknown);
+
+        let response: ErrorResponse = serde_json::from_str(r#"{"error":"","status":400}"#).unwrap();
+        assert_eq!(response.code, ErrorCode::Unknown);
+    }
 }
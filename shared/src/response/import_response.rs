@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+use crate::state::topic_state::TopicState;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportResponse {
+    pub topic: TopicState,
+    pub records: u64,
+}
@@ -1,3 +1,8 @@
+pub mod dead_letter_response;
 pub mod error_response;
+pub mod import_response;
 pub mod produce_response;
+pub mod receive_response;
 pub mod record_response;
+pub mod snapshot_response;
+pub mod token_response;
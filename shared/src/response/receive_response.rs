@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::{encoding::Encoding, lease::Lease, timestamp::Timestamp};
+
+use super::record_response::RecordResponse;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceiveResponse {
+    pub encoding: Encoding,
+    pub messages: Vec<QueueMessageResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QueueMessageResponse {
+    pub lease: Lease,
+    /// How often the message has been received, including this delivery
+    pub deliveries: u64,
+    /// When the message becomes visible to other workers unless it is acked
+    pub visible_at: Timestamp,
+    pub record: RecordResponse,
+}
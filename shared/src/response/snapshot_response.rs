@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotResponse {
+    /// Offset of the last metadata entry included in the snapshot, empty when there is no metadata
+    pub offset: Option<u64>,
+}
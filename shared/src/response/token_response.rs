@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateTokenResponse {
+    pub name: String,
+    pub admin: bool,
+    /// The plaintext token, this is only returned once and can not be retrieved again
+    pub token: String,
+}
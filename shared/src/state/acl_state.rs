@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::acl::{AclOperation, AclPattern};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AclState {
+    pub acl_id: u64,
+    pub principal: String,
+    pub pattern: AclPattern,
+    pub operations: Vec<AclOperation>,
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::timestamp::Timestamp;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct BackupState {
+    pub backup_id: String,
+    /// Data directory of the backup, start the server with this `path` to restore it
+    pub path: String,
+    /// Copies of the log directories, start the server with these `log_dirs` to restore it
+    #[serde(default)]
+    pub log_dirs: Vec<String>,
+    pub created_at: Timestamp,
+    /// Offset of the latest metadata snapshot included in the backup
+    pub metadata_snapshot_offset: Option<u64>,
+    pub partitions: Vec<BackupPartitionState>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct BackupPartitionState {
+    pub topic_id: u64,
+    pub partition_id: u64,
+    /// Log-end offset when the backup was taken, records from this offset on are not included
+    pub end_offset: u64,
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct LogDirState {
+    pub path: String,
+    pub partitions: usize,
+    /// Bytes used by the segments of the partitions in this directory
+    pub disk_usage: u64,
+    /// Bytes free on the filesystem of this directory as of the last check
+    #[serde(default)]
+    pub free_bytes: u64,
+}
@@ -1,2 +1,10 @@
+pub mod acl_state;
+pub mod backup_state;
+pub mod log_dir_state;
 pub mod partition_state;
+pub mod queue_state;
+pub mod quota_state;
+pub mod segment_state;
+pub mod token_state;
 pub mod topic_state;
+pub mod trash_state;
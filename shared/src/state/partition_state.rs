@@ -5,4 +5,7 @@ pub struct PartitionState {
     pub partition_id: u64,
     pub current_offset: u64,
     pub segment_count: usize,
+    /// The log directory the partition is placed in
+    #[serde(default)]
+    pub log_dir: String,
 }
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct QueueState {
+    pub topic_id: u64,
+    pub name: String,
+    /// Per partition, the offset after the last message received from it
+    pub cursors: Vec<u64>,
+    /// Received messages that are invisible until their visibility timeout runs out
+    pub in_flight: u64,
+    /// Received messages that were not acked in time or were nacked, and wait for a redelivery
+    pub visible: u64,
+}
@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::quota::{Quota, QuotaEntity};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct QuotaState {
+    pub entity: QuotaEntity,
+    pub quota: Quota,
+}
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SegmentState {
+    pub start_offset: u64,
+    /// Offsets of the first and last record, empty segments have none
+    pub min_offset: Option<u64>,
+    pub max_offset: Option<u64>,
+    pub log_size: u64,
+    pub log_path: String,
+    pub index_path: String,
+    /// Full segments no longer accept appends, the next append rolls a new segment
+    pub is_full: bool,
+    /// Key of the log in tiered storage, unset while the log is on the local disk
+    #[serde(default)]
+    pub tiered_key: Option<String>,
+}
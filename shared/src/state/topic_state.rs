@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::data::topic_config::TopicConfig;
+
 use super::partition_state::PartitionState;
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -7,4 +9,6 @@ pub struct TopicState {
     pub name: String,
     pub topic_id: u64,
     pub partitions: Vec<PartitionState>,
+    #[serde(default)]
+    pub config: TopicConfig,
 }
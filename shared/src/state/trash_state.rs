@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::{timestamp::Timestamp, topic_config::TopicConfig};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TrashState {
+    pub trash_id: String,
+    pub topic_id: u64,
+    /// Name of the topic, unknown for directories that had no topic in the metadata
+    pub name: Option<String>,
+    pub partitions: u64,
+    pub config: TopicConfig,
+    pub deleted_at: Timestamp,
+}
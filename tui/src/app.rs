@@ -1,3 +1,4 @@
+use client::http_client::HttpClient;
 use ratatui::{
     crossterm::event::KeyCode,
     layout::{Constraint, Direction, Layout},
@@ -19,11 +20,11 @@ pub struct App {
 }
 
 impl App {
-    pub fn new(tx: Tx) -> Self {
+    pub fn new(tx: Tx, client: HttpClient) -> Self {
         Self {
             should_close: false,
-            topic_list: TopicList::new(tx.clone()),
-            record_list: RecordList::new(tx.clone()),
+            topic_list: TopicList::new(tx.clone(), client.clone()),
+            record_list: RecordList::new(tx.clone(), client),
             topics_active: true,
             prompt: None,
         }
@@ -8,7 +8,6 @@ use ratatui::{
 };
 use shared::{
     commands::fetch_command::{FetchCommand, FetchPartitionCommand, FetchTopicCommand},
-    consts::DEFAULT_PORT,
     data::{encoding::Encoding, identifier::Identifier, offset_selection::OffsetSelection},
     response::record_response::RecordResponse,
     state::topic_state::TopicState,
@@ -29,11 +28,12 @@ pub struct RecordList {
     timeout_ms: u64,
     min_bytes: usize,
     tx: Tx,
+    client: HttpClient,
     records: Vec<RecordResponse>,
 }
 
 impl RecordList {
-    pub fn new(tx: Tx) -> Self {
+    pub fn new(tx: Tx, client: HttpClient) -> Self {
         Self {
             topic: None,
             update_handle: None,
@@ -41,6 +41,7 @@ impl RecordList {
             min_bytes: 1,
             record_history_count: 10,
             tx,
+            client,
             records: vec![],
         }
     }
@@ -76,7 +77,7 @@ impl RecordList {
             })
             .collect();
 
-        let client = HttpClient::new(format!("http://127.0.0.1:{}", DEFAULT_PORT)).unwrap();
+        let client = self.client.clone();
         let timeout_ms = self.timeout_ms;
         let min_bytes = self.min_bytes;
         let tx = self.tx.clone();
@@ -135,10 +136,10 @@ impl Component for RecordList {
     fn event(&mut self, event: TuiEvent) -> Option<TuiEvent> {
         match event {
             TuiEvent::RemoveTopic(topic_id) => {
-                if self.topic.as_ref().is_some_and(|t| t.topic_id == topic_id) {
-                    if let Some(handle) = self.update_handle.take() {
-                        handle.abort();
-                    }
+                if self.topic.as_ref().is_some_and(|t| t.topic_id == topic_id)
+                    && let Some(handle) = self.update_handle.take()
+                {
+                    handle.abort();
                 }
             }
             TuiEvent::SelectTopic(topic) => self.select_topic(topic),
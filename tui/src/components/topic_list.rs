@@ -7,7 +7,7 @@ use ratatui::{
     style::{Modifier, Style, Stylize},
     widgets::{Block, BorderType, Borders, HighlightSpacing, List, ListItem, ListState},
 };
-use shared::{consts::DEFAULT_PORT, state::topic_state::TopicState};
+use shared::state::topic_state::TopicState;
 use tokio::{task::JoinHandle, time::sleep};
 
 use crate::{
@@ -21,17 +21,21 @@ pub struct TopicList {
     topics: BTreeMap<u64, TopicState>,
     list_state: ListState,
     tx: Tx,
+    client: HttpClient,
     refresh_task: JoinHandle<()>,
 }
 
 impl TopicList {
-    pub fn new(tx: Tx) -> Self {
+    pub fn new(tx: Tx, client: HttpClient) -> Self {
+        let refresh_client = client.clone();
+
         Self {
             topics: BTreeMap::new(),
             list_state: ListState::default().with_selected(Some(0)),
             tx: tx.clone(),
+            client,
             refresh_task: tokio::spawn(async move {
-                let client = HttpClient::new(format!("http://127.0.0.1:{}", DEFAULT_PORT)).unwrap();
+                let client = refresh_client;
 
                 loop {
                     let topics = client.get_topics().await.unwrap();
@@ -119,6 +123,7 @@ impl Component for TopicList {
                     let id = topic.topic_id;
 
                     let tx = self.tx.clone();
+                    let client = self.client.clone();
                     tokio::spawn(async move {
                         if Prompt::new("Delete topic")
                             .paragraph(format!("Are you sure you want to delete topic: {}", name))
@@ -129,9 +134,6 @@ impl Component for TopicList {
                             return;
                         }
 
-                        let client =
-                            HttpClient::new(format!("http://127.0.0.1:{}", DEFAULT_PORT)).unwrap();
-
                         if let Err(err) = client.delete_topic(&name).await {
                             Prompt::error("Delete topic failed", err.to_string()).show(tx);
                         } else {
@@ -141,6 +143,7 @@ impl Component for TopicList {
                 }
                 KeyCode::Char('a') => {
                     let tx = self.tx.clone();
+                    let client = self.client.clone();
                     tokio::spawn(async move {
                         let Ok(result) = Prompt::new("Create topic")
                             .title("Add new topic")
@@ -155,9 +158,6 @@ impl Component for TopicList {
                         let topic: String = result.get("Name").unwrap();
                         let partitions = result.get("Partitions").ok();
 
-                        let client =
-                            HttpClient::new(format!("http://127.0.0.1:{}", DEFAULT_PORT)).unwrap();
-
                         match client.create_topic(&topic, partitions).await {
                             Ok(topic) => tx.send(TuiEvent::AddTopic(topic)).unwrap(),
                             Err(err) => {
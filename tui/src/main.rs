@@ -10,6 +10,8 @@ use std::{io, process, time::Duration};
 
 use anyhow::Result;
 use app::App;
+use clap::Parser;
+use client::http_client::HttpClient;
 use component::Component;
 use ratatui::{
     Terminal,
@@ -21,9 +23,18 @@ use ratatui::{
     },
     prelude::CrosstermBackend,
 };
+use shared::consts::DEFAULT_PORT;
 use tokio::sync::mpsc;
 use tui_event::TuiEvent;
 
+#[derive(Parser, Debug)]
+#[command(name = "pigeon-tui", version, author, about = "Pigeon-rs TUI")]
+struct Cli {
+    /// Bearer token used to authenticate with the server
+    #[arg(long, env = "PIGEON_TOKEN")]
+    token: Option<String>,
+}
+
 pub fn initialize_panic_handler() {
     std::panic::set_hook(Box::new(|panic_info| {
         crossterm::execute!(
@@ -42,6 +53,12 @@ pub fn initialize_panic_handler() {
 #[tokio::main]
 // This is far from pretty, but it's mostly async wiring
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let mut client = HttpClient::new(format!("http://127.0.0.1:{}", DEFAULT_PORT))?;
+    if let Some(token) = cli.token {
+        client = client.with_token(token);
+    }
+
     enable_raw_mode()?;
     initialize_panic_handler();
     let mut stderr = io::stderr();
@@ -72,7 +89,7 @@ async fn main() -> Result<()> {
         }
     });
 
-    let mut app = App::new(tx);
+    let mut app = App::new(tx, client);
     while !app.should_close {
         terminal.draw(|f| app.render(f, f.area(), true))?;
 